@@ -461,6 +461,7 @@ fn testnet_genesis(
 		democracy: DemocracyConfig::default(),
 
 		machine: MachineConfig {
+			startup_machine_bindings: vec![],
 			startup_tapp_bindings: tapp_startup,
 			startup_owner: Some(dao_reserved.clone()),
 		},
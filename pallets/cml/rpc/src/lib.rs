@@ -1,7 +1,8 @@
 use cml_runtime_api::CmlApi as CmlRuntimeApi;
-use codec::{Codec};
+use codec::Codec;
 use jsonrpc_core::{Error, ErrorCode, Result};
 use jsonrpc_derive::rpc;
+use node_primitives::BlockNumber;
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
@@ -18,6 +19,63 @@ where
 {
 	#[rpc(name = "cml_userCmlList")]
 	fn user_cml_list(&self, who: AccountId, at: Option<BlockHash>) -> Result<Vec<u64>>;
+
+	/// Returns ids of CMLs leased to the given user.
+	#[rpc(name = "cml_userLeasedCmlList")]
+	fn user_leased_cml_list(&self, who: AccountId, at: Option<BlockHash>) -> Result<Vec<u64>>;
+
+	#[rpc(name = "cml_userCmlDefrostHeights")]
+	fn user_cml_defrost_heights(
+		&self,
+		who: AccountId,
+		at: Option<BlockHash>,
+	) -> Result<Vec<(u64, Option<BlockNumber>)>>;
+
+	#[rpc(name = "cml_userExpiringSeeds")]
+	fn user_expiring_seeds(
+		&self,
+		who: AccountId,
+		within: BlockNumber,
+		at: Option<BlockHash>,
+	) -> Result<Vec<(u64, BlockNumber)>>;
+
+	#[rpc(name = "cml_cmlInfo")]
+	fn cml_info(&self, cml_id: u64, at: Option<BlockHash>)
+		-> Result<Option<RpcCmlInfo<AccountId>>>;
+
+	/// Returns CMLs matching the filter ordered by id, `page` starts from 0.
+	#[rpc(name = "cml_cmlList")]
+	fn cml_list(
+		&self,
+		filter: RpcCmlFilter<AccountId>,
+		page: u32,
+		page_size: u32,
+		at: Option<BlockHash>,
+	) -> Result<Vec<RpcCmlInfo<AccountId>>>;
+
+	/// Returns counts of all CMLs grouped by type and by lifecycle state.
+	#[rpc(name = "cml_cmlStats")]
+	fn cml_stats(&self, at: Option<BlockHash>) -> Result<RpcCmlStats>;
+
+	/// Returns effects of all CML traits.
+	#[rpc(name = "cml_traitEffects")]
+	fn trait_effects(&self, at: Option<BlockHash>) -> Result<Vec<RpcTraitEffect>>;
+
+	/// Returns all open listings of the market ordered by cml id.
+	#[rpc(name = "cml_openListings")]
+	fn open_listings(&self, at: Option<BlockHash>) -> Result<Vec<RpcListingInfo<AccountId>>>;
+
+	/// Returns cml id and amount of the highest bids placed by the given user.
+	#[rpc(name = "cml_userBids")]
+	fn user_bids(&self, who: AccountId, at: Option<BlockHash>) -> Result<Vec<(u64, Price)>>;
+
+	/// Returns disagreements between owners of CMLs and the user cml index, an empty list means
+	/// the index is consistent.
+	#[rpc(name = "cml_indexInconsistencies")]
+	fn index_inconsistencies(
+		&self,
+		at: Option<BlockHash>,
+	) -> Result<Vec<RpcIndexInconsistency<AccountId>>>;
 }
 
 pub struct CmlApiImpl<C, M> {
@@ -68,4 +126,154 @@ where
 			.map_err(runtime_error_into_rpc_err)?;
 		Ok(result)
 	}
+
+	fn user_leased_cml_list(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<u64>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.user_leased_cml_list(&at, who)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result)
+	}
+
+	fn user_cml_defrost_heights(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(u64, Option<BlockNumber>)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.user_cml_defrost_heights(&at, who)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result)
+	}
+
+	fn user_expiring_seeds(
+		&self,
+		who: AccountId,
+		within: BlockNumber,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(u64, BlockNumber)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.user_expiring_seeds(&at, who, within)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result)
+	}
+
+	fn cml_info(
+		&self,
+		cml_id: u64,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<RpcCmlInfo<AccountId>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.cml_info(&at, cml_id)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result.map(Into::into))
+	}
+
+	fn cml_list(
+		&self,
+		filter: RpcCmlFilter<AccountId>,
+		page: u32,
+		page_size: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<RpcCmlInfo<AccountId>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.cml_list(&at, filter.into(), page, page_size)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result.into_iter().map(Into::into).collect())
+	}
+
+	fn cml_stats(&self, at: Option<<Block as BlockT>::Hash>) -> Result<RpcCmlStats> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api.cml_stats(&at).map_err(runtime_error_into_rpc_err)?;
+		Ok(result.into())
+	}
+
+	fn trait_effects(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<RpcTraitEffect>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api.trait_effects(&at).map_err(runtime_error_into_rpc_err)?;
+		Ok(result.into_iter().map(Into::into).collect())
+	}
+
+	fn open_listings(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<RpcListingInfo<AccountId>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api.open_listings(&at).map_err(runtime_error_into_rpc_err)?;
+		Ok(result.into_iter().map(Into::into).collect())
+	}
+
+	fn user_bids(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(u64, Price)>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.user_bids(&at, who)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result
+			.into_iter()
+			.map(|(cml_id, amount)| (cml_id, Price(amount)))
+			.collect())
+	}
+
+	fn index_inconsistencies(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<RpcIndexInconsistency<AccountId>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.cml_index_inconsistencies(&at)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result.into_iter().map(Into::into).collect())
+	}
 }
@@ -1,6 +1,10 @@
-use node_primitives::Balance;
+use cml_runtime_api::{
+	ClassFlag, CmlFilter, CmlInfo, CmlState, CmlStats, CmlTrait, CmlType, ListingInfo, ListingKind,
+	TraitEffect,
+};
+use node_primitives::{Balance, BlockNumber};
 use serde::de::{self, Visitor};
-use serde::{Deserializer, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::Formatter;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,9 +48,345 @@ impl<'de> Visitor<'de> for PriceVisitor {
 	}
 }
 
+/// `CmlType` serialized as "A", "B" or "C".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcCmlType(pub CmlType);
+
+impl serde::Serialize for RpcCmlType {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(match self.0 {
+			CmlType::A => "A",
+			CmlType::B => "B",
+			CmlType::C => "C",
+		})
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for RpcCmlType {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_str(CmlTypeVisitor)
+	}
+}
+
+struct CmlTypeVisitor;
+
+impl<'de> Visitor<'de> for CmlTypeVisitor {
+	type Value = RpcCmlType;
+
+	fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+		formatter.write_str("one of \"A\", \"B\" or \"C\"")
+	}
+
+	fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		match s {
+			"A" => Ok(RpcCmlType(CmlType::A)),
+			"B" => Ok(RpcCmlType(CmlType::B)),
+			"C" => Ok(RpcCmlType(CmlType::C)),
+			_ => Err(E::custom(format!("unknown cml type: {}", s))),
+		}
+	}
+}
+
+/// `CmlState` serialized as "FrozenSeed", "FreshSeed", "Tree" or "Dead".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcCmlState(pub CmlState);
+
+impl serde::Serialize for RpcCmlState {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(match self.0 {
+			CmlState::FrozenSeed => "FrozenSeed",
+			CmlState::FreshSeed => "FreshSeed",
+			CmlState::Tree => "Tree",
+			CmlState::Dead => "Dead",
+		})
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for RpcCmlState {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_str(CmlStateVisitor)
+	}
+}
+
+struct CmlStateVisitor;
+
+impl<'de> Visitor<'de> for CmlStateVisitor {
+	type Value = RpcCmlState;
+
+	fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+		formatter.write_str("one of \"FrozenSeed\", \"FreshSeed\", \"Tree\" or \"Dead\"")
+	}
+
+	fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		match s {
+			"FrozenSeed" => Ok(RpcCmlState(CmlState::FrozenSeed)),
+			"FreshSeed" => Ok(RpcCmlState(CmlState::FreshSeed)),
+			"Tree" => Ok(RpcCmlState(CmlState::Tree)),
+			"Dead" => Ok(RpcCmlState(CmlState::Dead)),
+			_ => Err(E::custom(format!("unknown cml state: {}", s))),
+		}
+	}
+}
+
+/// `CmlTrait` serialized as "Genesis", "Team", "Investor", "Dao" or "Boosted".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RpcCmlTrait(pub CmlTrait);
+
+impl serde::Serialize for RpcCmlTrait {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_str(match self.0 {
+			CmlTrait::Genesis => "Genesis",
+			CmlTrait::Team => "Team",
+			CmlTrait::Investor => "Investor",
+			CmlTrait::Dao => "Dao",
+			CmlTrait::Boosted => "Boosted",
+		})
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for RpcCmlTrait {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_str(CmlTraitVisitor)
+	}
+}
+
+struct CmlTraitVisitor;
+
+impl<'de> Visitor<'de> for CmlTraitVisitor {
+	type Value = RpcCmlTrait;
+
+	fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+		formatter.write_str("one of \"Genesis\", \"Team\", \"Investor\", \"Dao\" or \"Boosted\"")
+	}
+
+	fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		match s {
+			"Genesis" => Ok(RpcCmlTrait(CmlTrait::Genesis)),
+			"Team" => Ok(RpcCmlTrait(CmlTrait::Team)),
+			"Investor" => Ok(RpcCmlTrait(CmlTrait::Investor)),
+			"Dao" => Ok(RpcCmlTrait(CmlTrait::Dao)),
+			"Boosted" => Ok(RpcCmlTrait(CmlTrait::Boosted)),
+			_ => Err(E::custom(format!("unknown cml trait: {}", s))),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcCmlInfo<AccountId> {
+	pub id: u64,
+	pub cml_type: RpcCmlType,
+	pub owner: AccountId,
+	pub lessee: Option<AccountId>,
+	pub lifespan: BlockNumber,
+	pub performance: u32,
+	pub class_flag: u64,
+	pub traits: Vec<RpcCmlTrait>,
+	pub state: RpcCmlState,
+	pub remaining_life: BlockNumber,
+}
+
+impl<AccountId> From<CmlInfo<AccountId, BlockNumber>> for RpcCmlInfo<AccountId> {
+	fn from(info: CmlInfo<AccountId, BlockNumber>) -> Self {
+		RpcCmlInfo {
+			id: info.id,
+			cml_type: RpcCmlType(info.cml_type),
+			owner: info.owner,
+			lessee: info.lessee,
+			lifespan: info.lifespan,
+			performance: info.performance,
+			class_flag: info.class_flag.bits(),
+			traits: info
+				.class_flag
+				.traits()
+				.into_iter()
+				.map(RpcCmlTrait)
+				.collect(),
+			state: RpcCmlState(info.state),
+			remaining_life: info.remaining_life,
+		}
+	}
+}
+
+/// Filter of `cml_list`, omitted fields match all CMLs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcCmlFilter<AccountId> {
+	#[serde(default)]
+	pub cml_type: Option<RpcCmlType>,
+	#[serde(default)]
+	pub owner: Option<AccountId>,
+	#[serde(default)]
+	pub state: Option<RpcCmlState>,
+	/// CMLs should have all the given traits.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub traits: Option<Vec<RpcCmlTrait>>,
+}
+
+impl<AccountId> From<RpcCmlFilter<AccountId>> for CmlFilter<AccountId> {
+	fn from(filter: RpcCmlFilter<AccountId>) -> Self {
+		CmlFilter {
+			cml_type: filter.cml_type.map(|t| t.0),
+			owner: filter.owner,
+			state: filter.state.map(|s| s.0),
+			class_flag: filter.traits.map(|traits| {
+				traits
+					.into_iter()
+					.fold(ClassFlag::default(), |mut flag, t| {
+						flag.insert(t.0);
+						flag
+					})
+			}),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcCmlStats {
+	pub total: u64,
+	pub type_counts: Vec<(RpcCmlType, u64)>,
+	pub state_counts: Vec<(RpcCmlState, u64)>,
+	pub burned_counts: Vec<(RpcCmlType, u64)>,
+}
+
+impl From<CmlStats> for RpcCmlStats {
+	fn from(stats: CmlStats) -> Self {
+		RpcCmlStats {
+			total: stats.total,
+			type_counts: stats
+				.type_counts
+				.into_iter()
+				.map(|(t, count)| (RpcCmlType(t), count))
+				.collect(),
+			state_counts: stats
+				.state_counts
+				.into_iter()
+				.map(|(s, count)| (RpcCmlState(s), count))
+				.collect(),
+			burned_counts: stats
+				.burned_counts
+				.into_iter()
+				.map(|(t, count)| (RpcCmlType(t), count))
+				.collect(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcTraitEffect {
+	pub cml_trait: RpcCmlTrait,
+	/// Performance multiplier in percent, 100 means the performance is unchanged.
+	pub performance_multiplier: u32,
+	pub extra_staking_slots: u32,
+}
+
+impl From<(CmlTrait, TraitEffect)> for RpcTraitEffect {
+	fn from((cml_trait, effect): (CmlTrait, TraitEffect)) -> Self {
+		RpcTraitEffect {
+			cml_trait: RpcCmlTrait(cml_trait),
+			performance_multiplier: effect.performance_multiplier,
+			extra_staking_slots: effect.extra_staking_slots,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcListingInfo<AccountId> {
+	pub cml_id: u64,
+	pub seller: AccountId,
+	/// Price of fixed-price listings, or reserve price of auctions.
+	pub price: Price,
+	/// End height of auctions, `None` for fixed-price listings.
+	pub auction_end_at: Option<BlockNumber>,
+	pub highest_bidder: Option<AccountId>,
+	pub highest_bid: Option<Price>,
+	pub created_at: BlockNumber,
+}
+
+impl<AccountId> From<ListingInfo<AccountId, Balance, BlockNumber>> for RpcListingInfo<AccountId> {
+	fn from(info: ListingInfo<AccountId, Balance, BlockNumber>) -> Self {
+		let (price, auction_end_at) = match info.listing.kind {
+			ListingKind::FixedPrice(price) => (price, None),
+			ListingKind::Auction {
+				reserve_price,
+				end_at,
+			} => (reserve_price, Some(end_at)),
+		};
+		let (highest_bidder, highest_bid) = match info.highest_bid {
+			Some(bid) => (Some(bid.bidder), Some(Price(bid.amount))),
+			None => (None, None),
+		};
+		RpcListingInfo {
+			cml_id: info.cml_id,
+			seller: info.listing.seller,
+			price: Price(price),
+			auction_end_at,
+			highest_bidder,
+			highest_bid,
+			created_at: info.listing.created_at,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcIndexInconsistency<AccountId> {
+	/// One of "Missing", "Orphan" and "Mismatched".
+	pub kind: String,
+	/// Owner recorded in the user cml index, or owner of the CML if the index entry is missing.
+	pub owner: AccountId,
+	pub cml_id: u64,
+}
+
+impl<AccountId> From<IndexInconsistency<AccountId, u64>> for RpcIndexInconsistency<AccountId> {
+	fn from(inconsistency: IndexInconsistency<AccountId, u64>) -> Self {
+		let (kind, owner, cml_id) = match inconsistency {
+			IndexInconsistency::Missing(owner, cml_id) => ("Missing", owner, cml_id),
+			IndexInconsistency::Orphan(owner, cml_id) => ("Orphan", owner, cml_id),
+			IndexInconsistency::Mismatched(owner, cml_id) => ("Mismatched", owner, cml_id),
+		};
+		RpcIndexInconsistency {
+			kind: kind.to_string(),
+			owner,
+			cml_id,
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use crate::types::Price;
+	use crate::types::{Price, RpcCmlFilter, RpcCmlState, RpcCmlTrait, RpcCmlType};
+	use cml_runtime_api::{CmlFilter, CmlState, CmlTrait, CmlType};
 	use rmp_serde::{Deserializer, Serializer};
 	use serde::{Deserialize, Serialize};
 	use std::io::Cursor;
@@ -63,4 +403,38 @@ mod tests {
 
 		assert_eq!(p1, p2);
 	}
+
+	#[test]
+	fn cml_filter_serialize_deserialize_works() {
+		let filter: RpcCmlFilter<u64> =
+			serde_json::from_str(r#"{"cmlType":"B","state":"FreshSeed"}"#).unwrap();
+		assert_eq!(filter.cml_type, Some(RpcCmlType(CmlType::B)));
+		assert_eq!(filter.owner, None);
+		assert_eq!(filter.state, Some(RpcCmlState(CmlState::FreshSeed)));
+
+		let json = serde_json::to_string(&filter).unwrap();
+		assert_eq!(json, r#"{"cmlType":"B","owner":null,"state":"FreshSeed"}"#);
+		assert!(serde_json::from_str::<RpcCmlFilter<u64>>(r#"{"cmlType":"D"}"#).is_err());
+	}
+
+	#[test]
+	fn cml_filter_with_traits_works() {
+		let filter: RpcCmlFilter<u64> =
+			serde_json::from_str(r#"{"traits":["Genesis","Boosted"]}"#).unwrap();
+		assert_eq!(
+			filter.traits,
+			Some(vec![
+				RpcCmlTrait(CmlTrait::Genesis),
+				RpcCmlTrait(CmlTrait::Boosted)
+			])
+		);
+
+		let filter: CmlFilter<u64> = filter.into();
+		let class_flag = filter.class_flag.unwrap();
+		assert_eq!(
+			class_flag.traits(),
+			vec![CmlTrait::Genesis, CmlTrait::Boosted]
+		);
+		assert!(serde_json::from_str::<RpcCmlFilter<u64>>(r#"{"traits":["Unknown"]}"#).is_err());
+	}
 }
@@ -2,14 +2,54 @@
 #![allow(clippy::too_many_arguments)]
 #![allow(clippy::unnecessary_mut_passed)]
 
-use codec::{Codec};
+use codec::Codec;
+use node_primitives::{Balance, BlockNumber};
+pub use pallet_cml::{
+	Bid, ClassFlag, CmlFilter, CmlInfo, CmlState, CmlStats, CmlTrait, CmlType, IndexInconsistency,
+	Listing, ListingInfo, ListingKind, TraitEffect,
+};
 use sp_std::prelude::*;
 
 sp_api::decl_runtime_apis! {
+	#[api_version(2)]
 	pub trait CmlApi<AccountId>
 	where
 		AccountId: Codec,
 	{
 		fn user_cml_list(who: AccountId) -> Vec<u64>;
+
+		/// Returns ids of CMLs leased to the given user.
+		fn user_leased_cml_list(who: AccountId) -> Vec<u64>;
+
+		/// Returns id and defrost height of all CMLs of the given user.
+		fn user_cml_defrost_heights(who: AccountId) -> Vec<(u64, Option<BlockNumber>)>;
+
+		/// Returns id and expire height of fresh seeds of the given user that will expire within
+		/// `within` blocks.
+		fn user_expiring_seeds(who: AccountId, within: BlockNumber) -> Vec<(u64, BlockNumber)>;
+
+		fn cml_info(cml_id: u64) -> Option<CmlInfo<AccountId, BlockNumber>>;
+
+		/// Returns CMLs matching the filter ordered by id, `page` starts from 0.
+		fn cml_list(
+			filter: CmlFilter<AccountId>,
+			page: u32,
+			page_size: u32,
+		) -> Vec<CmlInfo<AccountId, BlockNumber>>;
+
+		/// Returns counts of all CMLs grouped by type and by lifecycle state.
+		fn cml_stats() -> CmlStats;
+
+		/// Returns effects of all CML traits.
+		fn trait_effects() -> Vec<(CmlTrait, TraitEffect)>;
+
+		/// Returns all open listings of the market ordered by cml id.
+		fn open_listings() -> Vec<ListingInfo<AccountId, Balance, BlockNumber>>;
+
+		/// Returns cml id and amount of the highest bids placed by the given user.
+		fn user_bids(who: AccountId) -> Vec<(u64, Balance)>;
+
+		/// Returns disagreements between owners of CMLs and the user cml index.
+		fn cml_index_inconsistencies() -> Vec<IndexInconsistency<AccountId, u64>>;
 	}
 }
@@ -1,4 +1,5 @@
 use super::*;
+use frame_support::{sp_io, storage::StoragePrefixedMap, ReversibleStorageHasher};
 
 impl<T: cml::Config> cml::Pallet<T> {
 	pub fn next_id() -> CmlId {
@@ -12,66 +13,873 @@ impl<T: cml::Config> cml::Pallet<T> {
 			*id
 		})
 	}
+
+	/// Operations reading CMLs are rejected while `CmlStore` is being migrated, since entries
+	/// not visited yet are still in the legacy encoding.
+	pub(crate) fn check_cml_store_migrated() -> DispatchResult {
+		ensure!(
+			!MigrationCursor::<T>::exists(),
+			Error::<T>::CmlStoreMigrating
+		);
+		Ok(())
+	}
+
+	pub(crate) fn check_batch_length(len: usize) -> DispatchResult {
+		ensure!(len > 0, Error::<T>::EmptyBatch);
+		ensure!(
+			len <= T::MaxBatchLength::get() as usize,
+			Error::<T>::BatchTooLong
+		);
+		Ok(())
+	}
+
+	/// Create a batch starting from the next cml id with the current generator settings, `who`
+	/// and `salt` are used to generate the random seed of the batch.
+	pub(crate) fn new_generation_batch(
+		who: &T::AccountId,
+		salt: Vec<u8>,
+		a_count: u64,
+		b_count: u64,
+		c_count: u64,
+		airdrop: bool,
+	) -> GenerationBatch {
+		let rand_value =
+			sp_core::U256::from(T::CommonUtils::generate_random(who.clone(), &salt).as_bytes());
+		GenerationBatch {
+			start_id: LastCmlId::<T>::get(),
+			seed: frame_support::Hashable::twox_256(&rand_value),
+			a_count,
+			b_count,
+			c_count,
+			start_height: frame_system::Pallet::<T>::block_number().saturated_into(),
+			generator_version: SeedGeneratorVersion::<T>::get(),
+			distributions: SeedDistributionConfig::<T>::get(),
+			airdrop,
+		}
+	}
+
+	/// Record the batch so seeds of it can be regenerated, returns id of the batch.
+	pub(crate) fn record_generation_batch(batch: GenerationBatch) -> BatchId {
+		let batch_id = LastBatchId::<T>::mutate(|id| {
+			*id = id.saturating_add(1);
+			*id
+		});
+		GenerationBatches::<T>::insert(batch_id, batch);
+		batch_id
+	}
+
+	/// Check if minting `amount` more CMLs of the given type exceeds the max supply.
+	pub(crate) fn check_max_supply(cml_type: CmlType, amount: u64) -> DispatchResult {
+		if let Some(max_supply) = MaxSupply::<T>::get(cml_type) {
+			ensure!(
+				MintedCount::<T>::get(cml_type).saturating_add(amount) <= max_supply,
+				Error::<T>::ExceedMaxSupply
+			);
+		}
+		Ok(())
+	}
+
+	pub(crate) fn is_staking_period_start(height: T::BlockNumber) -> bool {
+		height % T::StakingPeriodLength::get() == Zero::zero()
+	}
+
+	/// Check lifecycle related conditions that forbid the CML to change its owner, listed CMLs
+	/// can only be transferred by the market.
+	pub(crate) fn check_transferable(cml: &CML<T::AccountId, T::BlockNumber>) -> DispatchResult {
+		Self::check_tradable(cml)?;
+		ensure!(
+			!MarketListings::<T>::contains_key(cml.id()),
+			Error::<T>::CmlIsListed
+		);
+		Ok(())
+	}
+
+	/// Same as `check_transferable` except that listed CMLs are allowed.
+	pub(crate) fn check_tradable(cml: &CML<T::AccountId, T::BlockNumber>) -> DispatchResult {
+		let current_height = frame_system::Pallet::<T>::block_number();
+		ensure!(
+			!cml.is_frozen_seed() || cml.can_be_defrost(&current_height),
+			Error::<T>::CmlStillFrozen
+		);
+		ensure!(
+			!cml.seed_expired(&current_height, &T::SeedFreshDuration::get()),
+			Error::<T>::SeedHasExpired
+		);
+		ensure!(!cml.should_dead(&current_height), Error::<T>::CmlIsDead);
+		ensure!(!cml.is_mining(), Error::<T>::CmlIsMining);
+		ensure!(
+			!StakingCmls::<T>::contains_key(cml.id()),
+			Error::<T>::CmlIsStaking
+		);
+		ensure!(
+			!Leases::<T>::contains_key(cml.id()),
+			Error::<T>::CmlIsLeased
+		);
+		Ok(())
+	}
+
+	/// Only living trees not mining, staking or leased can be leased.
+	pub(crate) fn check_leasable(cml: &CML<T::AccountId, T::BlockNumber>) -> DispatchResult {
+		let current_height = frame_system::Pallet::<T>::block_number();
+		ensure!(!cml.should_dead(&current_height), Error::<T>::CmlIsDead);
+		ensure!(cml.is_tree(), Error::<T>::CmlIsNotTree);
+		ensure!(!cml.is_mining(), Error::<T>::CmlIsMining);
+		ensure!(
+			!StakingCmls::<T>::contains_key(cml.id()),
+			Error::<T>::CmlIsStaking
+		);
+		ensure!(
+			!Leases::<T>::contains_key(cml.id()),
+			Error::<T>::CmlIsLeased
+		);
+		ensure!(
+			!MarketListings::<T>::contains_key(cml.id()),
+			Error::<T>::CmlIsListed
+		);
+		Ok(())
+	}
+
+	/// Account controls mining of the CML, which is the lessee if the CML is leased.
+	pub fn cml_controller(cml: &CML<T::AccountId, T::BlockNumber>) -> T::AccountId {
+		Leases::<T>::get(cml.id())
+			.map(|lease| lease.lessee)
+			.unwrap_or_else(|| cml.owner().clone())
+	}
+
+	/// Return control of CMLs whose leases expire at `height` to their owners, mining of the
+	/// CMLs are stopped. Returns the consumed weight.
+	pub(crate) fn expire_leases(height: T::BlockNumber) -> Weight {
+		let cml_ids = LeaseExpirations::<T>::take(height);
+		let migrate_weight = crate::migrations::migrate_cmls::<T>(&cml_ids);
+		let mut reads: Weight = 1;
+		let mut writes: Weight = 1;
+		for cml_id in cml_ids {
+			reads += 2;
+			let lease = match Leases::<T>::get(cml_id) {
+				Some(lease) if lease.expire_at == height => lease,
+				_ => continue,
+			};
+			let owner = match CmlStore::<T>::get(cml_id) {
+				Some(cml) => cml.owner().clone(),
+				None => continue,
+			};
+
+			Self::stop_mining_inner(cml_id, &height);
+			Leases::<T>::remove(cml_id);
+			UserLeasedCmlStore::<T>::remove(&lease.lessee, cml_id);
+			writes += 3;
+			Self::deposit_event(Event::LeaseExpired(cml_id, owner, lease.lessee));
+		}
+		T::DbWeight::get()
+			.reads_writes(reads, writes)
+			.saturating_add(migrate_weight)
+	}
+
+	/// Defrost the CML if it can be defrosted at the given height.
+	pub(crate) fn try_defrost_cml(cml_id: CmlId, height: &T::BlockNumber) {
+		let defrosted = CmlStore::<T>::mutate(cml_id, |cml| match cml {
+			Some(cml) if cml.try_defrost(height) => {
+				Self::schedule_sweep(cml);
+				true
+			}
+			_ => false,
+		});
+		if defrosted {
+			Self::deposit_event(Event::CmlDefrosted(cml_id, height.clone()));
+		}
+	}
+
+	/// Block number the next lifecycle change of the CML is due at: defrost time of frozen seeds,
+	/// expire time of fresh seeds and dead time of trees. Frozen seeds without defrost time are
+	/// due right away.
+	fn next_deadline(cml: &CML<T::AccountId, T::BlockNumber>) -> Option<T::BlockNumber> {
+		match cml.status() {
+			CmlStatus::FrozenSeed => Some(cml.defrost_time().unwrap_or_default()),
+			CmlStatus::FreshSeed(_) => cml.fresh_expire_at(&T::SeedFreshDuration::get()),
+			CmlStatus::Tree => cml.dead_at(),
+		}
+	}
+
+	/// Put the CML into `CmlDeadlines` at the first staking period start after the current block
+	/// and not before its next deadline. Once `MaxSweepsPerBlock` CMLs are scheduled at a block
+	/// the CML spills into the following blocks. Seeds held by the NPC account are not scheduled,
+	/// they are scheduled once given away.
+	pub(crate) fn schedule_sweep(cml: &CML<T::AccountId, T::BlockNumber>) {
+		if !cml.is_tree() && Self::is_npc(cml.owner()) {
+			return;
+		}
+		let deadline = match Self::next_deadline(cml) {
+			Some(deadline) => deadline,
+			None => return,
+		};
+
+		let earliest = frame_system::Pallet::<T>::block_number().saturating_add(One::one());
+		let at = deadline.max(earliest);
+		let period = T::StakingPeriodLength::get();
+		let remainder = at % period;
+		let period_start = if remainder.is_zero() {
+			at
+		} else {
+			at.saturating_sub(remainder).saturating_add(period)
+		};
+		let max_sweeps = T::MaxSweepsPerBlock::get() as usize;
+		let mut at = period_start;
+		while CmlDeadlines::<T>::decode_len(&at).unwrap_or_default() >= max_sweeps {
+			at = at.saturating_add(One::one());
+		}
+		CmlDeadlines::<T>::append(at, cml.id());
+	}
+
+	/// Defrost frozen seeds, and retire dead trees and expired fresh seeds scheduled to be checked
+	/// at `height` from `CmlStore` and `UserCmlStore`. Returns the consumed weight.
+	///
+	/// Seeds held by the NPC account are not touched, so that the fresh duration of these seeds
+	/// counts from the time they are distributed.
+	pub(crate) fn sweep_cmls(height: T::BlockNumber) -> Weight {
+		let cml_ids = CmlDeadlines::<T>::take(height);
+		let mut reads: Weight = 2;
+		let mut writes: Weight = 1;
+
+		let fresh_duration = T::SeedFreshDuration::get();
+		for cml_id in cml_ids {
+			reads += 2;
+			let cml = match CmlStore::<T>::get(cml_id) {
+				Some(cml) => cml,
+				None => continue,
+			};
+			if !cml.is_tree() && Self::is_npc(cml.owner()) {
+				continue;
+			}
+
+			// CMLs not due any more have been scheduled again when their deadlines changed
+			if cml.can_be_defrost(&height) {
+				Self::try_defrost_cml(cml_id, &height);
+				writes += 2;
+			} else if cml.seed_expired(&height, &fresh_duration) {
+				if let Some(owner) = Self::retire_cml(cml_id) {
+					writes += 3;
+					Self::deposit_event(Event::SeedExpired(cml_id, owner, height));
+				}
+			} else if cml.should_dead(&height) {
+				if let Some(owner) = Self::retire_cml(cml_id) {
+					writes += 3;
+					Self::deposit_event(Event::CmlDead(cml_id, owner, height));
+				}
+			}
+		}
+
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	/// Current performance of the CML with performance multipliers of its traits applied.
+	pub(crate) fn effective_performance(
+		cml: &CML<T::AccountId, T::BlockNumber>,
+		height: &T::BlockNumber,
+		decay: bool,
+	) -> Performance {
+		cml.class_flag()
+			.traits()
+			.into_iter()
+			.fold(
+				cml.current_performance(height, decay) as u64,
+				|performance, cml_trait| {
+					performance.saturating_mul(
+						TraitEffects::<T>::get(cml_trait).performance_multiplier as u64,
+					) / 100
+				},
+			)
+			.saturated_into()
+	}
+
+	/// Count of staking slots the mining CML can have, which is `DefaultStakingSlots` plus
+	/// extra slots of its traits and limited by `StakingSlotsMaxLength`.
+	pub(crate) fn staking_slots_limit(cml: &CML<T::AccountId, T::BlockNumber>) -> u32 {
+		cml.class_flag()
+			.traits()
+			.into_iter()
+			.fold(T::DefaultStakingSlots::get(), |slots, cml_trait| {
+				slots.saturating_add(TraitEffects::<T>::get(cml_trait).extra_staking_slots)
+			})
+			.min(T::StakingSlotsMaxLength::get())
+	}
+
+	/// Start splitting mining rewards of the staking period starting at `height`. Rewards of the
+	/// period are skipped if rewards of the last period are still being paid, which happens only
+	/// if `MaxRewardsPerBlock` is too small for the mining trees. Returns the consumed weight.
+	pub(crate) fn start_reward_round(height: T::BlockNumber) -> Weight {
+		if OngoingRewardRound::<T>::exists() {
+			log::warn!(
+				"mining rewards of the last staking period are not all paid, skip rewards at {:?}",
+				height
+			);
+			return T::DbWeight::get().reads(1);
+		}
+		OngoingRewardRound::<T>::put(RewardRound {
+			height,
+			total_performance: 0,
+			cursor: Some(MiningCmlIds::<T>::final_prefix().to_vec()),
+		});
+		T::DbWeight::get().reads_writes(1, 1)
+	}
+
+	/// Continue the ongoing reward round: measure performance of mining trees at the period
+	/// start, and once all are measured split `MiningRewardPool` among them in proportion to
+	/// their performance, paying from `RewardTreasury` (or issuing new tokens if the treasury is
+	/// not set). At most `MaxRewardsPerBlock` trees are measured or paid in one block. Returns
+	/// the consumed weight.
+	pub(crate) fn distribute_mining_rewards() -> Weight {
+		let mut round = match OngoingRewardRound::<T>::get() {
+			Some(round) => round,
+			None => return T::DbWeight::get().reads(1),
+		};
+		let limit = T::MaxRewardsPerBlock::get();
+		let decay = T::PerformanceDecay::get();
+		let mut visited: u32 = 0;
+		let mut reads: Weight = 1;
+		let mut writes: Weight = 1;
+
+		// the total performance should be known before paying anyone
+		if let Some(mut cursor) = round.cursor.take() {
+			let prefix = MiningCmlIds::<T>::final_prefix();
+			while visited < limit {
+				let key = match sp_io::storage::next_key(&cursor) {
+					Some(key) if key.starts_with(&prefix) => key,
+					_ => break,
+				};
+				visited += 1;
+				// index entry, the CML, its lease, status of its machine and effects of its traits
+				reads += 4;
+				let measured = CmlId::decode(&mut Twox64Concat::reverse(&key[prefix.len()..]))
+					.ok()
+					.and_then(|cml_id| {
+						Self::measure_mining_cml(cml_id, &round.height, decay, &mut reads)
+							.map(|measured| (cml_id, measured))
+					});
+				if let Some((cml_id, (controller, performance))) = measured {
+					round.total_performance =
+						round.total_performance.saturating_add(performance as u64);
+					RoundPerformances::<T>::insert(cml_id, (controller, performance));
+					writes += 1;
+				}
+				cursor = key;
+			}
+			if visited >= limit {
+				round.cursor = Some(cursor);
+				OngoingRewardRound::<T>::put(round);
+				return T::DbWeight::get().reads_writes(reads, writes);
+			}
+		}
+
+		let pool = T::MiningRewardPool::get();
+		let treasury = RewardTreasury::<T>::get();
+		reads += 1;
+		let mut finished = true;
+		for (cml_id, (controller, performance)) in RoundPerformances::<T>::drain() {
+			reads += 1;
+			writes += 1;
+			let reward = Perbill::from_rational(performance as u64, round.total_performance) * pool;
+			let (paid_reads, paid_writes, slots) = Self::pay_mining_reward(
+				cml_id,
+				controller,
+				reward,
+				&round.height,
+				decay,
+				treasury.as_ref(),
+			);
+			reads += paid_reads;
+			writes += paid_writes;
+			visited = visited.saturating_add(1 + slots);
+			if visited >= limit {
+				finished = false;
+				break;
+			}
+		}
+
+		if finished || RoundPerformances::<T>::iter_keys().next().is_none() {
+			OngoingRewardRound::<T>::kill();
+		} else {
+			OngoingRewardRound::<T>::put(round);
+		}
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	/// Controller and effective performance of the mining tree at the given height, `None` if
+	/// the tree earns nothing.
+	fn measure_mining_cml(
+		cml_id: CmlId,
+		height: &T::BlockNumber,
+		decay: bool,
+		reads: &mut Weight,
+	) -> Option<(T::AccountId, Performance)> {
+		let cml = CmlStore::<T>::get(cml_id)?;
+		if !cml.is_mining() {
+			return None;
+		}
+		// machines missing heartbeats earn nothing until they are online again
+		let machine_id = cml.machine_id()?;
+		if T::MachineOperation::machine_status(machine_id) != Some(MachineStatus::Online) {
+			return None;
+		}
+		*reads += cml.class_flag().traits().len() as Weight;
+		let performance = Self::effective_performance(&cml, height, decay);
+		match performance {
+			0 => None,
+			// rewards of leased CMLs belong to the lessees
+			_ => Some((Self::cml_controller(&cml), performance)),
+		}
+	}
+
+	/// Pay the reward of the mining CML to its controller and its staking slots. Returns the
+	/// consumed reads and writes, and count of the staking slots.
+	fn pay_mining_reward(
+		cml_id: CmlId,
+		controller: T::AccountId,
+		reward: BalanceOf<T>,
+		height: &T::BlockNumber,
+		decay: bool,
+		treasury: Option<&T::AccountId>,
+	) -> (Weight, Weight, u32) {
+		if reward.is_zero() {
+			return (0, 0, 0);
+		}
+
+		// the mining CML itself weighs as much as a TEA slot, the rest is shared by staking
+		// slots in proportion to their staking values
+		let slots = StakingSlots::<T>::get(cml_id);
+		let slot_count = slots.len() as u32;
+		let reads = 1 + slot_count as Weight;
+		let mut writes: Weight = 0;
+		let values: Vec<BalanceOf<T>> = slots
+			.iter()
+			.map(|item| Self::staking_value(item, height, decay))
+			.collect();
+		let total_value = values.iter().fold(T::StakingPrice::get(), |total, value| {
+			total.saturating_add(*value)
+		});
+		let mut controller_reward = reward;
+		for (item, value) in slots.into_iter().zip(values) {
+			let share = Perbill::from_rational(value, total_value) * reward;
+			if share.is_zero() {
+				continue;
+			}
+			controller_reward = controller_reward.saturating_sub(share);
+			if Self::pay_reward(treasury, &item.owner, share) {
+				writes += 2;
+				Self::deposit_event(Event::StakingRewarded(
+					cml_id,
+					item.owner,
+					share,
+					height.clone(),
+				));
+			}
+		}
+
+		if !controller_reward.is_zero()
+			&& Self::pay_reward(treasury, &controller, controller_reward)
+		{
+			writes += 2;
+			Self::deposit_event(Event::MiningRewarded(
+				cml_id,
+				controller,
+				controller_reward,
+				height.clone(),
+			));
+		}
+		(reads, writes, slot_count)
+	}
+
+	/// Value of the staking item when sharing rewards of the mining CML. TEA slots are valued at
+	/// the reserved amount, CML slots at `StakingPrice` scaled by performance of the staked CML
+	/// against the base performance of its type, so a staked CML of standard performance is
+	/// worth a TEA slot.
+	fn staking_value(
+		item: &StakingItem<T::AccountId, BalanceOf<T>>,
+		height: &T::BlockNumber,
+		decay: bool,
+	) -> BalanceOf<T> {
+		let cml = match item.cml.and_then(|cml_id| CmlStore::<T>::get(cml_id)) {
+			Some(cml) => cml,
+			None => return item.amount.unwrap_or_default(),
+		};
+		let performance = Self::effective_performance(&cml, height, decay);
+		let base = crate::generator::base_performance(cml.cml_type()).max(1);
+		T::StakingPrice::get().saturating_mul(performance.into()) / base.into()
+	}
+
+	/// Pay reward from the treasury, or issue new tokens if the treasury is `None`. Returns
+	/// true if paid successfully.
+	fn pay_reward(
+		treasury: Option<&T::AccountId>,
+		who: &T::AccountId,
+		amount: BalanceOf<T>,
+	) -> bool {
+		match treasury {
+			Some(treasury) => {
+				if let Err(e) = T::CurrencyOperations::transfer(
+					treasury,
+					who,
+					amount,
+					ExistenceRequirement::KeepAlive,
+				) {
+					log::error!("pay reward from treasury failed: {:?}", e);
+					return false;
+				}
+			}
+			None => T::CurrencyOperations::deposit_creating(who, amount),
+		}
+		true
+	}
+
+	/// Move the staking item into pending unstakes, it will be released after `UnstakeCooldown`.
+	pub(crate) fn unstake(
+		cml_id: CmlId,
+		item: StakingItem<T::AccountId, BalanceOf<T>>,
+		height: &T::BlockNumber,
+	) {
+		let release_at = height.saturating_add(T::UnstakeCooldown::get());
+		let owner = item.owner.clone();
+		PendingUnstakes::<T>::append(release_at, item);
+		Self::deposit_event(Event::StopStaking(cml_id, owner, release_at));
+	}
+
+	/// Unstake all staking items of the mining CML.
+	pub(crate) fn unstake_all(cml_id: CmlId, height: &T::BlockNumber) {
+		StakingSlots::<T>::take(cml_id)
+			.into_iter()
+			.for_each(|item| Self::unstake(cml_id, item, height));
+	}
+
+	/// Release staked TEA and CMLs whose unstaking cooldown finishes at the given height. Returns
+	/// the consumed weight.
+	pub(crate) fn release_pending_unstakes(height: T::BlockNumber) -> Weight {
+		let items = PendingUnstakes::<T>::take(height);
+		if items.is_empty() {
+			return T::DbWeight::get().reads(1);
+		}
+
+		let count = items.len() as Weight;
+		for item in items {
+			if let Some(amount) = item.amount {
+				T::CurrencyOperations::unreserve(&item.owner, amount);
+			}
+			if let Some(cml_id) = item.cml {
+				StakingCmls::<T>::remove(cml_id);
+			}
+			Self::deposit_event(Event::StakingReleased(item.owner, item.amount, item.cml));
+		}
+		T::DbWeight::get().reads_writes(1, count + 1)
+	}
+
+	/// Stop mining of the CML, free the machine it was mining on and unstake all its staking
+	/// items.
+	pub(crate) fn stop_mining_inner(cml_id: CmlId, height: &T::BlockNumber) {
+		let machine_id = CmlStore::<T>::mutate(cml_id, |cml| match cml {
+			Some(cml) => cml.stop_mining(),
+			None => None,
+		});
+		MiningCmlIds::<T>::remove(cml_id);
+		if let Some(machine_id) = machine_id {
+			T::MachineOperation::release(&machine_id);
+		}
+		Self::unstake_all(cml_id, height);
+	}
+
+	/// Remove the CML from all related storages, stop mining and staking of the CML, returns
+	/// owner of the CML if it exists.
+	pub(crate) fn retire_cml(cml_id: CmlId) -> Option<T::AccountId> {
+		let height = frame_system::Pallet::<T>::block_number();
+		Self::stop_mining_inner(cml_id, &height);
+		if let Some(staking_to) = StakingCmls::<T>::take(cml_id) {
+			StakingSlots::<T>::mutate(staking_to, |slots| {
+				slots.retain(|item| item.cml != Some(cml_id));
+			});
+		}
+
+		let cml = CmlStore::<T>::take(cml_id)?;
+		UserCmlStore::<T>::remove(cml.owner(), cml_id);
+		CmlApprovals::<T>::remove(cml_id);
+		LeaseOffers::<T>::remove(cml_id);
+		Self::remove_listing(cml_id);
+		if let Some(lease) = Leases::<T>::take(cml_id) {
+			UserLeasedCmlStore::<T>::remove(&lease.lessee, cml_id);
+		}
+		if let (true, Some(schedule_type)) = (Self::is_npc(cml.owner()), cml.defrost_schedule()) {
+			LuckyDrawBox::<T>::mutate(cml.cml_type(), schedule_type, |draw_box| {
+				draw_box.retain(|id| *id != cml_id)
+			});
+		}
+		Some(cml.owner().clone())
+	}
+
+	/// Disagreements between owners in `CmlStore` and the `UserCmlStore` index.
+	pub fn cml_index_inconsistencies() -> Vec<IndexInconsistency<T::AccountId, CmlId>> {
+		pallet_utils::check_reverse_index(
+			CmlStore::<T>::iter().map(|(cml_id, cml)| (cml_id, cml.owner().clone())),
+			UserCmlStore::<T>::iter().map(|(owner, cml_id, _)| (owner, cml_id)),
+		)
+	}
+
+	/// Invariant check of the CML index, fix inconsistencies with `repair_cml_index`.
+	pub fn check_cml_index() -> Result<(), &'static str> {
+		ensure!(
+			Self::cml_index_inconsistencies().is_empty(),
+			"owners in cml store and user cml store disagree"
+		);
+		Ok(())
+	}
+
+	fn is_npc(who: &T::AccountId) -> bool {
+		NPCAccount::<T>::get()
+			.map(|npc| npc.eq(who))
+			.unwrap_or(false)
+	}
+
+	/// Amount the NPC account pays for burning a CML of the type, the NPC account doesn't buy
+	/// back CMLs of its own.
+	pub(crate) fn buy_back_amount(who: &T::AccountId, cml_type: CmlType) -> Option<BalanceOf<T>> {
+		if Self::is_npc(who) {
+			return None;
+		}
+		BuyBackPrices::<T>::get(cml_type).filter(|price| !price.is_zero())
+	}
+
+	pub(crate) fn coupon_amount(
+		who: &T::AccountId,
+		cml_type: CmlType,
+		schedule_type: DefrostScheduleType,
+	) -> u32 {
+		let coupon = match schedule_type {
+			DefrostScheduleType::Investor => InvestorCouponStore::<T>::get(who, cml_type),
+			DefrostScheduleType::Team => TeamCouponStore::<T>::get(who, cml_type),
+		};
+		coupon.map(|coupon| coupon.amount).unwrap_or_default()
+	}
+
+	pub(crate) fn add_coupon(
+		who: &T::AccountId,
+		cml_type: CmlType,
+		schedule_type: DefrostScheduleType,
+		amount: u32,
+	) {
+		let amount = Self::coupon_amount(who, cml_type, schedule_type).saturating_add(amount);
+		Self::set_coupon(who, cml_type, schedule_type, amount);
+	}
+
+	/// Caller should make sure coupon amount of the account is enough.
+	pub(crate) fn sub_coupon(
+		who: &T::AccountId,
+		cml_type: CmlType,
+		schedule_type: DefrostScheduleType,
+		amount: u32,
+	) {
+		let amount = Self::coupon_amount(who, cml_type, schedule_type).saturating_sub(amount);
+		Self::set_coupon(who, cml_type, schedule_type, amount);
+	}
+
+	/// Set coupon amount of the account, the coupon is removed if `amount` is zero.
+	fn set_coupon(
+		who: &T::AccountId,
+		cml_type: CmlType,
+		schedule_type: DefrostScheduleType,
+		amount: u32,
+	) {
+		let coupon = match amount {
+			0 => None,
+			_ => Some(Coupon { amount, cml_type }),
+		};
+		match schedule_type {
+			DefrostScheduleType::Investor => InvestorCouponStore::<T>::set(who, cml_type, coupon),
+			DefrostScheduleType::Team => TeamCouponStore::<T>::set(who, cml_type, coupon),
+		}
+	}
+
+	/// Count of seeds of each type drawn with `amount` coupons of the schedule type, coupons of
+	/// type A are used first, then B and C. Counts sum up to less than `amount` if coupons of
+	/// the account are not enough.
+	pub(crate) fn coupon_draws(
+		who: &T::AccountId,
+		schedule_type: DefrostScheduleType,
+		amount: u32,
+	) -> Vec<(CmlType, u32)> {
+		let mut remains = amount;
+		let mut draws = Vec::new();
+		for cml_type in [CmlType::A, CmlType::B, CmlType::C] {
+			let count = Self::coupon_amount(who, cml_type, schedule_type).min(remains);
+			if count > 0 {
+				draws.push((cml_type, count));
+				remains -= count;
+			}
+		}
+		draws
+	}
+
+	/// Lucky draw boxes of the CML type should be able to hold `amount` more seeds, since
+	/// generated seeds of either defrost schedule type are put into them.
+	pub(crate) fn check_lucky_draw_box_capacity(cml_type: CmlType, amount: u32) -> DispatchResult {
+		for schedule_type in [DefrostScheduleType::Investor, DefrostScheduleType::Team] {
+			let len = LuckyDrawBox::<T>::decode_len(cml_type, schedule_type).unwrap_or_default();
+			ensure!(
+				len.saturating_add(amount as usize) <= T::LuckyDrawBoxMaxLength::get() as usize,
+				Error::<T>::LuckyDrawBoxFull
+			);
+		}
+		Ok(())
+	}
+
+	/// Randomly take `amount` seeds out of the lucky draw box, caller should make sure seeds in
+	/// the box are enough.
+	pub(crate) fn lucky_draw(
+		who: &T::AccountId,
+		cml_type: CmlType,
+		schedule_type: DefrostScheduleType,
+		amount: u32,
+	) -> Vec<CmlId> {
+		let mut drawn_cmls = Vec::new();
+		LuckyDrawBox::<T>::mutate(cml_type, schedule_type, |draw_box| {
+			for i in 0..amount {
+				if draw_box.is_empty() {
+					break;
+				}
+
+				let mut salt = vec![cml_type as u8, schedule_type as u8];
+				salt.append(&mut i.to_le_bytes().to_vec());
+				let rand_value = sp_core::U256::from(
+					T::CommonUtils::generate_random(who.clone(), &salt).as_bytes(),
+				);
+				let index =
+					(rand_value % sp_core::U256::from(draw_box.len() as u64)).low_u64() as usize;
+				drawn_cmls.push(draw_box.swap_remove(index));
+			}
+		});
+		drawn_cmls
+	}
+
+	pub(crate) fn is_owner_or_operator(owner: &T::AccountId, who: &T::AccountId) -> bool {
+		owner.eq(who) || OperatorApprovals::<T>::contains_key(owner, who)
+	}
+
+	pub(crate) fn is_approved_or_owner(
+		who: &T::AccountId,
+		cml: &CML<T::AccountId, T::BlockNumber>,
+	) -> bool {
+		Self::is_owner_or_operator(cml.owner(), who)
+			|| CmlApprovals::<T>::get(cml.id())
+				.map(|approved| approved.eq(who))
+				.unwrap_or(false)
+	}
 }
 
 pub fn transfer_cml<T: Config>(cml_id: CmlId, old: &T::AccountId, new: &T::AccountId) {
+	move_cml::<T>(cml_id, old, new, true);
+}
+
+/// Give the seed drawn out of the lucky draw box to the drawer.
+pub(crate) fn transfer_drawn_cml<T: Config>(cml_id: CmlId, npc: &T::AccountId, new: &T::AccountId) {
+	move_cml::<T>(cml_id, npc, new, false);
+}
+
+/// Transfer the CML, `in_draw_box` tells whether seeds given away by the NPC account may still
+/// be in the lucky draw box.
+fn move_cml<T: Config>(cml_id: CmlId, old: &T::AccountId, new: &T::AccountId, in_draw_box: bool) {
+	let from_npc = NPCAccount::<T>::get()
+		.map(|npc| npc.eq(old))
+		.unwrap_or(false);
 	CmlStore::<T>::mutate(cml_id, |cml| {
 		if let Some(cml) = cml {
 			cml.set_owner(new.clone());
+
+			// seeds given away by the NPC account can't be drawn any more
+			if let (true, true, Some(schedule_type)) =
+				(from_npc, in_draw_box, cml.defrost_schedule())
+			{
+				LuckyDrawBox::<T>::mutate(cml.cml_type(), schedule_type, |draw_box| {
+					draw_box.retain(|id| *id != cml_id)
+				});
+			}
+			// seeds held by the NPC account start their lifecycle once given away, trees have
+			// been scheduled when planted
+			if from_npc && !cml.is_tree() {
+				Pallet::<T>::schedule_sweep(cml);
+			}
 		}
 	});
 	UserCmlStore::<T>::remove(old, cml_id);
 	UserCmlStore::<T>::insert(new, cml_id, ());
+	CmlApprovals::<T>::remove(cml_id);
+	LeaseOffers::<T>::remove(cml_id);
+	Pallet::<T>::remove_listing(cml_id);
 }
 
+/// Mint CMLs of the genesis seeds to `account`.
 pub fn init_from_genesis_seeds<T>(genesis_seeds: &GenesisSeeds, account: T::AccountId)
 where
 	T: Config,
 {
-	let a_cml_list = convert_genesis_seeds_to_cmls::<T::AccountId, T::BlockNumber>(
-		&genesis_seeds.a_seeds,
-		account.clone(),
-	);
-	let b_cml_list = convert_genesis_seeds_to_cmls::<T::AccountId, T::BlockNumber>(
-		&genesis_seeds.b_seeds,
-		account.clone(),
-	);
-	let c_cml_list = convert_genesis_seeds_to_cmls::<T::AccountId, T::BlockNumber>(
-		&genesis_seeds.c_seeds,
-		account,
-	);
-
-	a_cml_list
-		.into_iter()
-		.chain(b_cml_list.into_iter())
-		.chain(c_cml_list.into_iter())
-		.for_each(|cml| {
-			UserCmlStore::<T>::insert(NPCAccount::<T>::get().unwrap(), cml.id(), ());
-			CmlStore::<T>::insert(cml.id(), cml);
-		});
-
-	LastCmlId::<T>::mutate(|old_last| {
-		*old_last = old_last.saturating_add(
-			(genesis_seeds.a_seeds.len()
-				+ genesis_seeds.b_seeds.len()
-				+ genesis_seeds.c_seeds.len()) as CmlId,
-		);
-	});
+	for (cml_type, seeds) in [
+		(CmlType::A, &genesis_seeds.a_seeds),
+		(CmlType::B, &genesis_seeds.b_seeds),
+		(CmlType::C, &genesis_seeds.c_seeds),
+	] {
+		mint_seeds::<T>(cml_type, seeds, None, |_| account.clone());
+	}
 }
 
-pub fn convert_genesis_seeds_to_cmls<AccountId, BlockNumber>(
-	seeds: &Vec<Seed>,
-	account: AccountId,
-) -> Vec<CML<AccountId, BlockNumber>>
+/// Mint fresh seeds of the given type to recipients, the i-th seed goes to the i-th recipient.
+/// Defrost schedules of the seeds and the traits derived from them are dropped, as airdrop
+/// batches regenerate them.
+pub fn mint_fresh_seeds_to<T>(cml_type: CmlType, seeds: &[Seed], recipients: &[T::AccountId])
 where
-	AccountId: PartialEq + Clone + MaxEncodedLen,
-	BlockNumber: Default + AtLeast32BitUnsigned + Clone + MaxEncodedLen,
+	T: Config,
 {
-	let mut cml_list = Vec::new();
+	let count = seeds.len().min(recipients.len());
+	let seeds: Vec<Seed> = seeds[..count]
+		.iter()
+		.map(|seed| {
+			let mut seed = seed.clone();
+			seed.drop_defrost_schedule();
+			seed
+		})
+		.collect();
+	let current_height = frame_system::Pallet::<T>::block_number();
+	mint_seeds::<T>(cml_type, &seeds, Some(current_height), |i| {
+		recipients[i].clone()
+	});
+}
 
-	for seed in seeds {
-		let cml = CML::from_seed(seed.clone(), account.clone());
-		cml_list.push(cml);
+/// Mint CMLs of the seeds, `recipient_of` returns the recipient of the i-th seed. Seeds are
+/// defrosted at `fresh_at` if given. Seeds with defrost schedule minted to the NPC account are
+/// put into the lucky draw box.
+fn mint_seeds<T>(
+	cml_type: CmlType,
+	seeds: &[Seed],
+	fresh_at: Option<T::BlockNumber>,
+	recipient_of: impl Fn(usize) -> T::AccountId,
+) where
+	T: Config,
+{
+	for (i, seed) in seeds.iter().enumerate() {
+		let recipient = recipient_of(i);
+		let mut cml =
+			CML::<T::AccountId, T::BlockNumber>::from_seed(seed.clone(), recipient.clone());
+		if let Some(height) = fresh_at.as_ref() {
+			cml.defrost(height);
+		}
+		if let Some(schedule_type) = cml.defrost_schedule() {
+			if Pallet::<T>::is_npc(&recipient) {
+				LuckyDrawBox::<T>::mutate(cml.cml_type(), schedule_type, |draw_box| {
+					if draw_box.try_push(cml.id()).is_err() {
+						// should never happen since capacity of the box checked before
+						log::error!("lucky draw box of {:?} seeds is full", cml.cml_type());
+					}
+				});
+			}
+		}
+		Pallet::<T>::schedule_sweep(&cml);
+		UserCmlStore::<T>::insert(&recipient, cml.id(), ());
+		CmlStore::<T>::insert(cml.id(), cml);
 	}
-	cml_list
+
+	let count = seeds.len() as u64;
+	MintedCount::<T>::mutate(cml_type, |minted| *minted = minted.saturating_add(count));
+	LastCmlId::<T>::mutate(|old_last| *old_last = old_last.saturating_add(count));
 }
@@ -1,37 +1,51 @@
+use crate::generator::defrost::make_generate_defrost_time_fn;
 use crate::generator::lifespan::make_generate_lifespan_fn;
+use crate::generator::lifespan::make_generate_lifespan_fn_v2;
 use crate::generator::performance::make_generate_performance_fn;
+use crate::generator::performance::make_generate_performance_fn_v2;
 use crate::{
-	CmlType, DefrostScheduleType, GenesisSeeds, GENESIS_SEED_A_COUNT, GENESIS_SEED_B_COUNT,
-	GENESIS_SEED_C_COUNT,
+	CmlTrait, CmlType, DefrostScheduleType, GeneratorVersion, GenesisSeeds, SeedDistributions,
+	GENESIS_SEED_A_COUNT, GENESIS_SEED_B_COUNT, GENESIS_SEED_C_COUNT,
 };
 use log::info;
+use node_primitives::BlockNumber;
 
+mod defrost;
+mod distribution;
 mod lifespan;
 mod performance;
 
+pub use performance::base_performance;
+
 pub type WideSeed = [u8; 32];
 pub type ShortSeed = [u8; 16];
 
 /// Generate fixed number of seeds with random properties.
 pub fn init_genesis(seed: WideSeed) -> GenesisSeeds {
 	info!("init_genesis");
-	GenesisSeeds::generate(
+	let mut seeds = GenesisSeeds::generate(
 		0,
 		GENESIS_SEED_A_COUNT,
 		GENESIS_SEED_B_COUNT,
 		GENESIS_SEED_C_COUNT,
 		make_generate_lifespan_fn(seed),
 		make_generate_performance_fn(seed),
-	)
+		make_generate_defrost_time_fn(seed),
+	);
+	seeds.insert_trait(CmlTrait::Genesis);
+	seeds
 }
 
+/// Generate seeds with random properties, defrost time of seeds are counted from `start_height`.
 pub fn construct_seeds(
 	seq_id: u64,
 	seed: WideSeed,
 	a_count: u64,
 	b_count: u64,
 	c_count: u64,
+	start_height: BlockNumber,
 ) -> GenesisSeeds {
+	let gen_defrost_time = make_generate_defrost_time_fn(seed);
 	GenesisSeeds::generate(
 		seq_id,
 		a_count,
@@ -39,9 +53,39 @@ pub fn construct_seeds(
 		c_count,
 		make_generate_lifespan_fn(seed),
 		make_generate_performance_fn(seed),
+		move |schedule_type, seq_id, index, count| {
+			start_height.saturating_add(gen_defrost_time(schedule_type, seq_id, index, count))
+		},
 	)
 }
 
+/// Same as `construct_seeds` except that lifespan and performance are generated by
+/// `GeneratorVersion::V2` with the given distributions.
+pub fn construct_seeds_v2(
+	seq_id: u64,
+	seed: WideSeed,
+	a_count: u64,
+	b_count: u64,
+	c_count: u64,
+	start_height: BlockNumber,
+	distributions: SeedDistributions,
+) -> GenesisSeeds {
+	let gen_defrost_time = make_generate_defrost_time_fn(seed);
+	let mut seeds = GenesisSeeds::generate(
+		seq_id,
+		a_count,
+		b_count,
+		c_count,
+		make_generate_lifespan_fn_v2(seed, distributions),
+		make_generate_performance_fn_v2(seed, distributions),
+		move |schedule_type, seq_id, index, count| {
+			start_height.saturating_add(gen_defrost_time(schedule_type, seq_id, index, count))
+		},
+	);
+	seeds.set_generator_version(GeneratorVersion::V2);
+	seeds
+}
+
 /// generate an individual seed for each random generation.
 ///
 /// `seed` is the proto seed for generating individual seed
@@ -92,6 +136,87 @@ pub fn cml_type_sub_type_value(cml_type: CmlType) -> u8 {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::{Distribution, Seed};
+	use std::collections::BTreeSet;
+
+	fn test_distributions() -> SeedDistributions {
+		SeedDistributions {
+			a: Distribution::Uniform,
+			b: Distribution::Uniform,
+			c: Distribution::TruncatedNormal,
+		}
+	}
+
+	// returns true if any pair of seeds is ordered differently by lifespan and by performance
+	fn has_discordant_pair(seeds: &[Seed]) -> bool {
+		seeds.iter().any(|x| {
+			seeds.iter().any(|y| {
+				(x.lifespan < y.lifespan && x.performance > y.performance)
+					|| (x.lifespan > y.lifespan && x.performance < y.performance)
+			})
+		})
+	}
+
+	#[test]
+	fn v1_seeds_are_unchanged() {
+		let seeds = construct_seeds(10, [3; 32], 5, 5, 5, 0);
+		let gen_lifespan = make_generate_lifespan_fn([3; 32]);
+		let gen_performance = make_generate_performance_fn([3; 32]);
+		for seed in seeds
+			.a_seeds
+			.iter()
+			.chain(seeds.b_seeds.iter())
+			.chain(seeds.c_seeds.iter())
+		{
+			assert_eq!(seed.lifespan, gen_lifespan(seed.cml_type, seed.id));
+			assert_eq!(seed.performance, gen_performance(seed.cml_type, seed.id));
+			assert_eq!(seed.generator_version, GeneratorVersion::V1);
+		}
+		// lifespan and performance of v1 seeds come from the same random stream
+		assert!(!has_discordant_pair(&seeds.b_seeds));
+	}
+
+	#[test]
+	fn v2_lifespan_and_performance_are_independent() {
+		let seeds = construct_seeds_v2(0, [3; 32], 0, 50, 0, 0, test_distributions());
+		assert!(seeds
+			.b_seeds
+			.iter()
+			.all(|seed| seed.generator_version == GeneratorVersion::V2));
+		assert!(has_discordant_pair(&seeds.b_seeds));
+	}
+
+	#[test]
+	fn v2_seeds_have_higher_resolution() {
+		let count = 2000;
+		let v1_seeds = construct_seeds(0, [4; 32], count, 0, 0, 0);
+		let v2_seeds = construct_seeds_v2(0, [4; 32], count, 0, 0, 0, test_distributions());
+		let distinct_performances = |seeds: &GenesisSeeds| {
+			seeds
+				.a_seeds
+				.iter()
+				.map(|seed| seed.performance)
+				.collect::<BTreeSet<_>>()
+				.len()
+		};
+		assert!(distinct_performances(&v1_seeds) <= u8::MAX as usize + 1);
+		assert!(distinct_performances(&v2_seeds) > u8::MAX as usize + 1);
+	}
+
+	#[test]
+	fn v2_seeds_within_deviation_range() {
+		use crate::param::{BASE_LIFESPAN_C, BASE_PERFORMANCE_C, DEVIATION, PERFORMANCE_DEVIATION};
+
+		let seeds = construct_seeds_v2(0, [5; 32], 0, 0, 500, 0, test_distributions());
+		let max_lifespan_offset = BASE_LIFESPAN_C * DEVIATION as u32 / 200;
+		let max_performance_offset = BASE_PERFORMANCE_C * PERFORMANCE_DEVIATION as u32 / 200;
+		for seed in seeds.c_seeds {
+			assert!(seed.lifespan >= BASE_LIFESPAN_C - max_lifespan_offset);
+			assert!(seed.lifespan <= BASE_LIFESPAN_C + max_lifespan_offset);
+			assert!(seed.performance >= BASE_PERFORMANCE_C - max_performance_offset);
+			assert!(seed.performance <= BASE_PERFORMANCE_C + max_performance_offset);
+		}
+	}
 
 	#[test]
 	fn generate_individual_seed_works() {
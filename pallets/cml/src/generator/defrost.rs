@@ -0,0 +1,74 @@
+use super::WideSeed;
+use crate::generator::{defrost_schedule_sub_type_value, generate_individual_seed};
+use crate::param::{
+	DEFROST_RANDOM_BLOCK_RANGE, INVESTOR_S_DEFROST_SCHEDULE, TEAM_DEFROST_SCHEDULE,
+};
+use crate::DefrostScheduleType;
+use node_primitives::BlockNumber;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const DEFROST_CLASS_VALUE: u8 = 1;
+
+/// Returns a function to generate defrost time of seeds, params of the generated function are:
+/// 1. defrost schedule type
+/// 2. sequence id of the seed
+/// 3. index of the seed among seeds of the same defrost schedule in one batch
+/// 4. count of seeds of the same defrost schedule in one batch
+pub fn make_generate_defrost_time_fn(
+	seed: WideSeed,
+) -> impl Fn(DefrostScheduleType, u64, u64, u64) -> BlockNumber {
+	move |schedule_type: DefrostScheduleType, seq_id: u64, index: u64, count: u64| {
+		let schedule = match schedule_type {
+			DefrostScheduleType::Investor => INVESTOR_S_DEFROST_SCHEDULE,
+			DefrostScheduleType::Team => TEAM_DEFROST_SCHEDULE,
+		};
+		let standard_height = schedule.defrost_height(index, count);
+		// seeds defrost at genesis block should not be delayed
+		if standard_height == 0 {
+			return 0;
+		}
+
+		let mut rng: SmallRng = SmallRng::from_seed(generate_individual_seed(
+			seed,
+			DEFROST_CLASS_VALUE,
+			defrost_schedule_sub_type_value(schedule_type),
+			seq_id,
+		));
+		let r: u32 = rng.gen();
+		let offset = r % (2 * DEFROST_RANDOM_BLOCK_RANGE + 1);
+		standard_height
+			.saturating_add(offset)
+			.saturating_sub(DEFROST_RANDOM_BLOCK_RANGE)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::param::BLOCKS_IN_A_MONTH;
+
+	#[test]
+	fn defrost_time_within_random_range() {
+		let closure = make_generate_defrost_time_fn([1; 32]);
+		for i in 0..100 {
+			let height = closure(DefrostScheduleType::Team, i, 0, 100);
+			assert!(height >= 3 * BLOCKS_IN_A_MONTH - DEFROST_RANDOM_BLOCK_RANGE);
+			assert!(height <= 3 * BLOCKS_IN_A_MONTH + DEFROST_RANDOM_BLOCK_RANGE);
+		}
+
+		// investor seeds in cliff are defrost at genesis
+		assert_eq!(closure(DefrostScheduleType::Investor, 0, 0, 100), 0);
+	}
+
+	#[test]
+	fn defrost_time_is_deterministic() {
+		let closure1 = make_generate_defrost_time_fn([2; 32]);
+		let closure2 = make_generate_defrost_time_fn([2; 32]);
+		for i in 0..20 {
+			assert_eq!(
+				closure1(DefrostScheduleType::Team, i, i, 20),
+				closure2(DefrostScheduleType::Team, i, i, 20)
+			);
+		}
+	}
+}
@@ -0,0 +1,63 @@
+use crate::Distribution;
+use rand::{rngs::SmallRng, Rng};
+
+/// Count of uniform samples summed up to approximate a standard normal sample (Irwin-Hall).
+const IRWIN_HALL_SAMPLES: u32 = 12;
+/// Normal samples are truncated at this many standard deviations.
+const TRUNCATED_SIGMAS: f64 = 3.0;
+/// Max times to resample before falling back to the mean, avoids unbounded loops.
+const MAX_RESAMPLE_TIMES: u32 = 16;
+
+/// Returns a random ratio within range [-0.5, 0.5] drawn from the given distribution.
+pub fn sample_deviation_ratio(rng: &mut SmallRng, distribution: Distribution) -> f64 {
+	match distribution {
+		Distribution::Uniform => unit_sample(rng) - 0.5,
+		Distribution::TruncatedNormal => {
+			for _ in 0..MAX_RESAMPLE_TIMES {
+				let z = (0..IRWIN_HALL_SAMPLES)
+					.map(|_| unit_sample(rng))
+					.sum::<f64>() - IRWIN_HALL_SAMPLES as f64 / 2.0;
+				if z.abs() <= TRUNCATED_SIGMAS {
+					return z / TRUNCATED_SIGMAS / 2.0;
+				}
+			}
+			0.0
+		}
+	}
+}
+
+/// Returns a random value within range [0, 1] using full-width randomness.
+fn unit_sample(rng: &mut SmallRng) -> f64 {
+	let r: u64 = rng.gen();
+	r as f64 / u64::MAX as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::SeedableRng;
+
+	#[test]
+	fn deviation_ratio_within_range() {
+		let mut rng = SmallRng::from_seed([1; 16]);
+		for distribution in [Distribution::Uniform, Distribution::TruncatedNormal] {
+			for _ in 0..1000 {
+				let ratio = sample_deviation_ratio(&mut rng, distribution);
+				assert!((-0.5..=0.5).contains(&ratio));
+			}
+		}
+	}
+
+	#[test]
+	fn truncated_normal_concentrates_around_mean() {
+		let mut rng = SmallRng::from_seed([2; 16]);
+		let count = 1000;
+		let mut near_mean = |distribution| {
+			(0..count)
+				.filter(|_| sample_deviation_ratio(&mut rng, distribution).abs() < 0.25)
+				.count()
+		};
+		// about 50% of uniform samples and 95% of normal samples are within half of the range
+		assert!(near_mean(Distribution::TruncatedNormal) > near_mean(Distribution::Uniform));
+	}
+}
@@ -1,11 +1,15 @@
+use super::distribution::sample_deviation_ratio;
 use super::WideSeed;
 use crate::generator::{cml_type_sub_type_value, generate_individual_seed};
 use crate::param::{BASE_LIFESPAN_A, BASE_LIFESPAN_B, BASE_LIFESPAN_C, DEVIATION};
-use crate::CmlType;
+use crate::{CmlType, SeedDistributions};
 use node_primitives::BlockNumber;
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
+/// Class value of `GeneratorVersion::V1`, it is shared with performance generation so lifespan
+/// and performance of a V1 seed are correlated.
 const LIFESPAN_CLASS_VALUE: u8 = 2;
+const LIFESPAN_CLASS_VALUE_V2: u8 = 3;
 
 pub fn make_generate_lifespan_fn(seed: WideSeed) -> impl Fn(CmlType, u64) -> BlockNumber {
 	move |cml_type: CmlType, seq_id: u64| {
@@ -16,19 +20,41 @@ pub fn make_generate_lifespan_fn(seed: WideSeed) -> impl Fn(CmlType, u64) -> Blo
 			seq_id,
 		));
 		let r: u8 = rng.gen();
-		let base_lifespan = {
-			match cml_type {
-				CmlType::A => BASE_LIFESPAN_A,
-				CmlType::B => BASE_LIFESPAN_B,
-				CmlType::C => BASE_LIFESPAN_C,
-			}
-		};
+		let base_lifespan = base_lifespan(cml_type);
 		let random_offset =
 			(r as f64 / u8::MAX as f64 - 0.5) * DEVIATION as f64 / 100.0 * base_lifespan as f64;
 		(base_lifespan as f64 + random_offset as f64) as BlockNumber
 	}
 }
 
+/// `GeneratorVersion::V2` counterpart of `make_generate_lifespan_fn`, the deviation is drawn
+/// from the distribution of each cml type with full-width randomness.
+pub fn make_generate_lifespan_fn_v2(
+	seed: WideSeed,
+	distributions: SeedDistributions,
+) -> impl Fn(CmlType, u64) -> BlockNumber {
+	move |cml_type: CmlType, seq_id: u64| {
+		let mut rng: SmallRng = SmallRng::from_seed(generate_individual_seed(
+			seed,
+			LIFESPAN_CLASS_VALUE_V2,
+			cml_type_sub_type_value(cml_type),
+			seq_id,
+		));
+		let ratio = sample_deviation_ratio(&mut rng, distributions.of(cml_type));
+		let base_lifespan = base_lifespan(cml_type);
+		let random_offset = ratio * DEVIATION as f64 / 100.0 * base_lifespan as f64;
+		(base_lifespan as f64 + random_offset) as BlockNumber
+	}
+}
+
+fn base_lifespan(cml_type: CmlType) -> BlockNumber {
+	match cml_type {
+		CmlType::A => BASE_LIFESPAN_A,
+		CmlType::B => BASE_LIFESPAN_B,
+		CmlType::C => BASE_LIFESPAN_C,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
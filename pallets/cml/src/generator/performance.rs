@@ -1,12 +1,16 @@
+use super::distribution::sample_deviation_ratio;
 use super::WideSeed;
 use crate::generator::{cml_type_sub_type_value, generate_individual_seed};
 use crate::param::{
 	Performance, BASE_PERFORMANCE_A, BASE_PERFORMANCE_B, BASE_PERFORMANCE_C, PERFORMANCE_DEVIATION,
 };
-use crate::CmlType;
+use crate::{CmlType, SeedDistributions};
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 
+/// Class value of `GeneratorVersion::V1`, it is shared with lifespan generation so lifespan
+/// and performance of a V1 seed are correlated.
 const PERFORMANCE_CLASS_VALUE: u8 = 2;
+const PERFORMANCE_CLASS_VALUE_V2: u8 = 4;
 
 pub fn make_generate_performance_fn(seed: WideSeed) -> impl Fn(CmlType, u64) -> Performance {
 	move |cml_type: CmlType, seq_id: u64| {
@@ -17,19 +21,42 @@ pub fn make_generate_performance_fn(seed: WideSeed) -> impl Fn(CmlType, u64) ->
 			seq_id,
 		));
 		let r: u8 = rng.gen();
-		let base_performance = {
-			match cml_type {
-				CmlType::A => BASE_PERFORMANCE_A,
-				CmlType::B => BASE_PERFORMANCE_B,
-				CmlType::C => BASE_PERFORMANCE_C,
-			}
-		};
+		let base_performance = base_performance(cml_type);
 		let random_offset = (r as f64 / u8::MAX as f64 - 0.5) * PERFORMANCE_DEVIATION as f64
 			/ 100.0 * base_performance as f64;
 		(base_performance as f64 + random_offset as f64) as Performance
 	}
 }
 
+/// `GeneratorVersion::V2` counterpart of `make_generate_performance_fn`, the deviation is drawn
+/// from the distribution of each cml type with full-width randomness.
+pub fn make_generate_performance_fn_v2(
+	seed: WideSeed,
+	distributions: SeedDistributions,
+) -> impl Fn(CmlType, u64) -> Performance {
+	move |cml_type: CmlType, seq_id: u64| {
+		let mut rng: SmallRng = SmallRng::from_seed(generate_individual_seed(
+			seed,
+			PERFORMANCE_CLASS_VALUE_V2,
+			cml_type_sub_type_value(cml_type),
+			seq_id,
+		));
+		let ratio = sample_deviation_ratio(&mut rng, distributions.of(cml_type));
+		let base_performance = base_performance(cml_type);
+		let random_offset = ratio * PERFORMANCE_DEVIATION as f64 / 100.0 * base_performance as f64;
+		(base_performance as f64 + random_offset) as Performance
+	}
+}
+
+/// Standard performance of seeds of the type before random deviation applied.
+pub fn base_performance(cml_type: CmlType) -> Performance {
+	match cml_type {
+		CmlType::A => BASE_PERFORMANCE_A,
+		CmlType::B => BASE_PERFORMANCE_B,
+		CmlType::C => BASE_PERFORMANCE_C,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -9,17 +9,30 @@ mod tests;
 
 mod functions;
 pub mod generator;
+mod market;
+pub mod migrations;
 mod rpc;
 mod types;
+pub mod weights;
 
 pub use cml::*;
 pub use param::*;
 pub use types::*;
+pub use weights::WeightInfo;
 
-use frame_support::{pallet_prelude::*, traits::Currency};
+use frame_support::{
+	pallet_prelude::*,
+	traits::{Currency, ExistenceRequirement, StorageVersion},
+};
 use frame_system::pallet_prelude::*;
+use pallet_machine::{MachineOperation, MachineStatus};
 use pallet_utils::{CommonUtils, CurrencyOperations};
-use sp_runtime::traits::AtLeast32BitUnsigned;
+
+pub use pallet_utils::IndexInconsistency;
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, One, SaturatedConversion, Saturating, Zero},
+	Perbill,
+};
 use sp_std::convert::TryInto;
 use sp_std::prelude::*;
 
@@ -27,6 +40,9 @@ use sp_std::prelude::*;
 pub type BalanceOf<T> =
 	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
+/// The current storage version, see `migrations` for how older versions are migrated.
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 #[frame_support::pallet]
 pub mod cml {
 	use crate::functions::transfer_cml;
@@ -47,10 +63,70 @@ pub mod cml {
 			AccountId = Self::AccountId,
 			Balance = BalanceOf<Self>,
 		>;
+
+		/// Operations about machines that CMLs mine on.
+		type MachineOperation: MachineOperation<AccountId = Self::AccountId>;
+
+		/// Length of a staking period, dead CMLs are cleaned from the beginning of each period.
+		#[pallet::constant]
+		type StakingPeriodLength: Get<Self::BlockNumber>;
+
+		/// Fresh seeds that have not been planted within this duration will expire.
+		#[pallet::constant]
+		type SeedFreshDuration: Get<Self::BlockNumber>;
+
+		/// Total rewards split among mining CMLs every staking period.
+		#[pallet::constant]
+		type MiningRewardPool: Get<BalanceOf<Self>>;
+
+		/// Whether performance of mining trees decays with their age when splitting rewards.
+		#[pallet::constant]
+		type PerformanceDecay: Get<bool>;
+
+		/// Max count of staking slots of a mining CML.
+		#[pallet::constant]
+		type StakingSlotsMaxLength: Get<u32>;
+
+		/// Count of staking slots of a mining CML without traits adding extra slots, should not
+		/// be greater than `StakingSlotsMaxLength`.
+		#[pallet::constant]
+		type DefaultStakingSlots: Get<u32>;
+
+		/// Amount of TEA reserved for each slot staking with TEA.
+		#[pallet::constant]
+		type StakingPrice: Get<BalanceOf<Self>>;
+
+		/// Duration that staked TEA or CML stays locked after unstaking.
+		#[pallet::constant]
+		type UnstakeCooldown: Get<Self::BlockNumber>;
+
+		/// Max count of items of batch calls like `transfer_batch` and `airdrop`.
+		#[pallet::constant]
+		type MaxBatchLength: Get<u32>;
+
+		/// Max count of seeds in each lucky draw box, seeds with defrost schedule can't be
+		/// generated once the box is full.
+		#[pallet::constant]
+		type LuckyDrawBoxMaxLength: Get<u32>;
+
+		/// Max count of CMLs checked by the lifecycle sweep in one block, CMLs due beyond it are
+		/// checked in the following blocks.
+		#[pallet::constant]
+		type MaxSweepsPerBlock: Get<u32>;
+
+		/// Max count of mining trees measured or rewards paid in one block when splitting mining
+		/// rewards, the rest are handled in the following blocks. Rewards of a mining tree and
+		/// its staking slots are always paid in the same block.
+		#[pallet::constant]
+		type MaxRewardsPerBlock: Get<u32>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Used to allocate CML ID of new created DAO CML.
@@ -71,10 +147,201 @@ pub mod cml {
 	pub type UserCmlStore<T: Config> =
 		StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, CmlId, ()>;
 
+	/// Double map about lessee and CMLs leased to him.
+	#[pallet::storage]
+	#[pallet::getter(fn user_leased_cml_store)]
+	pub type UserLeasedCmlStore<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, CmlId, ()>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn npc_account)]
 	pub type NPCAccount<T: Config> = StorageValue<_, T::AccountId>;
 
+	/// Account approved to operate a single CML, cleared every time the CML changes its owner.
+	#[pallet::storage]
+	#[pallet::getter(fn cml_approvals)]
+	pub type CmlApprovals<T: Config> = StorageMap<_, Twox64Concat, CmlId, T::AccountId>;
+
+	/// Double map about owner and operators approved to operate all CMLs of the owner.
+	#[pallet::storage]
+	#[pallet::getter(fn operator_approvals)]
+	pub type OperatorApprovals<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, T::AccountId, ()>;
+
+	/// CMLs to be checked by the sweep at the given height, a CML is scheduled at a staking period
+	/// start whenever it gets a new deadline to defrost, expire or die. At most
+	/// `MaxSweepsPerBlock` CMLs are scheduled at a height.
+	#[pallet::storage]
+	#[pallet::getter(fn cml_deadlines)]
+	pub type CmlDeadlines<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<CmlId>, ValueQuery>;
+
+	/// Account that mining rewards are paid from, rewards are newly issued if it is `None`.
+	#[pallet::storage]
+	#[pallet::getter(fn reward_treasury)]
+	pub type RewardTreasury<T: Config> = StorageValue<_, T::AccountId>;
+
+	/// Index of CMLs that are mining, so that rewards are distributed without iterating
+	/// `CmlStore`.
+	#[pallet::storage]
+	#[pallet::getter(fn mining_cml_ids)]
+	pub type MiningCmlIds<T: Config> = StorageMap<_, Twox64Concat, CmlId, ()>;
+
+	/// Mining rewards of a staking period being split, `None` if rewards of the last period are
+	/// all paid.
+	#[pallet::storage]
+	#[pallet::getter(fn ongoing_reward_round)]
+	pub type OngoingRewardRound<T: Config> = StorageValue<_, RewardRound<T::BlockNumber>>;
+
+	/// Controllers and performance of mining trees measured in the ongoing reward round, entries
+	/// are removed once their rewards are paid.
+	#[pallet::storage]
+	pub type RoundPerformances<T: Config> =
+		StorageMap<_, Twox64Concat, CmlId, (T::AccountId, Performance)>;
+
+	/// Staking slots of mining CMLs.
+	#[pallet::storage]
+	#[pallet::getter(fn staking_slots)]
+	pub type StakingSlots<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		CmlId,
+		BoundedVec<StakingItem<T::AccountId, BalanceOf<T>>, T::StakingSlotsMaxLength>,
+		ValueQuery,
+	>;
+
+	/// Map from staked CML to the mining CML it stakes to, the entry is kept until the cooldown of
+	/// unstaking finishes.
+	#[pallet::storage]
+	#[pallet::getter(fn staking_cmls)]
+	pub type StakingCmls<T: Config> = StorageMap<_, Twox64Concat, CmlId, CmlId>;
+
+	/// Unstaked items that will be released at the given height.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_unstakes)]
+	pub type PendingUnstakes<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		Vec<StakingItem<T::AccountId, BalanceOf<T>>>,
+		ValueQuery,
+	>;
+
+	/// Lease offers waiting for lessees to accept.
+	#[pallet::storage]
+	#[pallet::getter(fn lease_offers)]
+	pub type LeaseOffers<T: Config> =
+		StorageMap<_, Twox64Concat, CmlId, LeaseOffer<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+
+	/// Started leases of CMLs.
+	#[pallet::storage]
+	#[pallet::getter(fn leases)]
+	pub type Leases<T: Config> =
+		StorageMap<_, Twox64Concat, CmlId, Lease<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+
+	/// CMLs whose leases expire at the given height.
+	#[pallet::storage]
+	#[pallet::getter(fn lease_expirations)]
+	pub type LeaseExpirations<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<CmlId>, ValueQuery>;
+
+	/// CMLs listed on the market.
+	#[pallet::storage]
+	#[pallet::getter(fn market_listings)]
+	pub type MarketListings<T: Config> =
+		StorageMap<_, Twox64Concat, CmlId, Listing<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+
+	/// Highest bids of auctions.
+	#[pallet::storage]
+	#[pallet::getter(fn auction_bids)]
+	pub type AuctionBids<T: Config> =
+		StorageMap<_, Twox64Concat, CmlId, Bid<T::AccountId, BalanceOf<T>>>;
+
+	/// Auctions that end at the given height.
+	#[pallet::storage]
+	#[pallet::getter(fn auction_endings)]
+	pub type AuctionEndings<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<CmlId>, ValueQuery>;
+
+	/// Coupons of investors that can be used to draw seeds following the investor defrost
+	/// schedule.
+	#[pallet::storage]
+	#[pallet::getter(fn investor_coupon_store)]
+	pub type InvestorCouponStore<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, CmlType, Coupon>;
+
+	/// Coupons of team members that can be used to draw seeds following the team defrost
+	/// schedule.
+	#[pallet::storage]
+	#[pallet::getter(fn team_coupon_store)]
+	pub type TeamCouponStore<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, CmlType, Coupon>;
+
+	/// Seeds held by the NPC account that can be drawn with coupons, grouped by CML type and
+	/// defrost schedule type.
+	#[pallet::storage]
+	#[pallet::getter(fn lucky_draw_box)]
+	pub type LuckyDrawBox<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		CmlType,
+		Twox64Concat,
+		DefrostScheduleType,
+		BoundedVec<CmlId, T::LuckyDrawBoxMaxLength>,
+		ValueQuery,
+	>;
+
+	/// Max count of CMLs that can be minted of each type, no limit if not set.
+	#[pallet::storage]
+	#[pallet::getter(fn max_supply)]
+	pub type MaxSupply<T: Config> = StorageMap<_, Twox64Concat, CmlType, u64>;
+
+	/// Count of CMLs minted of each type, including genesis seeds.
+	#[pallet::storage]
+	#[pallet::getter(fn minted_count)]
+	pub type MintedCount<T: Config> = StorageMap<_, Twox64Concat, CmlType, u64, ValueQuery>;
+
+	/// Count of CMLs burned by owners of each type.
+	#[pallet::storage]
+	#[pallet::getter(fn burned_count)]
+	pub type BurnedCount<T: Config> = StorageMap<_, Twox64Concat, CmlType, u64, ValueQuery>;
+
+	/// Price the NPC account pays for each burned CML of the type, nothing is paid if not set.
+	#[pallet::storage]
+	#[pallet::getter(fn buy_back_price)]
+	pub type BuyBackPrices<T: Config> = StorageMap<_, Twox64Concat, CmlType, BalanceOf<T>>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn last_batch_id)]
+	pub type LastBatchId<T: Config> = StorageValue<_, BatchId, ValueQuery>;
+
+	/// Records of seeds generated by the NPC account after genesis.
+	#[pallet::storage]
+	#[pallet::getter(fn generation_batches)]
+	pub type GenerationBatches<T: Config> = StorageMap<_, Twox64Concat, BatchId, GenerationBatch>;
+
+	/// Version of the generator used by `generate_cml`, defaults to `GeneratorVersion::V1` so
+	/// that existing chains keep generating the same seeds.
+	#[pallet::storage]
+	#[pallet::getter(fn generator_version)]
+	pub type SeedGeneratorVersion<T: Config> = StorageValue<_, GeneratorVersion, ValueQuery>;
+
+	/// Effects of CML traits, traits without effects defined are neutral.
+	#[pallet::storage]
+	#[pallet::getter(fn trait_effects)]
+	pub type TraitEffects<T: Config> =
+		StorageMap<_, Twox64Concat, CmlTrait, TraitEffect, ValueQuery>;
+
+	/// Distributions of seed attributes used by `GeneratorVersion::V2`.
+	#[pallet::storage]
+	#[pallet::getter(fn seed_distributions)]
+	pub type SeedDistributionConfig<T: Config> = StorageValue<_, SeedDistributions, ValueQuery>;
+
+	/// Raw key of the last `CmlStore` entry visited by the ongoing migration, `None` if there is
+	/// no ongoing migration.
+	#[pallet::storage]
+	pub type MigrationCursor<T: Config> = StorageValue<_, Vec<u8>>;
+
 	#[pallet::genesis_config]
 	pub struct GenesisConfig<T: Config> {
 		pub npc_account: Option<T::AccountId>,
@@ -96,18 +363,60 @@ pub mod cml {
 	#[pallet::genesis_build]
 	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
 		fn build(&self) {
+			assert!(
+				self.startup_cmls.is_empty() || self.startup_account.is_some(),
+				"startup cmls require a startup account"
+			);
 			NPCAccount::<T>::set(self.npc_account.clone());
-			if let Some(npc_account) = self.npc_account.as_ref() {
-				crate::functions::init_from_genesis_seeds::<T>(
-					&self.genesis_seeds,
-					npc_account.clone(),
+			let npc_account = match self.npc_account.as_ref() {
+				Some(npc_account) => npc_account,
+				None => {
+					assert!(
+						self.genesis_seeds.is_empty() && self.startup_cmls.is_empty(),
+						"genesis seeds and startup cmls require a npc account"
+					);
+					return;
+				}
+			};
+
+			if let Err(e) = self.genesis_seeds.validate(LastCmlId::<T>::get()) {
+				panic!("invalid genesis seeds: {}", e);
+			}
+			for seeds in [
+				&self.genesis_seeds.a_seeds,
+				&self.genesis_seeds.b_seeds,
+				&self.genesis_seeds.c_seeds,
+			] {
+				assert!(
+					seeds.len() <= T::LuckyDrawBoxMaxLength::get() as usize,
+					"lucky draw box can't hold the genesis seeds"
 				);
+			}
+			crate::functions::init_from_genesis_seeds::<T>(
+				&self.genesis_seeds,
+				npc_account.clone(),
+			);
+
+			if let Some(account) = self.startup_account.as_ref() {
+				// startup CMLs are bound with startup machines, so plant them directly
+				self.startup_cmls.iter().for_each(|cml_id| {
+					let cml = CmlStore::<T>::get(cml_id)
+						.unwrap_or_else(|| panic!("startup cml {} not exist", cml_id));
+					assert!(
+						cml.owner().eq(npc_account),
+						"startup cml {} is not owned by the npc account",
+						cml_id
+					);
 
-				if let Some(account) = self.startup_account.as_ref() {
-					self.startup_cmls.iter().for_each(|cml_id| {
-						transfer_cml::<T>(*cml_id, npc_account, account);
+					transfer_cml::<T>(*cml_id, npc_account, account);
+					CmlStore::<T>::mutate(cml_id, |cml| {
+						if let Some(cml) = cml {
+							cml.defrost(&Zero::zero());
+							cml.plant(&Zero::zero());
+							Pallet::<T>::schedule_sweep(cml);
+						}
 					});
-				}
+				});
 			}
 		}
 	}
@@ -120,6 +429,247 @@ pub mod cml {
 		/// 2. from account
 		/// 3. to account
 		CmlTransfered(CmlId, T::AccountId, T::AccountId),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. approved account
+		CmlApproved(CmlId, T::AccountId, T::AccountId),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. revoked account
+		CmlApprovalRevoked(CmlId, T::AccountId, T::AccountId),
+
+		/// Params:
+		/// 1. owner
+		/// 2. operator
+		/// 3. approved or not
+		ApprovalForAll(T::AccountId, T::AccountId, bool),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. at height
+		CmlDefrosted(CmlId, T::BlockNumber),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. at height
+		CmlPlanted(CmlId, T::AccountId, T::BlockNumber),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. at height
+		CmlDead(CmlId, T::AccountId, T::BlockNumber),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. at height
+		SeedExpired(CmlId, T::AccountId, T::BlockNumber),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. machine id
+		/// 3. owner
+		StartMining(CmlId, MachineId, T::AccountId),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. machine id
+		/// 3. owner
+		StopMining(CmlId, MachineId, T::AccountId),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. reward amount
+		/// 4. at height
+		MiningRewarded(CmlId, T::AccountId, BalanceOf<T>, T::BlockNumber),
+
+		/// Params:
+		/// 1. mining cml id
+		/// 2. staker
+		/// 3. staking cml id, `None` if staking with TEA
+		StartStaking(CmlId, T::AccountId, Option<CmlId>),
+
+		/// Params:
+		/// 1. mining cml id
+		/// 2. staker
+		/// 3. height the staking will be released at
+		StopStaking(CmlId, T::AccountId, T::BlockNumber),
+
+		/// Params:
+		/// 1. staker
+		/// 2. released TEA amount
+		/// 3. released cml id
+		StakingReleased(T::AccountId, Option<BalanceOf<T>>, Option<CmlId>),
+
+		/// Params:
+		/// 1. mining cml id
+		/// 2. staker
+		/// 3. reward amount
+		/// 4. at height
+		StakingRewarded(CmlId, T::AccountId, BalanceOf<T>, T::BlockNumber),
+
+		/// Params:
+		/// 1. account
+		/// 2. cml type
+		/// 3. defrost schedule type
+		/// 4. amount
+		CouponIssued(T::AccountId, CmlType, DefrostScheduleType, u32),
+
+		/// Params:
+		/// 1. from account
+		/// 2. to account
+		/// 3. cml type
+		/// 4. defrost schedule type
+		/// 5. amount
+		CouponTransfered(
+			T::AccountId,
+			T::AccountId,
+			CmlType,
+			DefrostScheduleType,
+			u32,
+		),
+
+		/// Params:
+		/// 1. account
+		/// 2. defrost schedule type
+		/// 3. drawn cml ids
+		CmlsDrawn(T::AccountId, DefrostScheduleType, Vec<CmlId>),
+
+		/// Params:
+		/// 1. batch id
+		/// 2. start cml id
+		/// 3. a count
+		/// 4. b count
+		/// 5. c count
+		CmlsGenerated(BatchId, CmlId, u64, u64, u64),
+
+		/// Seeds are minted to recipients in order, the i-th recipient gets the seed with cml id
+		/// `start cml id + i`.
+		///
+		/// Params:
+		/// 1. batch id
+		/// 2. cml type
+		/// 3. start cml id
+		/// 4. recipients
+		CmlsAirdropped(BatchId, CmlType, CmlId, Vec<T::AccountId>),
+
+		/// Params:
+		/// 1. cml type
+		/// 2. max supply, `None` means no limit
+		MaxSupplySet(CmlType, Option<u64>),
+
+		/// Params:
+		/// 1. count of fixed `UserCmlStore` entries
+		CmlIndexRepaired(u32),
+
+		/// Params:
+		/// 1. generator version
+		/// 2. distributions of seed attributes
+		SeedGeneratorSet(GeneratorVersion, SeedDistributions),
+
+		/// Params:
+		/// 1. cml trait
+		/// 2. effect of the trait
+		TraitEffectSet(CmlTrait, TraitEffect),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. class flag
+		ClassFlagSet(CmlId, ClassFlag),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner of the burned cml
+		/// 3. cml type
+		/// 4. buy-back amount paid by the NPC account
+		CmlBurned(CmlId, T::AccountId, CmlType, Option<BalanceOf<T>>),
+
+		/// Params:
+		/// 1. cml type
+		/// 2. buy-back price, `None` means no buy-back
+		BuyBackPriceSet(CmlType, Option<BalanceOf<T>>),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. lessee
+		/// 4. price
+		/// 5. duration
+		LeaseOffered(
+			CmlId,
+			T::AccountId,
+			T::AccountId,
+			BalanceOf<T>,
+			T::BlockNumber,
+		),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		LeaseOfferCanceled(CmlId, T::AccountId),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. lessee
+		/// 4. expire height
+		LeaseStarted(CmlId, T::AccountId, T::AccountId, T::BlockNumber),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. owner
+		/// 3. lessee
+		LeaseExpired(CmlId, T::AccountId, T::AccountId),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. seller
+		/// 3. price
+		CmlListed(CmlId, T::AccountId, BalanceOf<T>),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. seller
+		/// 3. reserve price
+		/// 4. end height
+		AuctionCreated(CmlId, T::AccountId, BalanceOf<T>, T::BlockNumber),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. seller
+		ListingCanceled(CmlId, T::AccountId),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. seller
+		/// 3. buyer
+		/// 4. price
+		CmlBought(CmlId, T::AccountId, T::AccountId, BalanceOf<T>),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. bidder
+		/// 3. bid amount
+		BidPlaced(CmlId, T::AccountId, BalanceOf<T>),
+
+		/// Params:
+		/// 1. cml id
+		/// 2. seller
+		/// 3. winner, `None` if the auction has no valid bid
+		/// 4. final price
+		AuctionEnded(
+			CmlId,
+			T::AccountId,
+			Option<T::AccountId>,
+			Option<BalanceOf<T>>,
+		),
 	}
 
 	#[pallet::error]
@@ -132,17 +682,185 @@ pub mod cml {
 		OnlyNPCAccountCanGenerateCml,
 		/// NPC account is empty
 		NpcAccountIsEmpty,
+		/// Owner of the CML can't be approved to operate it again.
+		ApproveToCurrentOwner,
+		/// The CML has no approved account.
+		CmlApprovalNotExist,
+		/// The user can't approve himself as an operator.
+		ApproveToCaller,
+		/// The user is neither owner, approved account nor operator of the CML.
+		NotApprovedToTransfer,
+		/// Only fresh seed can be planted.
+		CmlIsNotFreshSeed,
+		/// The CML has lived longer than its lifespan.
+		CmlIsDead,
+		/// Frozen seed can't be transferred or planted before its defrost time.
+		CmlStillFrozen,
+		/// Fresh seed has not been planted within the fresh duration.
+		SeedHasExpired,
+		/// Only tree can mine.
+		CmlIsNotTree,
+		/// The CML is already mining on a machine.
+		CmlIsMiningAlready,
+		/// The CML is not mining, or not mining on the given machine.
+		CmlIsNotMining,
+		/// Mining CML can't be transferred.
+		CmlIsMining,
+		/// The given machine has not been registered.
+		MachineNotExist,
+		/// Only owner of the machine can mine on it.
+		InvalidMachineOwner,
+		/// The machine is occupied by another CML.
+		MachineIsOccupied,
+		/// Staking slots of the mining CML are full.
+		StakingSlotsFull,
+		/// Free balance is not enough to stake.
+		InsufficientFreeBalance,
+		/// The CML is staking or waiting to be released from staking.
+		CmlIsStaking,
+		/// The given staking index is out of range.
+		InvalidStakingIndex,
+		/// Only owner of the staking item can unstake it.
+		InvalidStakingOwner,
+		/// The item at the given staking index is not the expected one, slots may have shifted.
+		StakingItemMismatch,
+		/// Coupon amount is not enough to transfer or draw.
+		NotEnoughCoupon,
+		/// The account has no coupon to draw seeds.
+		WithoutCoupon,
+		/// Seeds left in the lucky draw box are not enough to draw.
+		NotEnoughDrawSeeds,
+		/// The lucky draw box can't hold the generated seeds.
+		LuckyDrawBoxFull,
+		/// Coupon amount should be larger than zero.
+		InvalidCouponAmount,
+		/// Should generate at least one seed.
+		InvalidGenerateAmount,
+		/// Minted count would exceed max supply of the CML type.
+		ExceedMaxSupply,
+		/// Free balance of the NPC account is not enough to buy back the CML.
+		InsufficientBuyBackBalance,
+		/// The CML is leased, only the lessee can mine with it until the lease expires.
+		CmlIsLeased,
+		/// The CML has no lease offer.
+		LeaseOfferNotExist,
+		/// Price or duration of the lease offer differs from the accepted ones.
+		LeaseOfferMismatch,
+		/// Lessee should not be the owner, or the sender is not the lessee of the offer.
+		InvalidLessee,
+		/// Lease duration should be larger than zero.
+		InvalidLeaseDuration,
+		/// Free balance of the lessee is not enough to pay the lease price.
+		InsufficientLeaseBalance,
+		/// The CML is listed on the market.
+		CmlIsListed,
+		/// The CML is not listed on the market.
+		CmlNotListed,
+		/// The listing is not a fixed-price listing.
+		NotFixedPriceListing,
+		/// The listing is not an auction.
+		NotAuction,
+		/// Auction duration should be larger than zero.
+		InvalidAuctionDuration,
+		/// The auction has ended.
+		AuctionHasEnded,
+		/// Auction with bids can't be canceled.
+		AuctionHasBids,
+		/// Bid should not be lower than the reserve price and should be higher than the highest
+		/// bid.
+		BidTooLow,
+		/// Seller can't buy or bid on his own CML.
+		InvalidBuyer,
+		/// Free balance is not enough to buy the CML or to reserve the bid.
+		InsufficientBuyBalance,
+		/// Listing price exceeds the max price the buyer accepts.
+		PriceExceedsMax,
+		/// Batch calls should have at least one item.
+		EmptyBatch,
+		/// Count of items exceeds `MaxBatchLength`.
+		BatchTooLong,
+		/// The same CML appears more than once in the batch.
+		DuplicateCmlInBatch,
+		/// CMLs can't be operated until the migration of `CmlStore` completes.
+		CmlStoreMigrating,
+		/// Entries of the CML index exceed `max_items` given to the repair call.
+		IndexExceedsRepairBound,
+		/// Only online machines, whose heartbeats are up to date, can be mined on.
+		MachineNotOnline,
 	}
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			// queues below are keyed by the exact height, so they keep running during migration
+			// and translate the legacy CMLs they touch on demand
+			let mut weight =
+				crate::migrations::migrate_batch::<T>(crate::migrations::MIGRATION_BATCH_SIZE)
+					.saturating_add(Self::release_pending_unstakes(n))
+					.saturating_add(Self::expire_leases(n))
+					.saturating_add(Self::settle_auctions(n));
+			if Self::is_staking_period_start(n) {
+				weight = weight.saturating_add(Self::start_reward_round(n));
+			}
+			// rewards of the last period should be paid before dead trees being retired
+			weight
+				.saturating_add(Self::distribute_mining_rewards())
+				.saturating_add(Self::sweep_cmls(n))
+		}
+
 		fn on_finalize(_n: BlockNumberFor<T>) {}
+
+		fn integrity_test() {
+			assert!(
+				T::DefaultStakingSlots::get() <= T::StakingSlotsMaxLength::get(),
+				"default staking slots should not exceed max staking slots"
+			);
+			assert!(
+				!T::StakingPeriodLength::get().is_zero(),
+				"staking period length should not be zero"
+			);
+			assert!(
+				T::MaxBatchLength::get() > 0,
+				"max batch length should not be zero"
+			);
+			assert!(
+				T::MaxSweepsPerBlock::get() > 0,
+				"max sweeps per block should not be zero"
+			);
+			assert!(
+				T::MaxRewardsPerBlock::get() > 0,
+				"max rewards per block should not be zero"
+			);
+		}
+
+		fn on_runtime_upgrade() -> Weight {
+			crate::migrations::on_runtime_upgrade::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			crate::migrations::pre_upgrade::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			crate::migrations::post_upgrade::<T>()
+		}
 	}
 
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight(195_000_000)]
-		pub fn generate_cml(sender: OriginFor<T>, b_amount: u32) -> DispatchResult {
+		/// Generate seeds of each type and give them to the NPC account, the generation is
+		/// recorded in `GenerationBatches` so that it can be re-derived and audited.
+		#[pallet::weight(T::WeightInfo::generate_cml(
+			a_amount.saturating_add(*b_amount).saturating_add(*c_amount)
+		))]
+		pub fn generate_cml(
+			sender: OriginFor<T>,
+			a_amount: u32,
+			b_amount: u32,
+			c_amount: u32,
+		) -> DispatchResult {
 			let who = ensure_signed(sender)?;
 
 			pallet_utils::extrinsic_procedure(
@@ -156,67 +874,1243 @@ pub mod cml {
 						who.eq(&NPCAccount::<T>::get().unwrap()),
 						Error::<T>::OnlyNPCAccountCanGenerateCml
 					);
+					ensure!(
+						a_amount > 0 || b_amount > 0 || c_amount > 0,
+						Error::<T>::InvalidGenerateAmount
+					);
+					for (cml_type, amount) in [
+						(CmlType::A, a_amount),
+						(CmlType::B, b_amount),
+						(CmlType::C, c_amount),
+					] {
+						Self::check_max_supply(cml_type, amount as u64)?;
+						Self::check_lucky_draw_box_capacity(cml_type, amount)?;
+					}
 					Ok(())
 				},
 				|who| {
 					let mut salt = vec![];
+					salt.append(&mut a_amount.to_le_bytes().to_vec());
 					salt.append(&mut b_amount.to_le_bytes().to_vec());
+					salt.append(&mut c_amount.to_le_bytes().to_vec());
 
-					let rand_value = sp_core::U256::from(
-						T::CommonUtils::generate_random(who.clone(), &salt).as_bytes(),
-					);
-					let seeds = generator::construct_seeds(
-						LastCmlId::<T>::get(),
-						frame_support::Hashable::twox_256(&rand_value),
-						0,
+					let batch = Self::new_generation_batch(
+						who,
+						salt,
+						a_amount as u64,
 						b_amount as u64,
-						0,
+						c_amount as u64,
+						false,
 					);
 					crate::functions::init_from_genesis_seeds::<T>(
-						&seeds,
+						&batch.regenerate(),
 						NPCAccount::<T>::get().unwrap(),
 					);
+
+					let start_id = batch.start_id;
+					let batch_id = Self::record_generation_batch(batch);
+					Self::deposit_event(Event::CmlsGenerated(
+						batch_id,
+						start_id,
+						a_amount as u64,
+						b_amount as u64,
+						c_amount as u64,
+					));
 				},
 			)
 		}
 
-		#[pallet::weight(195_000_000)]
-		pub fn transfer(
+		/// Mint fresh seeds of the given type straight to the recipients, one seed for each
+		/// recipient. Airdropped seeds have no defrost schedule. Only root or the NPC account can
+		/// airdrop.
+		#[pallet::weight(T::WeightInfo::airdrop(recipients.len() as u32))]
+		pub fn airdrop(
 			sender: OriginFor<T>,
-			cml_id: CmlId,
-			to_account: T::AccountId,
+			cml_type: CmlType,
+			recipients: BoundedVec<T::AccountId, T::MaxBatchLength>,
 		) -> DispatchResult {
-			let who = ensure_signed(sender)?;
+			let who = ensure_signed(sender.clone()).ok();
+			if who.is_none() {
+				ensure_root(sender)?;
+			}
 
 			pallet_utils::extrinsic_procedure(
 				&who,
 				|who| {
-					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
-					let cml_store = CmlStore::<T>::get(cml_id).unwrap();
-					ensure!(cml_store.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+					let npc = NPCAccount::<T>::get().ok_or(Error::<T>::NpcAccountIsEmpty)?;
+					if let Some(who) = who {
+						ensure!(who.eq(&npc), Error::<T>::OnlyNPCAccountCanGenerateCml);
+					}
+					Self::check_batch_length(recipients.len())?;
+					Self::check_max_supply(cml_type, recipients.len() as u64)
+				},
+				|_| {
+					let npc = NPCAccount::<T>::get().unwrap();
+					let count = recipients.len() as u64;
+					let (a_count, b_count, c_count) = match cml_type {
+						CmlType::A => (count, 0, 0),
+						CmlType::B => (0, count, 0),
+						CmlType::C => (0, 0, count),
+					};
+					let batch = Self::new_generation_batch(
+						&npc,
+						recipients.encode(),
+						a_count,
+						b_count,
+						c_count,
+						true,
+					);
+					let seeds = batch.regenerate();
+					let seeds = match cml_type {
+						CmlType::A => &seeds.a_seeds,
+						CmlType::B => &seeds.b_seeds,
+						CmlType::C => &seeds.c_seeds,
+					};
+					crate::functions::mint_fresh_seeds_to::<T>(cml_type, seeds, &recipients);
+
+					let start_id = batch.start_id;
+					let batch_id = Self::record_generation_batch(batch);
+					Self::deposit_event(Event::CmlsAirdropped(
+						batch_id,
+						cml_type,
+						start_id,
+						recipients.to_vec(),
+					));
+				},
+			)
+		}
+
+		/// Make `UserCmlStore` agree with owners in `CmlStore`, orphan and mismatched entries are
+		/// removed and missing entries are inserted. `max_items` bounds the total count of
+		/// entries in both maps and the weight is charged accordingly.
+		#[pallet::weight(
+			pallet_utils::repair_reverse_index_weight(T::DbWeight::get(), *max_items)
+				.saturating_add(10_000_000)
+		)]
+		pub fn repair_cml_index(sender: OriginFor<T>, max_items: u32) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&root,
+				|_| {
+					Self::check_cml_store_migrated()?;
+					ensure!(
+						pallet_utils::within_repair_bound(
+							CmlStore::<T>::iter_keys(),
+							UserCmlStore::<T>::iter_keys(),
+							max_items,
+						),
+						Error::<T>::IndexExceedsRepairBound
+					);
 					Ok(())
 				},
-				|who| {
-					transfer_cml::<T>(cml_id, who, &to_account);
+				|_| {
+					let fixed = pallet_utils::repair_reverse_index(
+						&Self::cml_index_inconsistencies(),
+						|owner, cml_id| UserCmlStore::<T>::remove(owner, cml_id),
+						|owner, cml_id| UserCmlStore::<T>::insert(owner, cml_id, ()),
+					);
+					Self::deposit_event(Event::CmlIndexRepaired(fixed));
+				},
+			)
+		}
 
-					Self::deposit_event(Event::CmlTransfered(cml_id, who.clone(), to_account));
+		/// Set max supply of the given CML type, `None` means no limit.
+		#[pallet::weight(195_000_000)]
+		pub fn set_max_supply(
+			sender: OriginFor<T>,
+			cml_type: CmlType,
+			max_supply: Option<u64>,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&root,
+				|_| Ok(()),
+				|_| {
+					MaxSupply::<T>::set(cml_type, max_supply);
+					Self::deposit_event(Event::MaxSupplySet(cml_type, max_supply));
 				},
 			)
 		}
 
+		/// Set the generator version and distributions used by `generate_cml`. Seeds generated
+		/// before are not affected.
 		#[pallet::weight(195_000_000)]
-		pub fn approve(
+		pub fn set_seed_generator(
 			sender: OriginFor<T>,
-			_cml_id: CmlId,
-			_proxy_account: T::AccountId,
+			version: GeneratorVersion,
+			distributions: SeedDistributions,
 		) -> DispatchResult {
-			let who = ensure_signed(sender)?;
+			let root = ensure_root(sender)?;
 
 			pallet_utils::extrinsic_procedure(
-				&who,
-				|_who| Ok(()),
-				|_who| {
-					// todo complete me
+				&root,
+				|_| Ok(()),
+				|_| {
+					SeedGeneratorVersion::<T>::set(version);
+					SeedDistributionConfig::<T>::set(distributions);
+					Self::deposit_event(Event::SeedGeneratorSet(version, distributions));
+				},
+			)
+		}
+
+		/// Define the effect of CMLs with the given trait.
+		#[pallet::weight(195_000_000)]
+		pub fn set_trait_effect(
+			sender: OriginFor<T>,
+			cml_trait: CmlTrait,
+			effect: TraitEffect,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&root,
+				|_| Ok(()),
+				|_| {
+					TraitEffects::<T>::insert(cml_trait, effect);
+					Self::deposit_event(Event::TraitEffectSet(cml_trait, effect));
+				},
+			)
+		}
+
+		/// Replace traits of the given CML, used to grant traits such as `CmlTrait::Dao` and
+		/// `CmlTrait::Boosted`.
+		#[pallet::weight(195_000_000)]
+		pub fn set_class_flag(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			class_flag: ClassFlag,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&root,
+				|_| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					Ok(())
+				},
+				|_| {
+					CmlStore::<T>::mutate(cml_id, |cml| {
+						if let Some(cml) = cml {
+							cml.set_class_flag(class_flag);
+						}
+					});
+					Self::deposit_event(Event::ClassFlagSet(cml_id, class_flag));
+				},
+			)
+		}
+
+		#[pallet::weight(T::WeightInfo::transfer())]
+		pub fn transfer(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			to_account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml_store = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(cml_store.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+					Self::check_transferable(&cml_store)
+				},
+				|who| {
+					transfer_cml::<T>(cml_id, who, &to_account);
+
+					Self::deposit_event(Event::CmlTransfered(cml_id, who.clone(), to_account));
+				},
+			)
+		}
+
+		/// Transfer multiple CMLs of the sender in one call, nothing is transferred if any of the
+		/// CMLs is not owned by the sender or not transferable.
+		#[pallet::weight(T::WeightInfo::transfer_batch(items.len() as u32))]
+		pub fn transfer_batch(
+			sender: OriginFor<T>,
+			items: BoundedVec<(CmlId, T::AccountId), T::MaxBatchLength>,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					Self::check_batch_length(items.len())?;
+					let mut cml_ids: Vec<CmlId> = items.iter().map(|(cml_id, _)| *cml_id).collect();
+					cml_ids.sort_unstable();
+					cml_ids.dedup();
+					ensure!(
+						cml_ids.len() == items.len(),
+						Error::<T>::DuplicateCmlInBatch
+					);
+
+					for cml_id in cml_ids {
+						let cml = CmlStore::<T>::get(cml_id).ok_or(Error::<T>::NotFoundCML)?;
+						ensure!(cml.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+						Self::check_transferable(&cml)?;
+					}
+					Ok(())
+				},
+				|who| {
+					for (cml_id, to_account) in items.iter() {
+						transfer_cml::<T>(*cml_id, who, to_account);
+						Self::deposit_event(Event::CmlTransfered(
+							*cml_id,
+							who.clone(),
+							to_account.clone(),
+						));
+					}
+				},
+			)
+		}
+
+		/// Approve `proxy_account` to transfer the given CML, only owner or operators of the owner
+		/// are allowed to do this.
+		#[pallet::weight(T::WeightInfo::approve())]
+		pub fn approve(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			proxy_account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(
+						Self::is_owner_or_operator(cml.owner(), who),
+						Error::<T>::CMLOwnerInvalid
+					);
+					ensure!(
+						!cml.owner().eq(&proxy_account),
+						Error::<T>::ApproveToCurrentOwner
+					);
+					Ok(())
+				},
+				|_who| {
+					let owner = CmlStore::<T>::get(cml_id).unwrap().owner().clone();
+					CmlApprovals::<T>::insert(cml_id, proxy_account.clone());
+
+					Self::deposit_event(Event::CmlApproved(cml_id, owner, proxy_account));
+				},
+			)
+		}
+
+		#[pallet::weight(195_000_000)]
+		pub fn revoke_approval(sender: OriginFor<T>, cml_id: CmlId) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(
+						Self::is_owner_or_operator(cml.owner(), who),
+						Error::<T>::CMLOwnerInvalid
+					);
+					ensure!(
+						CmlApprovals::<T>::contains_key(cml_id),
+						Error::<T>::CmlApprovalNotExist
+					);
+					Ok(())
+				},
+				|_who| {
+					let owner = CmlStore::<T>::get(cml_id).unwrap().owner().clone();
+					if let Some(approved) = CmlApprovals::<T>::take(cml_id) {
+						Self::deposit_event(Event::CmlApprovalRevoked(cml_id, owner, approved));
+					}
+				},
+			)
+		}
+
+		/// Approve or remove `operator` as an operator of all CMLs the sender owns.
+		#[pallet::weight(195_000_000)]
+		pub fn set_approval_for_all(
+			sender: OriginFor<T>,
+			operator: T::AccountId,
+			approved: bool,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					ensure!(!who.eq(&operator), Error::<T>::ApproveToCaller);
+					Ok(())
+				},
+				|who| {
+					if approved {
+						OperatorApprovals::<T>::insert(who, &operator, ());
+					} else {
+						OperatorApprovals::<T>::remove(who, &operator);
+					}
+
+					Self::deposit_event(Event::ApprovalForAll(who.clone(), operator, approved));
+				},
+			)
+		}
+
+		/// Transfer CML of `from` account to `to_account`, sender should be the owner, the
+		/// approved account or an operator of the owner.
+		#[pallet::weight(195_000_000)]
+		pub fn transfer_from(
+			sender: OriginFor<T>,
+			from: T::AccountId,
+			cml_id: CmlId,
+			to_account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(cml.owner().eq(&from), Error::<T>::CMLOwnerInvalid);
+					ensure!(
+						Self::is_approved_or_owner(who, &cml),
+						Error::<T>::NotApprovedToTransfer
+					);
+					Self::check_transferable(&cml)
+				},
+				|_who| {
+					transfer_cml::<T>(cml_id, &from, &to_account);
+
+					Self::deposit_event(Event::CmlTransfered(cml_id, from.clone(), to_account));
+				},
+			)
+		}
+
+		/// Burn a CML of the sender, the NPC account pays the buy-back price of the CML type if
+		/// it is set. Mining or staking CMLs can't be burned.
+		#[pallet::weight(195_000_000)]
+		pub fn burn(sender: OriginFor<T>, cml_id: CmlId) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(cml.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+					ensure!(!cml.is_mining(), Error::<T>::CmlIsMining);
+					ensure!(
+						!StakingCmls::<T>::contains_key(cml_id),
+						Error::<T>::CmlIsStaking
+					);
+					ensure!(!Leases::<T>::contains_key(cml_id), Error::<T>::CmlIsLeased);
+					ensure!(
+						!MarketListings::<T>::contains_key(cml_id),
+						Error::<T>::CmlIsListed
+					);
+
+					if let Some(price) = Self::buy_back_amount(who, cml.cml_type()) {
+						let npc = NPCAccount::<T>::get().ok_or(Error::<T>::NpcAccountIsEmpty)?;
+						ensure!(
+							T::CurrencyOperations::free_balance(&npc)
+								>= price.saturating_add(T::CurrencyOperations::minimum_balance()),
+							Error::<T>::InsufficientBuyBackBalance
+						);
+					}
+					Ok(())
+				},
+				|who| {
+					let cml_type = CmlStore::<T>::get(cml_id).unwrap().cml_type();
+					let paid = Self::buy_back_amount(who, cml_type).and_then(|price| {
+						let npc = NPCAccount::<T>::get().unwrap();
+						if let Err(e) = T::CurrencyOperations::transfer(
+							&npc,
+							who,
+							price,
+							ExistenceRequirement::KeepAlive,
+						) {
+							log::error!("buy back cml {} failed: {:?}", cml_id, e);
+							return None;
+						}
+						Some(price)
+					});
+
+					Self::retire_cml(cml_id);
+					BurnedCount::<T>::mutate(cml_type, |count| *count = count.saturating_add(1));
+					Self::deposit_event(Event::CmlBurned(cml_id, who.clone(), cml_type, paid));
+				},
+			)
+		}
+
+		/// Set the price the NPC account pays for each burned CML of the type, `None` means no
+		/// buy-back.
+		#[pallet::weight(195_000_000)]
+		pub fn set_buy_back_price(
+			sender: OriginFor<T>,
+			cml_type: CmlType,
+			price: Option<BalanceOf<T>>,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&root,
+				|_| Ok(()),
+				|_| {
+					BuyBackPrices::<T>::set(cml_type, price);
+					Self::deposit_event(Event::BuyBackPriceSet(cml_type, price));
+				},
+			)
+		}
+
+		/// Plant a fresh seed and convert it into a tree, the tree will die when it has lived
+		/// longer than its lifespan.
+		#[pallet::weight(195_000_000)]
+		pub fn plant(sender: OriginFor<T>, cml_id: CmlId) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let mut cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(cml.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+					cml.try_defrost(&current_height);
+					ensure!(!cml.is_frozen_seed(), Error::<T>::CmlStillFrozen);
+					ensure!(cml.is_fresh_seed(), Error::<T>::CmlIsNotFreshSeed);
+					ensure!(
+						!cml.seed_expired(&current_height, &T::SeedFreshDuration::get()),
+						Error::<T>::SeedHasExpired
+					);
+					Ok(())
+				},
+				|who| {
+					Self::try_defrost_cml(cml_id, &current_height);
+					CmlStore::<T>::mutate(cml_id, |cml| {
+						if let Some(cml) = cml {
+							cml.plant(&current_height);
+							Self::schedule_sweep(cml);
+						}
+					});
+
+					Self::deposit_event(Event::CmlPlanted(cml_id, who.clone(), current_height));
+				},
+			)
+		}
+
+		/// Set the account mining rewards are paid from, rewards are newly issued if `treasury` is
+		/// `None`.
+		#[pallet::weight(195_000_000)]
+		pub fn set_reward_treasury(
+			sender: OriginFor<T>,
+			treasury: Option<T::AccountId>,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&root,
+				|_| Ok(()),
+				|_| RewardTreasury::<T>::set(treasury.clone()),
+			)
+		}
+
+		/// Start mining on the given machine, both the tree and the machine should belong to the
+		/// sender.
+		#[pallet::weight(195_000_000)]
+		pub fn start_mining(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			machine_id: MachineId,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(
+						Self::cml_controller(&cml).eq(who),
+						Error::<T>::CMLOwnerInvalid
+					);
+					ensure!(!cml.should_dead(&current_height), Error::<T>::CmlIsDead);
+					ensure!(cml.is_tree(), Error::<T>::CmlIsNotTree);
+					ensure!(!cml.is_mining(), Error::<T>::CmlIsMiningAlready);
+					ensure!(
+						!StakingCmls::<T>::contains_key(cml_id),
+						Error::<T>::CmlIsStaking
+					);
+					ensure!(
+						!MarketListings::<T>::contains_key(cml_id),
+						Error::<T>::CmlIsListed
+					);
+
+					let machine_owner = T::MachineOperation::machine_owner(&machine_id)
+						.ok_or(Error::<T>::MachineNotExist)?;
+					ensure!(machine_owner.eq(who), Error::<T>::InvalidMachineOwner);
+					ensure!(
+						T::MachineOperation::machine_status(&machine_id)
+							== Some(MachineStatus::Online),
+						Error::<T>::MachineNotOnline
+					);
+					ensure!(
+						T::MachineOperation::mining_cml(&machine_id).is_none(),
+						Error::<T>::MachineIsOccupied
+					);
+					Ok(())
+				},
+				|who| {
+					CmlStore::<T>::mutate(cml_id, |cml| {
+						if let Some(cml) = cml {
+							cml.start_mining(machine_id);
+						}
+					});
+					MiningCmlIds::<T>::insert(cml_id, ());
+					T::MachineOperation::occupy(&machine_id, cml_id);
+
+					Self::deposit_event(Event::StartMining(cml_id, machine_id, who.clone()));
+				},
+			)
+		}
+
+		/// Stop mining and free the machine the CML is mining on.
+		#[pallet::weight(195_000_000)]
+		pub fn stop_mining(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			machine_id: MachineId,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(
+						Self::cml_controller(&cml).eq(who),
+						Error::<T>::CMLOwnerInvalid
+					);
+					ensure!(
+						cml.machine_id() == Some(&machine_id),
+						Error::<T>::CmlIsNotMining
+					);
+					Ok(())
+				},
+				|who| {
+					Self::stop_mining_inner(cml_id, &current_height);
+
+					Self::deposit_event(Event::StopMining(cml_id, machine_id, who.clone()));
+				},
+			)
+		}
+
+		/// Stake into a slot of the mining CML, with TEA if `staking_cml` is `None`, otherwise
+		/// with the given CML of the sender.
+		#[pallet::weight(195_000_000)]
+		pub fn start_staking(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			staking_cml: Option<CmlId>,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(cml.is_mining(), Error::<T>::CmlIsNotMining);
+					ensure!(
+						(StakingSlots::<T>::decode_len(cml_id).unwrap_or_default() as u32)
+							< Self::staking_slots_limit(&cml),
+						Error::<T>::StakingSlotsFull
+					);
+
+					match staking_cml {
+						Some(staking_cml) => {
+							ensure!(
+								CmlStore::<T>::contains_key(staking_cml),
+								Error::<T>::NotFoundCML
+							);
+							let staking_cml = CmlStore::<T>::get(staking_cml).unwrap();
+							ensure!(staking_cml.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+							ensure!(
+								!staking_cml.should_dead(&current_height),
+								Error::<T>::CmlIsDead
+							);
+							ensure!(staking_cml.is_tree(), Error::<T>::CmlIsNotTree);
+							ensure!(!staking_cml.is_mining(), Error::<T>::CmlIsMining);
+							ensure!(
+								!StakingCmls::<T>::contains_key(staking_cml.id()),
+								Error::<T>::CmlIsStaking
+							);
+							ensure!(
+								!Leases::<T>::contains_key(staking_cml.id()),
+								Error::<T>::CmlIsLeased
+							);
+							ensure!(
+								!MarketListings::<T>::contains_key(staking_cml.id()),
+								Error::<T>::CmlIsListed
+							);
+						}
+						None => ensure!(
+							T::CurrencyOperations::can_reserve(who, T::StakingPrice::get()),
+							Error::<T>::InsufficientFreeBalance
+						),
+					}
+					Ok(())
+				},
+				|who| {
+					let item = match staking_cml {
+						Some(staking_cml) => {
+							StakingCmls::<T>::insert(staking_cml, cml_id);
+							StakingItem {
+								owner: who.clone(),
+								category: StakingCategory::Cml,
+								amount: None,
+								cml: Some(staking_cml),
+							}
+						}
+						None => {
+							let price = T::StakingPrice::get();
+							if let Err(e) = T::CurrencyOperations::reserve(who, price) {
+								// should never happen since `can_reserve` checked before
+								log::error!("reserve staking price failed: {:?}", e);
+								return;
+							}
+							StakingItem {
+								owner: who.clone(),
+								category: StakingCategory::Tea,
+								amount: Some(price),
+								cml: None,
+							}
+						}
+					};
+					StakingSlots::<T>::mutate(cml_id, |slots| {
+						if slots.try_push(item).is_err() {
+							// should never happen since slots length checked before
+							log::error!("staking slots of cml {} are full", cml_id);
+						}
+					});
+
+					Self::deposit_event(Event::StartStaking(cml_id, who.clone(), staking_cml));
+				},
+			)
+		}
+
+		/// Unstake the item at `index` of the mining CML's staking slots, staked TEA or CML is
+		/// released after `UnstakeCooldown`. `staking_cml` is the CML staked by the item, or
+		/// `None` if it stakes TEA, so that an item shifted to the index is not unstaked by
+		/// mistake.
+		#[pallet::weight(195_000_000)]
+		pub fn stop_staking(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			index: StakingIndex,
+			staking_cml: Option<CmlId>,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					let slots = StakingSlots::<T>::get(cml_id);
+					ensure!(
+						(index as usize) < slots.len(),
+						Error::<T>::InvalidStakingIndex
+					);
+					let item = &slots[index as usize];
+					ensure!(item.owner.eq(who), Error::<T>::InvalidStakingOwner);
+					ensure!(item.cml == staking_cml, Error::<T>::StakingItemMismatch);
+					Ok(())
+				},
+				|_who| {
+					let item =
+						StakingSlots::<T>::mutate(cml_id, |slots| slots.remove(index as usize));
+					Self::unstake(cml_id, item, &current_height);
+				},
+			)
+		}
+
+		/// Issue coupons to the given account, coupons are accumulated if the account already
+		/// has coupons of the same type.
+		#[pallet::weight(195_000_000)]
+		pub fn issue_coupon(
+			sender: OriginFor<T>,
+			account: T::AccountId,
+			cml_type: CmlType,
+			schedule_type: DefrostScheduleType,
+			amount: u32,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&root,
+				|_| {
+					ensure!(amount > 0, Error::<T>::InvalidCouponAmount);
+					Ok(())
+				},
+				|_| {
+					Self::add_coupon(&account, cml_type, schedule_type, amount);
+					Self::deposit_event(Event::CouponIssued(
+						account.clone(),
+						cml_type,
+						schedule_type,
+						amount,
+					));
+				},
+			)
+		}
+
+		#[pallet::weight(195_000_000)]
+		pub fn transfer_coupon(
+			sender: OriginFor<T>,
+			target: T::AccountId,
+			cml_type: CmlType,
+			schedule_type: DefrostScheduleType,
+			amount: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					ensure!(amount > 0, Error::<T>::InvalidCouponAmount);
+					ensure!(
+						Self::coupon_amount(who, cml_type, schedule_type) >= amount,
+						Error::<T>::NotEnoughCoupon
+					);
+					Ok(())
+				},
+				|who| {
+					Self::sub_coupon(who, cml_type, schedule_type, amount);
+					Self::add_coupon(&target, cml_type, schedule_type, amount);
+					Self::deposit_event(Event::CouponTransfered(
+						who.clone(),
+						target.clone(),
+						cml_type,
+						schedule_type,
+						amount,
+					));
+				},
+			)
+		}
+
+		/// Draw `amount` seeds randomly from the lucky draw box with coupons of the given defrost
+		/// schedule type, coupons of type A are used first, then B and C. Coupons are consumed
+		/// after drawing, at most `MaxBatchLength` seeds can be drawn in one call.
+		#[pallet::weight(T::WeightInfo::draw_cmls_from_coupon(*amount))]
+		pub fn draw_cmls_from_coupon(
+			sender: OriginFor<T>,
+			schedule_type: DefrostScheduleType,
+			amount: u32,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(
+						NPCAccount::<T>::get().is_some(),
+						Error::<T>::NpcAccountIsEmpty
+					);
+					Self::check_batch_length(amount as usize)?;
+					let draws = Self::coupon_draws(who, schedule_type, amount);
+					let total: u32 = draws.iter().map(|(_, count)| *count).sum();
+					ensure!(total > 0, Error::<T>::WithoutCoupon);
+					ensure!(total == amount, Error::<T>::NotEnoughCoupon);
+					for (cml_type, count) in draws {
+						ensure!(
+							LuckyDrawBox::<T>::decode_len(cml_type, schedule_type)
+								.unwrap_or_default() >= count as usize,
+							Error::<T>::NotEnoughDrawSeeds
+						);
+					}
+					Ok(())
+				},
+				|who| {
+					let npc_account = NPCAccount::<T>::get().unwrap();
+					let mut drawn_cmls = Vec::new();
+					for (cml_type, count) in Self::coupon_draws(who, schedule_type, amount) {
+						Self::sub_coupon(who, cml_type, schedule_type, count);
+						drawn_cmls.append(&mut Self::lucky_draw(
+							who,
+							cml_type,
+							schedule_type,
+							count,
+						));
+					}
+
+					// drawn seeds have been taken out of the lucky draw box
+					drawn_cmls.iter().for_each(|cml_id| {
+						crate::functions::transfer_drawn_cml::<T>(*cml_id, &npc_account, who);
+					});
+					Self::deposit_event(Event::CmlsDrawn(who.clone(), schedule_type, drawn_cmls));
+				},
+			)
+		}
+
+		/// Offer to lease a tree to `lessee` for `duration` blocks at `price`, the offer replaces
+		/// the former one of the CML if exists.
+		#[pallet::weight(195_000_000)]
+		pub fn offer_lease(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			lessee: T::AccountId,
+			price: BalanceOf<T>,
+			duration: T::BlockNumber,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(cml.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+					ensure!(!lessee.eq(who), Error::<T>::InvalidLessee);
+					ensure!(!duration.is_zero(), Error::<T>::InvalidLeaseDuration);
+					Self::check_leasable(&cml)
+				},
+				|who| {
+					LeaseOffers::<T>::insert(
+						cml_id,
+						LeaseOffer {
+							lessee: lessee.clone(),
+							price,
+							duration,
+						},
+					);
+					Self::deposit_event(Event::LeaseOffered(
+						cml_id,
+						who.clone(),
+						lessee.clone(),
+						price,
+						duration,
+					));
+				},
+			)
+		}
+
+		#[pallet::weight(195_000_000)]
+		pub fn cancel_lease_offer(sender: OriginFor<T>, cml_id: CmlId) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					let cml = CmlStore::<T>::get(cml_id).unwrap();
+					ensure!(cml.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+					ensure!(
+						LeaseOffers::<T>::contains_key(cml_id),
+						Error::<T>::LeaseOfferNotExist
+					);
+					Ok(())
+				},
+				|who| {
+					LeaseOffers::<T>::remove(cml_id);
+					Self::deposit_event(Event::LeaseOfferCanceled(cml_id, who.clone()));
+				},
+			)
+		}
+
+		/// Accept the lease offer and pay the price to the owner, the sender controls mining of
+		/// the CML until the lease expires. `price` and `duration` should match the current
+		/// offer, so that an offer replaced by the owner is not accepted by mistake.
+		#[pallet::weight(195_000_000)]
+		pub fn accept_lease(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			price: BalanceOf<T>,
+			duration: T::BlockNumber,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					let offer =
+						LeaseOffers::<T>::get(cml_id).ok_or(Error::<T>::LeaseOfferNotExist)?;
+					ensure!(offer.lessee.eq(who), Error::<T>::InvalidLessee);
+					ensure!(
+						offer.price == price && offer.duration == duration,
+						Error::<T>::LeaseOfferMismatch
+					);
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					Self::check_leasable(&CmlStore::<T>::get(cml_id).unwrap())?;
+					ensure!(
+						T::CurrencyOperations::free_balance(who)
+							>= offer
+								.price
+								.saturating_add(T::CurrencyOperations::minimum_balance()),
+						Error::<T>::InsufficientLeaseBalance
+					);
+					Ok(())
+				},
+				|who| {
+					let offer = LeaseOffers::<T>::take(cml_id).unwrap();
+					let owner = CmlStore::<T>::get(cml_id).unwrap().owner().clone();
+					if let Err(e) = T::CurrencyOperations::transfer(
+						who,
+						&owner,
+						offer.price,
+						ExistenceRequirement::KeepAlive,
+					) {
+						// should not happen, balance of lessee has been checked
+						log::error!("pay lease price of cml {} failed: {:?}", cml_id, e);
+						return;
+					}
+
+					let expire_at = current_height.saturating_add(offer.duration);
+					Leases::<T>::insert(
+						cml_id,
+						Lease {
+							lessee: who.clone(),
+							price: offer.price,
+							start_at: current_height,
+							expire_at,
+						},
+					);
+					UserLeasedCmlStore::<T>::insert(who, cml_id, ());
+					LeaseExpirations::<T>::append(expire_at, cml_id);
+					Self::deposit_event(Event::LeaseStarted(cml_id, owner, who.clone(), expire_at));
+				},
+			)
+		}
+
+		/// List the CML on the market at a fixed price.
+		#[pallet::weight(195_000_000)]
+		pub fn list_cml(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			price: BalanceOf<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					Self::check_listable(who, cml_id)
+				},
+				|who| {
+					MarketListings::<T>::insert(
+						cml_id,
+						Listing {
+							seller: who.clone(),
+							kind: ListingKind::FixedPrice(price),
+							created_at: current_height,
+						},
+					);
+					Self::deposit_event(Event::CmlListed(cml_id, who.clone(), price));
+				},
+			)
+		}
+
+		/// Start an English auction of the CML lasting `duration` blocks, bids lower than
+		/// `reserve_price` are not accepted.
+		#[pallet::weight(195_000_000)]
+		pub fn create_auction(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			reserve_price: BalanceOf<T>,
+			duration: T::BlockNumber,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					ensure!(!duration.is_zero(), Error::<T>::InvalidAuctionDuration);
+					Self::check_listable(who, cml_id)
+				},
+				|who| {
+					let end_at = current_height.saturating_add(duration);
+					MarketListings::<T>::insert(
+						cml_id,
+						Listing {
+							seller: who.clone(),
+							kind: ListingKind::Auction {
+								reserve_price,
+								end_at,
+							},
+							created_at: current_height,
+						},
+					);
+					AuctionEndings::<T>::append(end_at, cml_id);
+					Self::deposit_event(Event::AuctionCreated(
+						cml_id,
+						who.clone(),
+						reserve_price,
+						end_at,
+					));
+				},
+			)
+		}
+
+		/// Cancel the listing, auctions with bids can't be canceled.
+		#[pallet::weight(195_000_000)]
+		pub fn cancel_listing(sender: OriginFor<T>, cml_id: CmlId) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					let listing =
+						MarketListings::<T>::get(cml_id).ok_or(Error::<T>::CmlNotListed)?;
+					ensure!(listing.seller.eq(who), Error::<T>::CMLOwnerInvalid);
+					ensure!(
+						!AuctionBids::<T>::contains_key(cml_id),
+						Error::<T>::AuctionHasBids
+					);
+					Ok(())
+				},
+				|who| {
+					MarketListings::<T>::remove(cml_id);
+					Self::deposit_event(Event::ListingCanceled(cml_id, who.clone()));
+				},
+			)
+		}
+
+		/// Buy a CML listed at a fixed price. The listing price should not exceed `max_price`, so
+		/// that a listing relisted at a higher price is not bought by mistake.
+		#[pallet::weight(195_000_000)]
+		pub fn buy_cml(
+			sender: OriginFor<T>,
+			cml_id: CmlId,
+			max_price: BalanceOf<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let listing = MarketListings::<T>::get(cml_id);
+			let price = match listing.as_ref().map(|listing| &listing.kind) {
+				Some(ListingKind::FixedPrice(price)) => Some(*price),
+				_ => None,
+			};
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					let listing = listing.as_ref().ok_or(Error::<T>::CmlNotListed)?;
+					let price = price.ok_or(Error::<T>::NotFixedPriceListing)?;
+					ensure!(!listing.seller.eq(who), Error::<T>::InvalidBuyer);
+					ensure!(price <= max_price, Error::<T>::PriceExceedsMax);
+					ensure!(
+						T::CurrencyOperations::free_balance(who)
+							>= price.saturating_add(T::CurrencyOperations::minimum_balance()),
+						Error::<T>::InsufficientBuyBalance
+					);
+					ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+					Self::check_tradable(&CmlStore::<T>::get(cml_id).unwrap())
+				},
+				|who| {
+					// both have been checked
+					let (listing, price) = (listing.as_ref().unwrap(), price.unwrap());
+					MarketListings::<T>::remove(cml_id);
+					if let Err(e) = T::CurrencyOperations::transfer(
+						who,
+						&listing.seller,
+						price,
+						ExistenceRequirement::KeepAlive,
+					) {
+						// should not happen, balance of buyer has been checked
+						log::error!("pay for cml {} failed: {:?}", cml_id, e);
+						return;
+					}
+					transfer_cml::<T>(cml_id, &listing.seller, who);
+					Self::deposit_event(Event::CmlBought(
+						cml_id,
+						listing.seller.clone(),
+						who.clone(),
+						price,
+					));
+				},
+			)
+		}
+
+		/// Bid on an auction, the bid amount is reserved until outbid or the auction settled.
+		#[pallet::weight(195_000_000)]
+		pub fn bid(sender: OriginFor<T>, cml_id: CmlId, amount: BalanceOf<T>) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+			let current_height = frame_system::Pallet::<T>::block_number();
+
+			pallet_utils::extrinsic_procedure(
+				&who,
+				|who| {
+					Self::check_cml_store_migrated()?;
+					let listing =
+						MarketListings::<T>::get(cml_id).ok_or(Error::<T>::CmlNotListed)?;
+					let (reserve_price, end_at) = match listing.kind {
+						ListingKind::Auction {
+							reserve_price,
+							end_at,
+						} => (reserve_price, end_at),
+						_ => return Err(Error::<T>::NotAuction.into()),
+					};
+					ensure!(current_height < end_at, Error::<T>::AuctionHasEnded);
+					ensure!(!listing.seller.eq(who), Error::<T>::InvalidBuyer);
+					ensure!(amount >= reserve_price, Error::<T>::BidTooLow);
+
+					let highest_bid = AuctionBids::<T>::get(cml_id);
+					if let Some(ref bid) = highest_bid {
+						ensure!(amount > bid.amount, Error::<T>::BidTooLow);
+					}
+					ensure!(
+						T::CurrencyOperations::can_reserve(
+							who,
+							Self::additional_bid_amount(who, amount, highest_bid.as_ref())
+						),
+						Error::<T>::InsufficientBuyBalance
+					);
+					Ok(())
+				},
+				|who| {
+					let highest_bid = AuctionBids::<T>::get(cml_id);
+					let additional = Self::additional_bid_amount(who, amount, highest_bid.as_ref());
+					if let Err(e) = T::CurrencyOperations::reserve(who, additional) {
+						// should not happen, balance of bidder has been checked
+						log::error!("reserve bid of cml {} failed: {:?}", cml_id, e);
+						return;
+					}
+					if let Some(bid) = highest_bid.filter(|bid| !bid.bidder.eq(who)) {
+						T::CurrencyOperations::unreserve(&bid.bidder, bid.amount);
+					}
+
+					AuctionBids::<T>::insert(
+						cml_id,
+						Bid {
+							bidder: who.clone(),
+							amount,
+						},
+					);
+					Self::deposit_event(Event::BidPlaced(cml_id, who.clone(), amount));
 				},
 			)
 		}
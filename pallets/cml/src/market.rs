@@ -0,0 +1,122 @@
+use super::*;
+use crate::functions::transfer_cml;
+
+impl<T: cml::Config> cml::Pallet<T> {
+	/// Only transferable CMLs of the sender can be listed.
+	pub(crate) fn check_listable(who: &T::AccountId, cml_id: CmlId) -> DispatchResult {
+		ensure!(CmlStore::<T>::contains_key(cml_id), Error::<T>::NotFoundCML);
+		let cml = CmlStore::<T>::get(cml_id).unwrap();
+		ensure!(cml.owner().eq(who), Error::<T>::CMLOwnerInvalid);
+		Self::check_transferable(&cml)
+	}
+
+	/// Amount should be reserved additionally if `who` bids `amount`, bidder raising his own bid
+	/// only needs to reserve the difference.
+	pub(crate) fn additional_bid_amount(
+		who: &T::AccountId,
+		amount: BalanceOf<T>,
+		highest_bid: Option<&Bid<T::AccountId, BalanceOf<T>>>,
+	) -> BalanceOf<T> {
+		match highest_bid {
+			Some(bid) if bid.bidder.eq(who) => amount.saturating_sub(bid.amount),
+			_ => amount,
+		}
+	}
+
+	/// Remove the listing of the CML and refund the highest bid if exists.
+	pub(crate) fn remove_listing(cml_id: CmlId) {
+		if MarketListings::<T>::take(cml_id).is_none() {
+			return;
+		}
+		if let Some(bid) = AuctionBids::<T>::take(cml_id) {
+			T::CurrencyOperations::unreserve(&bid.bidder, bid.amount);
+		}
+	}
+
+	/// Settle auctions ending at `height`, the CML goes to the highest bidder and the bid goes
+	/// to the seller. Auctions without bids are closed. Returns the consumed weight.
+	pub(crate) fn settle_auctions(height: T::BlockNumber) -> Weight {
+		let cml_ids = AuctionEndings::<T>::take(height);
+		let migrate_weight = crate::migrations::migrate_cmls::<T>(&cml_ids);
+		let mut reads: Weight = 1;
+		let mut writes: Weight = 1;
+		for cml_id in cml_ids {
+			reads += 3;
+			let listing = match MarketListings::<T>::get(cml_id) {
+				Some(listing) => listing,
+				None => continue,
+			};
+			match listing.kind {
+				ListingKind::Auction { end_at, .. } if end_at == height => {}
+				_ => continue,
+			}
+			MarketListings::<T>::remove(cml_id);
+			writes += 2;
+
+			let winner = AuctionBids::<T>::take(cml_id).and_then(|bid| {
+				if Self::try_settle_bid(cml_id, &listing.seller, &bid) {
+					return Some(bid);
+				}
+				T::CurrencyOperations::unreserve(&bid.bidder, bid.amount);
+				None
+			});
+			if winner.is_some() {
+				writes += 5;
+			}
+			Self::deposit_event(Event::AuctionEnded(
+				cml_id,
+				listing.seller,
+				winner.as_ref().map(|bid| bid.bidder.clone()),
+				winner.map(|bid| bid.amount),
+			));
+		}
+		T::DbWeight::get()
+			.reads_writes(reads, writes)
+			.saturating_add(migrate_weight)
+	}
+
+	fn try_settle_bid(
+		cml_id: CmlId,
+		seller: &T::AccountId,
+		bid: &Bid<T::AccountId, BalanceOf<T>>,
+	) -> bool {
+		let tradable = CmlStore::<T>::get(cml_id)
+			.map(|cml| cml.owner().eq(seller) && Self::check_tradable(&cml).is_ok())
+			.unwrap_or(false);
+		if !tradable {
+			return false;
+		}
+
+		if let Err(e) = T::CurrencyOperations::repatriate_reserved(&bid.bidder, seller, bid.amount)
+		{
+			log::error!("pay bid of cml {} failed: {:?}", cml_id, e);
+			return false;
+		}
+		transfer_cml::<T>(cml_id, seller, &bid.bidder);
+		true
+	}
+
+	/// Returns all open listings ordered by cml id.
+	pub fn open_listings() -> Vec<ListingInfo<T::AccountId, BalanceOf<T>, T::BlockNumber>> {
+		let mut listings: Vec<ListingInfo<T::AccountId, BalanceOf<T>, T::BlockNumber>> =
+			MarketListings::<T>::iter()
+				.map(|(cml_id, listing)| ListingInfo {
+					cml_id,
+					listing,
+					highest_bid: AuctionBids::<T>::get(cml_id),
+				})
+				.collect();
+		listings.sort_by_key(|info| info.cml_id);
+		listings
+	}
+
+	/// Returns cml id and amount of the highest bids placed by the given user.
+	pub fn user_bids(who: T::AccountId) -> Vec<(CmlId, BalanceOf<T>)> {
+		let mut bids: Vec<(CmlId, BalanceOf<T>)> = AuctionBids::<T>::iter()
+			.filter(|(_, bid)| bid.bidder.eq(&who))
+			.map(|(cml_id, bid)| (cml_id, bid.amount))
+			.collect();
+		bids.sort_by_key(|(cml_id, _)| *cml_id);
+		bids
+	}
+}
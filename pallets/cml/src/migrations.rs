@@ -0,0 +1,229 @@
+//! Storage migrations of pallet-cml. `CmlStore` may hold too many entries to be translated in
+//! one block, so entries are translated in batches of `MIGRATION_BATCH_SIZE` starting from
+//! `on_runtime_upgrade` and continued in `on_initialize` of the following blocks. Calls reading
+//! CMLs are rejected until the migration completes, while hooks translate the CMLs they touch
+//! on demand.
+
+use super::*;
+use codec::DecodeAll;
+use frame_support::{
+	sp_io,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::GetStorageVersion,
+};
+
+/// Max count of `CmlStore` entries visited in one block during migration.
+pub const MIGRATION_BATCH_SIZE: u32 = 1000;
+
+/// Encodings of storage version 0.
+pub mod v0 {
+	use crate::{CmlId, CmlType, Performance};
+	use codec::{Decode, Encode};
+	use node_primitives::BlockNumber;
+	use sp_runtime::RuntimeDebug;
+
+	/// Seed before traits, defrost schedule and generator version were added.
+	#[derive(Clone, Encode, Decode, RuntimeDebug)]
+	pub struct Seed {
+		pub id: CmlId,
+		pub cml_type: CmlType,
+		pub lifespan: BlockNumber,
+		pub performance: Performance,
+		pub class_flag: u64,
+	}
+
+	/// CML before lifecycle and mining fields were added, the phantom block number field of the
+	/// original struct encodes to nothing.
+	#[derive(Clone, Encode, Decode, RuntimeDebug)]
+	pub struct CML<AccountId> {
+		pub intrinsic: Seed,
+		pub owner: AccountId,
+	}
+}
+
+impl<AccountId, BlockNumber> From<v0::CML<AccountId>> for CML<AccountId, BlockNumber>
+where
+	AccountId: PartialEq + Clone + MaxEncodedLen,
+	BlockNumber: Default + AtLeast32BitUnsigned + Clone + MaxEncodedLen,
+{
+	/// Legacy CMLs become frozen seeds. Genesis seeds get the defrost schedule of their position
+	/// among genesis seeds and the standard defrost height of the schedule, since the random
+	/// deviation can't be re-derived. Other seeds can be defrosted at any time.
+	fn from(old: v0::CML<AccountId>) -> Self {
+		let mut seed = Seed {
+			id: old.intrinsic.id,
+			cml_type: old.intrinsic.cml_type,
+			lifespan: old.intrinsic.lifespan,
+			performance: old.intrinsic.performance,
+			class_flag: ClassFlag::from_bits_truncate(old.intrinsic.class_flag),
+			..Default::default()
+		};
+		if let Some((schedule_type, defrost_time)) = GenesisSeeds::genesis_defrost(seed.id) {
+			seed.class_flag.insert(CmlTrait::Genesis);
+			for cml_trait in ClassFlag::from(schedule_type).traits() {
+				seed.class_flag.insert(cml_trait);
+			}
+			seed.defrost_schedule = Some(schedule_type);
+			seed.defrost_time = Some(defrost_time);
+		}
+		CML::from_seed(seed, old.owner)
+	}
+}
+
+/// Start translating `CmlStore` if the on-chain storage version is behind, the first batch is
+/// translated right away.
+pub fn on_runtime_upgrade<T: Config>() -> Weight {
+	let on_chain_version = Pallet::<T>::on_chain_storage_version();
+	if on_chain_version >= STORAGE_VERSION {
+		return T::DbWeight::get().reads(1);
+	}
+
+	log::info!(
+		"migrating cml store from storage version {:?} to {:?}",
+		on_chain_version,
+		STORAGE_VERSION
+	);
+	if !MigrationCursor::<T>::exists() {
+		MigrationCursor::<T>::put(CmlStore::<T>::final_prefix().to_vec());
+	}
+	T::DbWeight::get()
+		.reads_writes(2, 1)
+		.saturating_add(migrate_batch::<T>(MIGRATION_BATCH_SIZE))
+}
+
+/// Translate at most `limit` entries of `CmlStore` following the cursor into the current
+/// encoding. Entries already in the current encoding are skipped. The storage version is updated
+/// once all entries are visited.
+pub fn migrate_batch<T: Config>(limit: u32) -> Weight {
+	let mut cursor = match MigrationCursor::<T>::get() {
+		Some(cursor) => cursor,
+		None => return T::DbWeight::get().reads(1),
+	};
+	let prefix = CmlStore::<T>::final_prefix();
+
+	let mut visited: u32 = 0;
+	let mut translated: u32 = 0;
+	let mut finished = false;
+	while visited < limit {
+		let key = match sp_io::storage::next_key(&cursor) {
+			Some(key) if key.starts_with(&prefix) => key,
+			_ => {
+				finished = true;
+				break;
+			}
+		};
+		if migrate_key::<T>(&key) {
+			translated += 1;
+		}
+		cursor = key;
+		visited += 1;
+	}
+
+	if finished {
+		MigrationCursor::<T>::kill();
+		STORAGE_VERSION.put::<Pallet<T>>();
+		log::info!(
+			"cml store migrated to storage version {:?}",
+			STORAGE_VERSION
+		);
+	} else {
+		MigrationCursor::<T>::put(cursor);
+	}
+	T::DbWeight::get().reads_writes(
+		visited as Weight + translated as Weight + 2,
+		translated as Weight * 2 + 2,
+	)
+}
+
+/// Translate the given `CmlStore` entries right away if the migration is ongoing, so hooks
+/// handling them don't have to wait for the cursor to reach them.
+pub fn migrate_cmls<T: Config>(cml_ids: &[CmlId]) -> Weight {
+	if !MigrationCursor::<T>::exists() {
+		return T::DbWeight::get().reads(1);
+	}
+
+	let translated = cml_ids
+		.iter()
+		.filter(|cml_id| migrate_key::<T>(&CmlStore::<T>::hashed_key_for(**cml_id)))
+		.count() as Weight;
+	T::DbWeight::get().reads_writes(cml_ids.len() as Weight + translated + 1, translated * 2)
+}
+
+/// Translate the entry at the raw key if it is in the legacy encoding, returns true if
+/// translated.
+///
+/// Translated CMLs are not scheduled to be swept, so they stay frozen seeds until their owners
+/// plant them and nothing held before the upgrade expires because of it.
+fn migrate_key<T: Config>(key: &[u8]) -> bool {
+	// entries in the current encoding are longer than legacy ones, so they never decode as
+	// legacy entries without trailing bytes
+	let old = match unhashed::get_raw(key)
+		.and_then(|raw| v0::CML::<T::AccountId>::decode_all(&mut &raw[..]).ok())
+	{
+		Some(old) => old,
+		None => return false,
+	};
+	let cml = CML::<T::AccountId, T::BlockNumber>::from(old);
+	unhashed::put(key, &cml);
+	true
+}
+
+#[cfg(feature = "try-runtime")]
+pub fn pre_upgrade<T: Config>() -> Result<(), &'static str> {
+	use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+	Pallet::<T>::set_temp_storage(raw_cml_entries::<T>().len() as u64, "cml_count");
+	Ok(())
+}
+
+/// Checks that no entry is lost, and that entries visited so far are in the current encoding.
+#[cfg(feature = "try-runtime")]
+pub fn post_upgrade<T: Config>() -> Result<(), &'static str> {
+	use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+	let count: u64 =
+		Pallet::<T>::get_temp_storage("cml_count").ok_or("cml count is not recorded")?;
+	let entries = raw_cml_entries::<T>();
+	ensure!(
+		entries.len() as u64 == count,
+		"cml count changed by migration"
+	);
+
+	let cursor = MigrationCursor::<T>::get();
+	if cursor.is_none() {
+		ensure!(
+			Pallet::<T>::on_chain_storage_version() == STORAGE_VERSION,
+			"storage version is not updated"
+		);
+		// legacy entries can't be decoded until migrated, so the index is checked only after
+		// the migration completes
+		Pallet::<T>::check_cml_index()?;
+	}
+	for (key, raw) in entries {
+		if cursor.as_ref().map(|cursor| key > *cursor).unwrap_or(false) {
+			break;
+		}
+		ensure!(
+			CML::<T::AccountId, T::BlockNumber>::decode_all(&mut &raw[..]).is_ok(),
+			"cml is not translated into the current encoding"
+		);
+	}
+	Ok(())
+}
+
+#[cfg(feature = "try-runtime")]
+fn raw_cml_entries<T: Config>() -> Vec<(Vec<u8>, Vec<u8>)> {
+	let prefix = CmlStore::<T>::final_prefix();
+	let mut entries = Vec::new();
+	let mut key = prefix.to_vec();
+	while let Some(next) = sp_io::storage::next_key(&key) {
+		if !next.starts_with(&prefix) {
+			break;
+		}
+		if let Some(raw) = unhashed::get_raw(&next) {
+			entries.push((next.clone(), raw));
+		}
+		key = next;
+	}
+	entries
+}
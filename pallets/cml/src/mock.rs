@@ -35,6 +35,7 @@ frame_support::construct_runtime!(
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
 		Cml: pallet_cml::{Pallet, Call, Storage, Event<T>},
 		Utils: pallet_utils::{Pallet, Call, Storage, Event<T>},
+		Machine: pallet_machine::{Pallet, Call, Storage, Event<T>},
 		RandomnessCollectiveFlip: pallet_randomness_collective_flip::{Pallet, Storage},
 	}
 );
@@ -74,27 +75,43 @@ impl system::Config for Test {
 }
 
 pub const SEEDS_TIMEOUT_HEIGHT: u32 = 1 * 30 * 24 * 60 * 10;
-pub const STAKING_PERIOD_LENGTH: u32 = 100;
+pub const STAKING_PERIOD_LENGTH: u64 = 100;
 pub const STAKING_PRICE: Balance = 1000;
 pub const MACHINE_ACCOUNT_TOP_UP_AMOUNT: Balance = 1;
 pub const STAKING_SLOTS_MAX_LENGTH: u32 = 100;
+pub const DEFAULT_STAKING_SLOTS: u32 = 10;
 pub const STOP_MINING_PUNISHMENT: Balance = 100;
 pub const MAX_ALLOWED_SUSPEND_HEIGHT: u32 = 1000;
 pub const CML_A_MINING_REWARD_RATE: Balance = 0;
 pub const CML_B_MINING_REWARD_RATE: Balance = 5000;
 pub const CML_C_MINING_REWARD_RATE: Balance = 0;
+pub const MAX_SWEEPS_PER_BLOCK: u32 = 10;
+pub const MAX_REWARDS_PER_BLOCK: u32 = 20;
+pub const MINING_REWARD_POOL: Balance = 10000;
+pub const UNSTAKE_COOLDOWN: u64 = 50;
+pub const MAX_BATCH_LENGTH: u32 = 10;
+pub const LUCKY_DRAW_BOX_MAX_LENGTH: u32 = 1000;
 
 parameter_types! {
 	pub const StakingPrice: Balance = STAKING_PRICE;
 	pub const MachineAccountTopUpAmount: Balance = MACHINE_ACCOUNT_TOP_UP_AMOUNT;
-	pub const StakingPeriodLength: u32 = STAKING_PERIOD_LENGTH;
+	pub const StakingPeriodLength: u64 = STAKING_PERIOD_LENGTH;
+	pub const SeedFreshDuration: u64 = SEED_FRESH_DURATION;
+	pub const MiningRewardPool: Balance = MINING_REWARD_POOL;
+	pub const PerformanceDecay: bool = true;
+	pub const UnstakeCooldown: u64 = UNSTAKE_COOLDOWN;
+	pub const MaxBatchLength: u32 = MAX_BATCH_LENGTH;
+	pub const LuckyDrawBoxMaxLength: u32 = LUCKY_DRAW_BOX_MAX_LENGTH;
 	pub const SeedsTimeoutHeight: u32 = SEEDS_TIMEOUT_HEIGHT;
 	pub const StakingSlotsMaxLength: u32 = STAKING_SLOTS_MAX_LENGTH;
+	pub const DefaultStakingSlots: u32 = DEFAULT_STAKING_SLOTS;
 	pub const StopMiningPunishment: Balance = STOP_MINING_PUNISHMENT;
 	pub const MaxAllowedSuspendHeight: u32 = MAX_ALLOWED_SUSPEND_HEIGHT;
 	pub const CmlAMiningRewardRate: Balance = CML_A_MINING_REWARD_RATE;
 	pub const CmlBMiningRewardRate: Balance = CML_B_MINING_REWARD_RATE;
 	pub const CmlCMiningRewardRate: Balance = CML_C_MINING_REWARD_RATE;
+	pub const MaxSweepsPerBlock: u32 = MAX_SWEEPS_PER_BLOCK;
+	pub const MaxRewardsPerBlock: u32 = MAX_REWARDS_PER_BLOCK;
 }
 
 impl pallet_cml::Config for Test {
@@ -102,6 +119,55 @@ impl pallet_cml::Config for Test {
 	type Currency = Balances;
 	type CommonUtils = Utils;
 	type CurrencyOperations = Utils;
+	type StakingPeriodLength = StakingPeriodLength;
+	type SeedFreshDuration = SeedFreshDuration;
+	type MaxSweepsPerBlock = MaxSweepsPerBlock;
+	type MaxRewardsPerBlock = MaxRewardsPerBlock;
+	type MachineOperation = Machine;
+	type MiningRewardPool = MiningRewardPool;
+	type PerformanceDecay = PerformanceDecay;
+	type StakingSlotsMaxLength = StakingSlotsMaxLength;
+	type DefaultStakingSlots = DefaultStakingSlots;
+	type StakingPrice = StakingPrice;
+	type UnstakeCooldown = UnstakeCooldown;
+	type MaxBatchLength = MaxBatchLength;
+	type LuckyDrawBoxMaxLength = LuckyDrawBoxMaxLength;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const ConnIdLength: u32 = 100;
+	pub const IpAddressLength: u32 = 100;
+	pub const StartupMachineBindingsLength: u32 = 100;
+	pub const StartupTappBindingsLength: u32 = 100;
+	pub const IssuerUrlLength: u32 = 100;
+	pub const IssuerNameLength: u32 = 100;
+	pub const MaxActiveNodes: u32 = 100;
+	pub const RaQuorumSize: u32 = 3;
+	pub const RaThreshold: u32 = 2;
+	pub const RuntimeActivityThreshold: u64 = 3600;
+	pub const ActivityCheckDuration: u64 = 500;
+	pub const RaReselectCooldown: u64 = 100;
+}
+
+impl pallet_machine::Config for Test {
+	type Event = Event;
+	type Currency = Balances;
+	type CommonUtils = Utils;
+	type CurrencyOperations = Utils;
+	type ConnIdLength = ConnIdLength;
+	type IpAddressLength = IpAddressLength;
+	type StartupMachineBindingsLength = StartupMachineBindingsLength;
+	type StartupTappBindingsLength = StartupTappBindingsLength;
+	type IssuerUrlLength = IssuerUrlLength;
+	type IssuerNameLength = IssuerNameLength;
+	type MaxActiveNodes = MaxActiveNodes;
+	type RaQuorumSize = RaQuorumSize;
+	type RaThreshold = RaThreshold;
+	type RuntimeActivityThreshold = RuntimeActivityThreshold;
+	type ActivityCheckDuration = ActivityCheckDuration;
+	type RaReselectCooldown = RaReselectCooldown;
+	type WeightInfo = ();
 }
 
 impl pallet_utils::Config for Test {
@@ -162,7 +228,7 @@ impl ExtBuilder {
 			.unwrap();
 
 		pallet_cml::GenesisConfig::<Test> {
-			npc_account: Default::default(),
+			npc_account: Some(NPC_ACCOUNT),
 			startup_account: Default::default(),
 			genesis_seeds: self.seeds,
 			startup_cmls: Default::default(),
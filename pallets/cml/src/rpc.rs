@@ -6,4 +6,153 @@ impl<T: cml::Config> cml::Pallet<T> {
 			.map(|(id, _)| id)
 			.collect()
 	}
+
+	/// Returns ids of CMLs leased to the given user, the CMLs are not included in
+	/// `user_cml_list` of the user.
+	pub fn user_leased_cml_list(who: T::AccountId) -> Vec<u64> {
+		UserLeasedCmlStore::<T>::iter_prefix(who)
+			.map(|(id, _)| id)
+			.collect()
+	}
+
+	/// Returns id and defrost height of all CMLs of the given user, defrost height is `None` if
+	/// the CML can be defrosted at any time.
+	pub fn user_cml_defrost_heights(who: T::AccountId) -> Vec<(u64, Option<T::BlockNumber>)> {
+		UserCmlStore::<T>::iter_prefix(who)
+			.filter_map(|(id, _)| CmlStore::<T>::get(id))
+			.map(|cml| (cml.id(), cml.defrost_time()))
+			.collect()
+	}
+
+	/// Returns id and expire height of fresh seeds of the given user that will expire within
+	/// `within` blocks from now.
+	pub fn user_expiring_seeds(
+		who: T::AccountId,
+		within: T::BlockNumber,
+	) -> Vec<(u64, T::BlockNumber)> {
+		let deadline = frame_system::Pallet::<T>::block_number().saturating_add(within);
+		let fresh_duration = T::SeedFreshDuration::get();
+		let mut seeds: Vec<(u64, T::BlockNumber)> = UserCmlStore::<T>::iter_prefix(who)
+			.filter_map(|(id, _)| CmlStore::<T>::get(id))
+			.filter_map(|cml| {
+				cml.fresh_expire_at(&fresh_duration)
+					.map(|expire_at| (cml.id(), expire_at))
+			})
+			.filter(|(_, expire_at)| *expire_at <= deadline)
+			.collect();
+		seeds.sort_by(|a, b| a.1.cmp(&b.1));
+		seeds
+	}
+
+	pub fn cml_info(cml_id: CmlId) -> Option<CmlInfo<T::AccountId, T::BlockNumber>> {
+		let current_height = frame_system::Pallet::<T>::block_number();
+		CmlStore::<T>::get(cml_id).map(|cml| Self::to_cml_info(&cml, &current_height))
+	}
+
+	/// Returns CMLs matching the filter ordered by id, `page` starts from 0 and `page_size` is
+	/// limited by `MAX_CML_LIST_PAGE_SIZE`.
+	pub fn cml_list(
+		filter: CmlFilter<T::AccountId>,
+		page: u32,
+		page_size: u32,
+	) -> Vec<CmlInfo<T::AccountId, T::BlockNumber>> {
+		let current_height = frame_system::Pallet::<T>::block_number();
+		let page_size = page_size.min(MAX_CML_LIST_PAGE_SIZE) as usize;
+
+		let cmls: Vec<CML<T::AccountId, T::BlockNumber>> = match filter.owner.as_ref() {
+			Some(owner) => UserCmlStore::<T>::iter_prefix(owner)
+				.filter_map(|(id, _)| CmlStore::<T>::get(id))
+				.collect(),
+			None => CmlStore::<T>::iter().map(|(_, cml)| cml).collect(),
+		};
+		let mut infos: Vec<CmlInfo<T::AccountId, T::BlockNumber>> = cmls
+			.iter()
+			.filter(|cml| {
+				filter
+					.cml_type
+					.map(|cml_type| cml.cml_type() == cml_type)
+					.unwrap_or(true)
+			})
+			.filter(|cml| {
+				filter
+					.state
+					.map(|state| cml.state(&current_height) == state)
+					.unwrap_or(true)
+			})
+			.filter(|cml| {
+				filter
+					.class_flag
+					.map(|class_flag| cml.class_flag().contains_all(&class_flag))
+					.unwrap_or(true)
+			})
+			.map(|cml| Self::to_cml_info(cml, &current_height))
+			.collect();
+		infos.sort_by_key(|info| info.id);
+
+		infos
+			.into_iter()
+			.skip((page as usize).saturating_mul(page_size))
+			.take(page_size)
+			.collect()
+	}
+
+	/// Returns counts of all CMLs grouped by type and by lifecycle state.
+	pub fn cml_stats() -> CmlStats {
+		let current_height = frame_system::Pallet::<T>::block_number();
+		let mut stats = CmlStats {
+			total: 0,
+			type_counts: vec![(CmlType::A, 0), (CmlType::B, 0), (CmlType::C, 0)],
+			state_counts: vec![
+				(CmlState::FrozenSeed, 0),
+				(CmlState::FreshSeed, 0),
+				(CmlState::Tree, 0),
+				(CmlState::Dead, 0),
+			],
+			burned_counts: vec![
+				(CmlType::A, BurnedCount::<T>::get(CmlType::A)),
+				(CmlType::B, BurnedCount::<T>::get(CmlType::B)),
+				(CmlType::C, BurnedCount::<T>::get(CmlType::C)),
+			],
+		};
+		CmlStore::<T>::iter().for_each(|(_, cml)| {
+			stats.total += 1;
+			let state = cml.state(&current_height);
+			stats
+				.type_counts
+				.iter_mut()
+				.filter(|(cml_type, _)| *cml_type == cml.cml_type())
+				.for_each(|(_, count)| *count += 1);
+			stats
+				.state_counts
+				.iter_mut()
+				.filter(|(s, _)| *s == state)
+				.for_each(|(_, count)| *count += 1);
+		});
+		stats
+	}
+
+	/// Returns effects of all CML traits, traits without effects defined are neutral.
+	pub fn trait_effects() -> Vec<(CmlTrait, TraitEffect)> {
+		CmlTrait::ALL
+			.iter()
+			.map(|cml_trait| (*cml_trait, TraitEffects::<T>::get(cml_trait)))
+			.collect()
+	}
+
+	fn to_cml_info(
+		cml: &CML<T::AccountId, T::BlockNumber>,
+		height: &T::BlockNumber,
+	) -> CmlInfo<T::AccountId, T::BlockNumber> {
+		CmlInfo {
+			id: cml.id(),
+			cml_type: cml.cml_type(),
+			owner: cml.owner().clone(),
+			lessee: Leases::<T>::get(cml.id()).map(|lease| lease.lessee),
+			lifespan: cml.lifespan(),
+			performance: cml.get_peak_performance(),
+			class_flag: cml.class_flag(),
+			state: cml.state(height),
+			remaining_life: cml.remaining_life(height),
+		}
+	}
 }
@@ -1,8 +1,33 @@
 use crate::{
-	mock::{new_test_ext, Cml, Origin, Test},
-	CmlId, CmlStore, LastCmlId, NPCAccount, UserCmlStore,
+	migrations,
+	mock::{
+		new_test_ext, Balances, Cml, Machine, MaxBatchLength, Origin, Test, DEFAULT_STAKING_SLOTS,
+		LUCKY_DRAW_BOX_MAX_LENGTH, MAX_BATCH_LENGTH, MAX_REWARDS_PER_BLOCK, MAX_SWEEPS_PER_BLOCK,
+		MINING_REWARD_POOL, SEED_FRESH_DURATION, STAKING_PERIOD_LENGTH, STAKING_PRICE,
+		UNSTAKE_COOLDOWN,
+	},
+	AuctionBids, AuctionEndings, BurnedCount, CmlApprovals, CmlDeadlines, CmlFilter, CmlId,
+	CmlState, CmlStatus, CmlStore, CmlTrait, CmlType, DefrostScheduleType, Distribution, Error,
+	GenerationBatches, GeneratorVersion, GenesisConfig, GenesisSeeds, IndexInconsistency,
+	InvestorCouponStore, LastCmlId, LeaseExpirations, LeaseOffers, Leases, LuckyDrawBox,
+	MarketListings, MigrationCursor, MiningCmlIds, MintedCount, NPCAccount, OngoingRewardRound,
+	OperatorApprovals, PendingUnstakes, RoundPerformances, Seed, SeedDistributions,
+	StakingCategory, StakingCmls, StakingSlots, TeamCouponStore, TraitEffect, UserCmlStore,
+	UserLeasedCmlStore, CML, GENESIS_SEED_A_COUNT, GENESIS_SEED_B_COUNT, GENESIS_SEED_C_COUNT,
+	STORAGE_VERSION, TEAM_PERCENTAGE,
 };
-use frame_support::assert_ok;
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::{
+		Currency, GenesisBuild, GetStorageVersion, OnInitialize, OnRuntimeUpgrade,
+		ReservableCurrency, StorageVersion,
+	},
+	BoundedVec,
+};
+use pallet_machine::MachineOperation;
+use sp_core::{ed25519, Pair};
 
 #[test]
 fn generate_cml_works() {
@@ -12,7 +37,7 @@ fn generate_cml_works() {
 		NPCAccount::<Test>::set(Some(npc));
 
 		let b_count = 5;
-		assert_ok!(Cml::generate_cml(Origin::signed(npc), b_count));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, b_count, 0));
 
 		assert_eq!(CmlStore::<Test>::iter().count() as u32, b_count);
 		assert_eq!(LastCmlId::<Test>::get() as u32, b_count);
@@ -27,12 +52,273 @@ fn generate_cml_works() {
 			));
 		}
 
-		assert_ok!(Cml::generate_cml(Origin::signed(npc), 3));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 3, 0));
 		assert_eq!(CmlStore::<Test>::iter().count() as u32, b_count + 3);
 		assert_eq!(LastCmlId::<Test>::get() as u32, b_count + 3);
 	})
 }
 
+#[test]
+fn generate_cml_of_all_types_and_record_batch() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(100);
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+
+		assert_noop!(
+			Cml::generate_cml(Origin::signed(npc), 0, 0, 0),
+			Error::<Test>::InvalidGenerateAmount
+		);
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 2, 3, 4));
+		assert_eq!(MintedCount::<Test>::get(CmlType::A), 2);
+		assert_eq!(MintedCount::<Test>::get(CmlType::B), 3);
+		assert_eq!(MintedCount::<Test>::get(CmlType::C), 4);
+
+		let batch = GenerationBatches::<Test>::get(1).unwrap();
+		assert_eq!(batch.start_id, 0);
+		assert_eq!(batch.start_height, 100);
+		let seeds = batch.regenerate();
+		let all_seeds: Vec<Seed> = seeds
+			.a_seeds
+			.into_iter()
+			.chain(seeds.b_seeds.into_iter())
+			.chain(seeds.c_seeds.into_iter())
+			.collect();
+		assert_eq!(all_seeds.len(), 9);
+		for seed in all_seeds {
+			let cml = CmlStore::<Test>::get(seed.id).unwrap();
+			assert_eq!(cml.cml_type(), seed.cml_type);
+			assert_eq!(cml.lifespan(), seed.lifespan as u64);
+			assert_eq!(cml.get_peak_performance(), seed.performance);
+			assert_eq!(cml.defrost_time(), seed.defrost_time.map(|t| t as u64));
+		}
+
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 1, 0));
+		assert_eq!(GenerationBatches::<Test>::get(2).unwrap().start_id, 9);
+	})
+}
+
+#[test]
+fn generate_cml_should_not_exceed_max_supply() {
+	new_test_ext().execute_with(|| {
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_noop!(
+			Cml::set_max_supply(Origin::signed(npc), CmlType::B, Some(5)),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(Cml::set_max_supply(Origin::root(), CmlType::B, Some(5)));
+
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 4, 0));
+		assert_noop!(
+			Cml::generate_cml(Origin::signed(npc), 0, 2, 0),
+			Error::<Test>::ExceedMaxSupply
+		);
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 10, 1, 10));
+		assert_eq!(MintedCount::<Test>::get(CmlType::B), 5);
+
+		assert_ok!(Cml::set_max_supply(Origin::root(), CmlType::B, None));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 2, 0));
+	})
+}
+
+#[test]
+fn airdrop_works() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(100);
+		let npc = 1;
+		let recipients: BoundedVec<u64, MaxBatchLength> = vec![2, 3, 4].try_into().unwrap();
+		assert_noop!(
+			Cml::airdrop(Origin::root(), CmlType::B, recipients.clone()),
+			Error::<Test>::NpcAccountIsEmpty
+		);
+
+		NPCAccount::<Test>::set(Some(npc));
+		assert_noop!(
+			Cml::airdrop(Origin::signed(2), CmlType::B, recipients.clone()),
+			Error::<Test>::OnlyNPCAccountCanGenerateCml
+		);
+		assert_noop!(
+			Cml::airdrop(Origin::root(), CmlType::B, Default::default()),
+			Error::<Test>::EmptyBatch
+		);
+		assert!(BoundedVec::<u64, MaxBatchLength>::try_from(vec![
+			2;
+			MAX_BATCH_LENGTH as usize + 1
+		])
+		.is_err());
+
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 1, 0));
+		assert_ok!(Cml::airdrop(
+			Origin::signed(npc),
+			CmlType::B,
+			recipients.clone()
+		));
+		for (i, recipient) in recipients.iter().enumerate() {
+			let cml_id = 1 + i as CmlId;
+			let cml = CmlStore::<Test>::get(cml_id).unwrap();
+			assert_eq!(cml.owner(), recipient);
+			assert_eq!(cml.cml_type(), CmlType::B);
+			assert_eq!(cml.status(), &CmlStatus::FreshSeed(100));
+			assert_eq!(cml.defrost_schedule(), None);
+			assert_eq!(cml.defrost_time(), None);
+			assert!(cml.class_flag().traits().is_empty());
+			assert!(UserCmlStore::<Test>::contains_key(recipient, cml_id));
+			assert!(!UserCmlStore::<Test>::contains_key(npc, cml_id));
+		}
+		assert_eq!(MintedCount::<Test>::get(CmlType::B), 4);
+		assert_eq!(LastCmlId::<Test>::get(), 4);
+
+		let batch = GenerationBatches::<Test>::get(2).unwrap();
+		assert_eq!(batch.start_id, 1);
+		assert_eq!(batch.b_count, 3);
+		assert!(batch.airdrop);
+		// regenerated seeds agree with the minted ones
+		let seeds = batch.regenerate();
+		assert_eq!(seeds.b_seeds[2].id, 3);
+		for seed in seeds.b_seeds {
+			let cml = CmlStore::<Test>::get(seed.id).unwrap();
+			assert_eq!(seed.defrost_schedule, None);
+			assert_eq!(seed.defrost_time, None);
+			assert_eq!(seed.class_flag, cml.class_flag());
+			assert_eq!(seed.lifespan, cml.lifespan() as u32);
+			assert_eq!(seed.performance, cml.get_peak_performance());
+		}
+
+		assert_ok!(Cml::set_max_supply(Origin::root(), CmlType::A, Some(1)));
+		assert_noop!(
+			Cml::airdrop(Origin::root(), CmlType::A, vec![2, 3].try_into().unwrap()),
+			Error::<Test>::ExceedMaxSupply
+		);
+		assert_ok!(Cml::airdrop(
+			Origin::root(),
+			CmlType::A,
+			vec![2].try_into().unwrap()
+		));
+		assert_eq!(CmlStore::<Test>::get(4).unwrap().cml_type(), CmlType::A);
+	})
+}
+
+#[test]
+fn generate_cml_with_v2_generator() {
+	new_test_ext().execute_with(|| {
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+		let distributions = SeedDistributions {
+			a: Distribution::TruncatedNormal,
+			b: Distribution::Uniform,
+			c: Distribution::Uniform,
+		};
+		assert_noop!(
+			Cml::set_seed_generator(Origin::signed(npc), GeneratorVersion::V2, distributions),
+			sp_runtime::DispatchError::BadOrigin
+		);
+
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 1, 1, 1));
+		assert_ok!(Cml::set_seed_generator(
+			Origin::root(),
+			GeneratorVersion::V2,
+			distributions
+		));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 1, 1, 1));
+
+		let v1_batch = GenerationBatches::<Test>::get(1).unwrap();
+		assert_eq!(v1_batch.generator_version, GeneratorVersion::V1);
+		let v2_batch = GenerationBatches::<Test>::get(2).unwrap();
+		assert_eq!(v2_batch.generator_version, GeneratorVersion::V2);
+		assert_eq!(v2_batch.distributions, distributions);
+
+		for (batch, version) in [
+			(v1_batch, GeneratorVersion::V1),
+			(v2_batch, GeneratorVersion::V2),
+		] {
+			let seeds = batch.regenerate();
+			for seed in seeds
+				.a_seeds
+				.iter()
+				.chain(seeds.b_seeds.iter())
+				.chain(seeds.c_seeds.iter())
+			{
+				let cml = CmlStore::<Test>::get(seed.id).unwrap();
+				assert_eq!(cml.generator_version(), version);
+				assert_eq!(cml.lifespan(), seed.lifespan as u64);
+				assert_eq!(cml.get_peak_performance(), seed.performance);
+			}
+		}
+	})
+}
+
+#[test]
+fn genesis_seeds_are_minted_to_npc_and_startup_cmls_planted() {
+	let npc = 1;
+	let startup_account = 2;
+	build_genesis(GenesisConfig::<Test> {
+		npc_account: Some(npc),
+		startup_account: Some(startup_account),
+		genesis_seeds: crate::generator::construct_seeds(0, [1; 32], 1, 2, 3, 0),
+		startup_cmls: vec![1, 2],
+	})
+	.execute_with(|| {
+		assert_eq!(LastCmlId::<Test>::get(), 6);
+		assert_eq!(Cml::user_cml_list(npc).len(), 4);
+		assert!(UserCmlStore::<Test>::contains_key(npc, 0));
+		assert!(!UserCmlStore::<Test>::contains_key(npc, 1));
+
+		for cml_id in [1, 2] {
+			let cml = CmlStore::<Test>::get(cml_id).unwrap();
+			assert_eq!(cml.owner(), &startup_account);
+			assert!(cml.is_tree());
+			assert!(UserCmlStore::<Test>::contains_key(startup_account, cml_id));
+		}
+	})
+}
+
+#[test]
+#[should_panic(expected = "duplicate seed id")]
+fn genesis_should_fail_if_seed_ids_duplicated() {
+	let mut genesis_seeds = crate::generator::construct_seeds(0, [1; 32], 1, 1, 0, 0);
+	genesis_seeds.b_seeds[0].id = 0;
+	build_genesis(GenesisConfig::<Test> {
+		npc_account: Some(1),
+		startup_account: None,
+		genesis_seeds,
+		startup_cmls: vec![],
+	});
+}
+
+#[test]
+#[should_panic(expected = "startup cml 9 not exist")]
+fn genesis_should_fail_if_startup_cml_not_exist() {
+	build_genesis(GenesisConfig::<Test> {
+		npc_account: Some(1),
+		startup_account: Some(2),
+		genesis_seeds: crate::generator::construct_seeds(0, [1; 32], 1, 1, 0, 0),
+		startup_cmls: vec![9],
+	});
+}
+
+#[test]
+#[should_panic(expected = "startup cml 0 is not owned by the npc account")]
+fn genesis_should_fail_if_startup_cml_listed_twice() {
+	build_genesis(GenesisConfig::<Test> {
+		npc_account: Some(1),
+		startup_account: Some(2),
+		genesis_seeds: crate::generator::construct_seeds(0, [1; 32], 1, 1, 0, 0),
+		startup_cmls: vec![0, 0],
+	});
+}
+
+#[test]
+#[should_panic(expected = "genesis seeds and startup cmls require a npc account")]
+fn genesis_should_fail_if_seeds_without_npc() {
+	build_genesis(GenesisConfig::<Test> {
+		npc_account: None,
+		startup_account: None,
+		genesis_seeds: crate::generator::construct_seeds(0, [1; 32], 1, 1, 0, 0),
+		startup_cmls: vec![],
+	});
+}
+
 #[test]
 fn transfer_works() {
 	new_test_ext().execute_with(|| {
@@ -40,7 +326,7 @@ fn transfer_works() {
 		let npc = 1;
 		NPCAccount::<Test>::set(Some(npc));
 
-		assert_ok!(Cml::generate_cml(Origin::signed(npc), 1));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 1, 0));
 		assert_eq!(
 			CmlStore::<Test>::get(0).unwrap().owner(),
 			&NPCAccount::<Test>::get().unwrap()
@@ -57,3 +343,1849 @@ fn transfer_works() {
 		assert!(!UserCmlStore::<Test>::contains_key(npc, 0));
 	})
 }
+
+#[test]
+fn transfer_batch_works() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		insert_cml(owner, 0, 1000);
+		insert_cml(owner, 1, 1000);
+		insert_cml(2, 2, 1000);
+		insert_mining_cml(owner, 3, 1000, 1000);
+
+		assert_noop!(
+			Cml::transfer_batch(Origin::signed(owner), Default::default()),
+			Error::<Test>::EmptyBatch
+		);
+		assert!(BoundedVec::<(CmlId, u64), MaxBatchLength>::try_from(vec![
+			(0, 3);
+			MAX_BATCH_LENGTH
+				as usize + 1
+		])
+		.is_err());
+		assert_noop!(
+			Cml::transfer_batch(
+				Origin::signed(owner),
+				vec![(0, 3), (1, 4), (0, 5)].try_into().unwrap()
+			),
+			Error::<Test>::DuplicateCmlInBatch
+		);
+
+		// nothing is transferred if any of the CMLs can't be transferred
+		assert_noop!(
+			Cml::transfer_batch(
+				Origin::signed(owner),
+				vec![(0, 3), (2, 4)].try_into().unwrap()
+			),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_noop!(
+			Cml::transfer_batch(
+				Origin::signed(owner),
+				vec![(0, 3), (3, 4)].try_into().unwrap()
+			),
+			Error::<Test>::CmlIsMining
+		);
+		assert_noop!(
+			Cml::transfer_batch(
+				Origin::signed(owner),
+				vec![(0, 3), (4, 4)].try_into().unwrap()
+			),
+			Error::<Test>::NotFoundCML
+		);
+
+		assert_ok!(Cml::transfer_batch(
+			Origin::signed(owner),
+			vec![(0, 3), (1, 4)].try_into().unwrap()
+		));
+		assert_eq!(CmlStore::<Test>::get(0).unwrap().owner(), &3);
+		assert_eq!(CmlStore::<Test>::get(1).unwrap().owner(), &4);
+		assert!(UserCmlStore::<Test>::contains_key(3, 0));
+		assert!(UserCmlStore::<Test>::contains_key(4, 1));
+		assert_eq!(Cml::user_cml_list(owner), vec![3]);
+		assert_ok!(Cml::check_cml_index());
+	})
+}
+
+#[test]
+fn approve_and_transfer_from_works() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(100);
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 1, 0));
+
+		let proxy = 2;
+		let user1 = 3;
+		assert_ok!(Cml::approve(Origin::signed(npc), 0, proxy));
+		assert_eq!(CmlApprovals::<Test>::get(0), Some(proxy));
+
+		assert_ok!(Cml::transfer_from(Origin::signed(proxy), npc, 0, user1));
+		assert_eq!(CmlStore::<Test>::get(0).unwrap().owner(), &user1);
+		assert!(UserCmlStore::<Test>::contains_key(user1, 0));
+		assert!(!UserCmlStore::<Test>::contains_key(npc, 0));
+		// approval is cleared after the CML transferred
+		assert!(!CmlApprovals::<Test>::contains_key(0));
+
+		assert_noop!(
+			Cml::transfer_from(Origin::signed(proxy), user1, 0, proxy),
+			Error::<Test>::NotApprovedToTransfer
+		);
+	})
+}
+
+#[test]
+fn approve_should_fail_if_not_owner_or_approve_to_owner() {
+	new_test_ext().execute_with(|| {
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 1, 0));
+
+		assert_noop!(
+			Cml::approve(Origin::signed(2), 0, 3),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_noop!(
+			Cml::approve(Origin::signed(npc), 0, npc),
+			Error::<Test>::ApproveToCurrentOwner
+		);
+		assert_noop!(
+			Cml::approve(Origin::signed(npc), 1, 3),
+			Error::<Test>::NotFoundCML
+		);
+	})
+}
+
+#[test]
+fn revoke_approval_works() {
+	new_test_ext().execute_with(|| {
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 1, 0));
+
+		assert_noop!(
+			Cml::revoke_approval(Origin::signed(npc), 0),
+			Error::<Test>::CmlApprovalNotExist
+		);
+
+		let proxy = 2;
+		assert_ok!(Cml::approve(Origin::signed(npc), 0, proxy));
+		assert_ok!(Cml::revoke_approval(Origin::signed(npc), 0));
+		assert!(!CmlApprovals::<Test>::contains_key(0));
+
+		assert_noop!(
+			Cml::transfer_from(Origin::signed(proxy), npc, 0, proxy),
+			Error::<Test>::NotApprovedToTransfer
+		);
+	})
+}
+
+#[test]
+fn operator_can_approve_and_transfer_all_cmls_of_owner() {
+	new_test_ext().execute_with(|| {
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 2, 0));
+
+		let operator = 2;
+		let user1 = 3;
+		assert_noop!(
+			Cml::set_approval_for_all(Origin::signed(npc), npc, true),
+			Error::<Test>::ApproveToCaller
+		);
+		assert_ok!(Cml::set_approval_for_all(
+			Origin::signed(npc),
+			operator,
+			true
+		));
+		assert!(OperatorApprovals::<Test>::contains_key(npc, operator));
+
+		assert_ok!(Cml::transfer_from(Origin::signed(operator), npc, 0, user1));
+		assert_ok!(Cml::approve(Origin::signed(operator), 1, user1));
+		assert_ok!(Cml::transfer_from(Origin::signed(user1), npc, 1, user1));
+		assert_eq!(CmlStore::<Test>::get(0).unwrap().owner(), &user1);
+		assert_eq!(CmlStore::<Test>::get(1).unwrap().owner(), &user1);
+
+		assert_ok!(Cml::set_approval_for_all(
+			Origin::signed(npc),
+			operator,
+			false
+		));
+		assert!(!OperatorApprovals::<Test>::contains_key(npc, operator));
+	})
+}
+
+#[test]
+fn plant_works() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(100);
+		let owner = 1;
+		insert_cml(owner, 0, 1000);
+
+		assert_ok!(Cml::plant(Origin::signed(owner), 0));
+		let cml = CmlStore::<Test>::get(0).unwrap();
+		assert!(cml.is_tree());
+		assert_eq!(cml.planted_at(), Some(&100));
+		assert_eq!(cml.dead_at(), Some(1100));
+
+		assert_noop!(
+			Cml::plant(Origin::signed(owner), 0),
+			Error::<Test>::CmlIsNotFreshSeed
+		);
+		assert_noop!(
+			Cml::plant(Origin::signed(2), 0),
+			Error::<Test>::CMLOwnerInvalid
+		);
+	})
+}
+
+#[test]
+fn dead_cmls_are_retired_at_the_beginning_of_staking_period() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(1);
+		let owner = 1;
+		insert_cml(owner, 0, 10);
+		insert_cml(owner, 1, STAKING_PERIOD_LENGTH as u32 * 2);
+		insert_cml(owner, 2, 10);
+		assert_ok!(Cml::plant(Origin::signed(owner), 0));
+		assert_ok!(Cml::plant(Origin::signed(owner), 1));
+
+		// the sweep only checks CMLs whose deadlines fall in the period
+		assert_eq!(Cml::cml_deadlines(STAKING_PERIOD_LENGTH * 3), vec![1]);
+
+		frame_system::Pallet::<Test>::set_block_number(20);
+		assert_noop!(
+			Cml::transfer(Origin::signed(owner), 0, 2),
+			Error::<Test>::CmlIsDead
+		);
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert!(Cml::cml_deadlines(STAKING_PERIOD_LENGTH).is_empty());
+		assert!(!CmlStore::<Test>::contains_key(0));
+		assert!(!UserCmlStore::<Test>::contains_key(owner, 0));
+		// not dead yet
+		assert!(CmlStore::<Test>::get(1).unwrap().is_tree());
+		// seeds never planted will not die
+		assert_eq!(
+			CmlStore::<Test>::get(2).unwrap().status(),
+			&CmlStatus::FreshSeed(STAKING_PERIOD_LENGTH)
+		);
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH * 3);
+		assert!(!CmlStore::<Test>::contains_key(1));
+		assert!(!UserCmlStore::<Test>::contains_key(owner, 1));
+		assert!(CmlStore::<Test>::contains_key(2));
+	})
+}
+
+#[test]
+fn sweeps_beyond_max_sweeps_per_block_spill_into_following_blocks() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(1);
+		let owner = 1;
+		let count = MAX_SWEEPS_PER_BLOCK as u64 + 2;
+		for cml_id in 0..count {
+			insert_cml(owner, cml_id, 1000);
+		}
+		assert_eq!(
+			Cml::cml_deadlines(STAKING_PERIOD_LENGTH).len(),
+			MAX_SWEEPS_PER_BLOCK as usize
+		);
+		assert_eq!(
+			Cml::cml_deadlines(STAKING_PERIOD_LENGTH + 1),
+			vec![count - 2, count - 1]
+		);
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert!(CmlStore::<Test>::get(0).unwrap().is_fresh_seed());
+		assert!(CmlStore::<Test>::get(count - 1).unwrap().is_frozen_seed());
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH + 1);
+		assert!(Cml::cml_deadlines(STAKING_PERIOD_LENGTH + 1).is_empty());
+		assert_eq!(
+			CmlStore::<Test>::get(count - 1).unwrap().status(),
+			&CmlStatus::FreshSeed(STAKING_PERIOD_LENGTH + 1)
+		);
+	})
+}
+
+#[test]
+fn frozen_seed_cannot_be_transferred_or_planted_before_defrost_time() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(100);
+		let owner = 1;
+		insert_frozen_cml(owner, 0, 1000, 200);
+
+		assert_noop!(
+			Cml::transfer(Origin::signed(owner), 0, 2),
+			Error::<Test>::CmlStillFrozen
+		);
+		assert_noop!(
+			Cml::plant(Origin::signed(owner), 0),
+			Error::<Test>::CmlStillFrozen
+		);
+		assert_eq!(Cml::user_cml_defrost_heights(owner), vec![(0, Some(200))]);
+
+		frame_system::Pallet::<Test>::set_block_number(200);
+		assert_ok!(Cml::plant(Origin::signed(owner), 0));
+		assert!(CmlStore::<Test>::get(0).unwrap().is_tree());
+	})
+}
+
+#[test]
+fn generated_seeds_follow_defrost_schedule() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(100);
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 10, 0));
+		let team_count = CmlStore::<Test>::iter_values()
+			.filter(|cml| cml.defrost_schedule() == Some(DefrostScheduleType::Team))
+			.count() as u64;
+		assert_eq!(team_count, 10 * TEAM_PERCENTAGE / 100);
+
+		for (_, cml) in CmlStore::<Test>::iter() {
+			let defrost_time = cml.defrost_time().unwrap();
+			match cml.defrost_schedule().unwrap() {
+				DefrostScheduleType::Team => assert!(defrost_time > 100),
+				DefrostScheduleType::Investor => assert!(defrost_time >= 100),
+			}
+		}
+	})
+}
+
+#[test]
+fn generated_seeds_have_traits_of_defrost_schedule() {
+	new_test_ext().execute_with(|| {
+		let npc = 1;
+		NPCAccount::<Test>::set(Some(npc));
+
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 10, 0));
+		for (_, cml) in CmlStore::<Test>::iter() {
+			let class_flag = cml.class_flag();
+			assert!(!class_flag.contains(CmlTrait::Genesis));
+			match cml.defrost_schedule().unwrap() {
+				DefrostScheduleType::Team => assert_eq!(class_flag, CmlTrait::Team.into()),
+				DefrostScheduleType::Investor => {
+					assert_eq!(class_flag, CmlTrait::Investor.into())
+				}
+			}
+		}
+
+		let team_cmls = Cml::cml_list(
+			CmlFilter {
+				class_flag: Some(CmlTrait::Team.into()),
+				..Default::default()
+			},
+			0,
+			100,
+		);
+		assert_eq!(team_cmls.len() as u64, 10 * TEAM_PERCENTAGE / 100);
+
+		let genesis_seeds = crate::generator::init_genesis([1; 32]);
+		assert!(genesis_seeds
+			.a_seeds
+			.iter()
+			.chain(genesis_seeds.b_seeds.iter())
+			.chain(genesis_seeds.c_seeds.iter())
+			.all(|seed| seed.class_flag.contains(CmlTrait::Genesis)));
+	})
+}
+
+#[test]
+fn trait_effects_apply_to_mining_cmls() {
+	new_test_ext().execute_with(|| {
+		let owner1 = 1;
+		let owner2 = 2;
+		insert_mining_cml(owner1, 0, 1000, 1000);
+		insert_mining_cml(owner2, 1, 1000, 2000);
+
+		let effect = TraitEffect {
+			performance_multiplier: 200,
+			extra_staking_slots: 5,
+		};
+		assert_noop!(
+			Cml::set_trait_effect(Origin::signed(owner1), CmlTrait::Boosted, effect),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Cml::set_class_flag(Origin::signed(owner1), 0, CmlTrait::Boosted.into()),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Cml::set_class_flag(Origin::root(), 2, CmlTrait::Boosted.into()),
+			Error::<Test>::NotFoundCML
+		);
+		assert_ok!(Cml::set_trait_effect(
+			Origin::root(),
+			CmlTrait::Boosted,
+			effect
+		));
+		assert_ok!(Cml::set_class_flag(
+			Origin::root(),
+			0,
+			CmlTrait::Boosted.into()
+		));
+		assert_eq!(
+			Cml::trait_effects(),
+			vec![
+				(CmlTrait::Genesis, TraitEffect::default()),
+				(CmlTrait::Team, TraitEffect::default()),
+				(CmlTrait::Investor, TraitEffect::default()),
+				(CmlTrait::Dao, TraitEffect::default()),
+				(CmlTrait::Boosted, effect),
+			]
+		);
+
+		let boosted = CmlStore::<Test>::get(0).unwrap();
+		let normal = CmlStore::<Test>::get(1).unwrap();
+		assert_eq!(
+			Cml::staking_slots_limit(&boosted),
+			DEFAULT_STAKING_SLOTS + 5
+		);
+		assert_eq!(Cml::staking_slots_limit(&normal), DEFAULT_STAKING_SLOTS);
+
+		// boosted performance equals to performance of the other CML
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert_eq!(Balances::free_balance(owner1), MINING_REWARD_POOL / 2);
+		assert_eq!(Balances::free_balance(owner2), MINING_REWARD_POOL / 2);
+
+		for staker in 10..10 + DEFAULT_STAKING_SLOTS as u64 {
+			<Test as crate::Config>::Currency::make_free_balance_be(&staker, STAKING_PRICE * 3);
+			assert_ok!(Cml::start_staking(Origin::signed(staker), 1, None));
+			assert_ok!(Cml::start_staking(Origin::signed(staker), 0, None));
+		}
+		let staker = 100;
+		<Test as crate::Config>::Currency::make_free_balance_be(&staker, STAKING_PRICE * 3);
+		assert_noop!(
+			Cml::start_staking(Origin::signed(staker), 1, None),
+			Error::<Test>::StakingSlotsFull
+		);
+		assert_ok!(Cml::start_staking(Origin::signed(staker), 0, None));
+	})
+}
+
+#[test]
+fn expired_fresh_seeds_are_retired() {
+	new_test_ext().execute_with(|| {
+		let npc = 5;
+		NPCAccount::<Test>::set(Some(npc));
+		let owner = 1;
+		insert_cml(owner, 0, 1000);
+		insert_cml(owner, 1, 1000);
+		insert_cml(npc, 2, 1000);
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert!(CmlStore::<Test>::get(0).unwrap().is_fresh_seed());
+		// seeds held by npc stay frozen
+		assert!(CmlStore::<Test>::get(2).unwrap().is_frozen_seed());
+
+		let expire_at = STAKING_PERIOD_LENGTH + SEED_FRESH_DURATION;
+		frame_system::Pallet::<Test>::set_block_number(STAKING_PERIOD_LENGTH + 1);
+		assert_ok!(Cml::plant(Origin::signed(owner), 1));
+		assert_eq!(Cml::user_expiring_seeds(owner, 10), vec![]);
+		assert_eq!(
+			Cml::user_expiring_seeds(owner, SEED_FRESH_DURATION),
+			vec![(0, expire_at)]
+		);
+
+		frame_system::Pallet::<Test>::set_block_number(expire_at);
+		assert_noop!(
+			Cml::transfer(Origin::signed(owner), 0, 2),
+			Error::<Test>::SeedHasExpired
+		);
+		assert_noop!(
+			Cml::plant(Origin::signed(owner), 0),
+			Error::<Test>::SeedHasExpired
+		);
+
+		let next_period = (expire_at / STAKING_PERIOD_LENGTH + 1) * STAKING_PERIOD_LENGTH;
+		Cml::on_initialize(next_period);
+		assert!(!CmlStore::<Test>::contains_key(0));
+		assert!(!UserCmlStore::<Test>::contains_key(owner, 0));
+		// planted and npc held seeds are not affected
+		assert!(CmlStore::<Test>::contains_key(1));
+		assert!(CmlStore::<Test>::contains_key(2));
+	})
+}
+
+#[test]
+fn start_mining_works() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(1);
+		let owner = 1;
+		let machine_id = register_machine(owner, 1);
+		insert_cml(owner, 0, 1000);
+
+		assert_noop!(
+			Cml::start_mining(Origin::signed(owner), 0, machine_id),
+			Error::<Test>::CmlIsNotTree
+		);
+		assert_ok!(Cml::plant(Origin::signed(owner), 0));
+		assert_noop!(
+			Cml::start_mining(Origin::signed(owner), 0, [2; 32]),
+			Error::<Test>::MachineNotExist
+		);
+		let pending_machine_id = register_pending_machine(owner, 2);
+		assert_noop!(
+			Cml::start_mining(Origin::signed(owner), 0, pending_machine_id),
+			Error::<Test>::MachineNotOnline
+		);
+
+		assert_ok!(Cml::start_mining(Origin::signed(owner), 0, machine_id));
+		assert_eq!(
+			CmlStore::<Test>::get(0).unwrap().machine_id(),
+			Some(&machine_id)
+		);
+		assert_eq!(Machine::mining_cml(&machine_id), Some(0));
+		assert!(MiningCmlIds::<Test>::contains_key(0));
+
+		assert_noop!(
+			Cml::start_mining(Origin::signed(owner), 0, machine_id),
+			Error::<Test>::CmlIsMiningAlready
+		);
+		insert_cml(owner, 1, 1000);
+		assert_ok!(Cml::plant(Origin::signed(owner), 1));
+		assert_noop!(
+			Cml::start_mining(Origin::signed(owner), 1, machine_id),
+			Error::<Test>::MachineIsOccupied
+		);
+	})
+}
+
+#[test]
+fn start_mining_should_fail_if_machine_not_belongs_to_user() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let machine_id = register_machine(2, 1);
+		insert_cml(owner, 0, 1000);
+		assert_ok!(Cml::plant(Origin::signed(owner), 0));
+
+		assert_noop!(
+			Cml::start_mining(Origin::signed(owner), 0, machine_id),
+			Error::<Test>::InvalidMachineOwner
+		);
+	})
+}
+
+#[test]
+fn stop_mining_works() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let machine_id = register_machine(owner, 1);
+		insert_cml(owner, 0, 1000);
+		assert_ok!(Cml::plant(Origin::signed(owner), 0));
+		assert_ok!(Cml::start_mining(Origin::signed(owner), 0, machine_id));
+
+		assert_noop!(
+			Cml::transfer(Origin::signed(owner), 0, 2),
+			Error::<Test>::CmlIsMining
+		);
+		assert_noop!(
+			Cml::stop_mining(Origin::signed(owner), 0, [2; 32]),
+			Error::<Test>::CmlIsNotMining
+		);
+		assert_noop!(
+			Cml::stop_mining(Origin::signed(2), 0, machine_id),
+			Error::<Test>::CMLOwnerInvalid
+		);
+
+		assert_ok!(Cml::stop_mining(Origin::signed(owner), 0, machine_id));
+		assert!(!CmlStore::<Test>::get(0).unwrap().is_mining());
+		assert!(!MiningCmlIds::<Test>::contains_key(0));
+		assert_eq!(Machine::mining_cml(&machine_id), None);
+		assert_ok!(Cml::transfer(Origin::signed(owner), 0, 2));
+	})
+}
+
+#[test]
+fn dead_mining_cml_frees_machine() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let machine_id = register_machine(owner, 1);
+		insert_cml(owner, 0, 10);
+		assert_ok!(Cml::plant(Origin::signed(owner), 0));
+		assert_ok!(Cml::start_mining(Origin::signed(owner), 0, machine_id));
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert!(!CmlStore::<Test>::contains_key(0));
+		assert_eq!(Machine::mining_cml(&machine_id), None);
+	})
+}
+
+#[test]
+fn mining_rewards_are_split_by_performance() {
+	new_test_ext().execute_with(|| {
+		let owner1 = 1;
+		let owner2 = 2;
+		insert_mining_cml(owner1, 0, 1000, 3000);
+		insert_mining_cml(owner2, 1, 1000, 1000);
+		// trees not mining are not rewarded
+		insert_cml(owner2, 2, 1000);
+		assert_ok!(Cml::plant(Origin::signed(owner2), 2));
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		// performance decays to 2700 and 900 at height 100
+		assert_eq!(Balances::free_balance(owner1), MINING_REWARD_POOL * 3 / 4);
+		assert_eq!(Balances::free_balance(owner2), MINING_REWARD_POOL / 4);
+	})
+}
+
+#[test]
+fn mining_rewards_are_split_across_blocks() {
+	new_test_ext().execute_with(|| {
+		let count = MAX_REWARDS_PER_BLOCK as u64 + 1;
+		for cml_id in 0..count {
+			insert_mining_cml(cml_id + 1, cml_id, 1000, 1000);
+		}
+		let reward = MINING_REWARD_POOL / count as u128;
+
+		// trees are measured in the first two blocks before anyone is paid
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert!(OngoingRewardRound::<Test>::get().unwrap().cursor.is_some());
+		assert!((1..=count).all(|owner| Balances::free_balance(owner) == 0));
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH + 1);
+		let round = OngoingRewardRound::<Test>::get().unwrap();
+		assert_eq!(round.cursor, None);
+		assert_eq!(round.total_performance, 900 * count);
+		assert_eq!(RoundPerformances::<Test>::iter().count(), 2);
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH + 2);
+		assert!(OngoingRewardRound::<Test>::get().is_none());
+		assert!((1..=count).all(|owner| Balances::free_balance(owner) == reward));
+	})
+}
+
+#[test]
+fn mining_rewards_skip_machines_not_online() {
+	new_test_ext().execute_with(|| {
+		let owner1 = 1;
+		let owner2 = 2;
+		insert_mining_cml(owner1, 0, 1000, 3000);
+		insert_tree(owner2, 1, 1000, 3000);
+		let machine_id = register_pending_machine(owner2, 2);
+		CmlStore::<Test>::mutate(1, |cml| {
+			cml.as_mut().unwrap().start_mining(machine_id);
+		});
+		MiningCmlIds::<Test>::insert(1, ());
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert_eq!(Balances::free_balance(owner1), MINING_REWARD_POOL);
+		assert_eq!(Balances::free_balance(owner2), 0);
+	})
+}
+
+#[test]
+fn mining_rewards_paid_from_treasury() {
+	new_test_ext().execute_with(|| {
+		let treasury = 10;
+		let treasury_balance = MINING_REWARD_POOL * 2;
+		<Test as crate::Config>::Currency::make_free_balance_be(&treasury, treasury_balance);
+		assert_noop!(
+			Cml::set_reward_treasury(Origin::signed(1), Some(treasury)),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(Cml::set_reward_treasury(Origin::root(), Some(treasury)));
+
+		let owner = 1;
+		insert_mining_cml(owner, 0, 1000, 3000);
+		let total_issuance = Balances::total_issuance();
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert_eq!(Balances::free_balance(owner), MINING_REWARD_POOL);
+		assert_eq!(
+			Balances::free_balance(treasury),
+			treasury_balance - MINING_REWARD_POOL
+		);
+		assert_eq!(Balances::total_issuance(), total_issuance);
+	})
+}
+
+#[test]
+fn start_staking_works() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let staker = 2;
+		let staker2 = 3;
+		<Test as crate::Config>::Currency::make_free_balance_be(&staker, STAKING_PRICE * 10);
+		insert_mining_cml(owner, 0, 1000, 3000);
+		insert_cml(staker2, 1, 1000);
+		insert_cml(staker2, 2, 1000);
+		assert_ok!(Cml::plant(Origin::signed(staker2), 1));
+
+		assert_ok!(Cml::start_staking(Origin::signed(staker), 0, None));
+		assert_eq!(Balances::reserved_balance(staker), STAKING_PRICE);
+
+		assert_noop!(
+			Cml::start_staking(Origin::signed(staker2), 0, Some(2)),
+			Error::<Test>::CmlIsNotTree
+		);
+		assert_ok!(Cml::start_staking(Origin::signed(staker2), 0, Some(1)));
+		assert_eq!(StakingCmls::<Test>::get(1), Some(0));
+
+		let slots = StakingSlots::<Test>::get(0);
+		assert_eq!(slots.len(), 2);
+		assert_eq!(slots[0].owner, staker);
+		assert_eq!(slots[0].category, StakingCategory::Tea);
+		assert_eq!(slots[0].amount, Some(STAKING_PRICE));
+		assert_eq!(slots[1].owner, staker2);
+		assert_eq!(slots[1].category, StakingCategory::Cml);
+		assert_eq!(slots[1].cml, Some(1));
+
+		assert_noop!(
+			Cml::transfer(Origin::signed(staker2), 1, owner),
+			Error::<Test>::CmlIsStaking
+		);
+		assert_noop!(
+			Cml::start_staking(Origin::signed(staker2), 0, Some(1)),
+			Error::<Test>::CmlIsStaking
+		);
+		assert_noop!(
+			Cml::start_staking(Origin::signed(staker), 1, None),
+			Error::<Test>::CmlIsNotMining
+		);
+		assert_noop!(
+			Cml::start_staking(Origin::signed(4), 0, None),
+			Error::<Test>::InsufficientFreeBalance
+		);
+	})
+}
+
+#[test]
+fn stop_staking_releases_after_cooldown() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(1);
+		let owner = 1;
+		let staker = 2;
+		<Test as crate::Config>::Currency::make_free_balance_be(&staker, STAKING_PRICE * 10);
+		insert_mining_cml(owner, 0, 1000, 3000);
+		insert_cml(staker, 1, 1000);
+		assert_ok!(Cml::plant(Origin::signed(staker), 1));
+		assert_ok!(Cml::start_staking(Origin::signed(staker), 0, None));
+		assert_ok!(Cml::start_staking(Origin::signed(staker), 0, Some(1)));
+
+		assert_noop!(
+			Cml::stop_staking(Origin::signed(staker), 0, 2, None),
+			Error::<Test>::InvalidStakingIndex
+		);
+		assert_noop!(
+			Cml::stop_staking(Origin::signed(owner), 0, 0, None),
+			Error::<Test>::InvalidStakingOwner
+		);
+		assert_noop!(
+			Cml::stop_staking(Origin::signed(staker), 0, 1, None),
+			Error::<Test>::StakingItemMismatch
+		);
+
+		assert_ok!(Cml::stop_staking(Origin::signed(staker), 0, 0, None));
+		// the CML slot shifted to index 0
+		assert_noop!(
+			Cml::stop_staking(Origin::signed(staker), 0, 0, None),
+			Error::<Test>::StakingItemMismatch
+		);
+		assert_ok!(Cml::stop_staking(Origin::signed(staker), 0, 0, Some(1)));
+		assert!(StakingSlots::<Test>::get(0).is_empty());
+		let release_at = 1 + UNSTAKE_COOLDOWN;
+		assert_eq!(PendingUnstakes::<Test>::get(release_at).len(), 2);
+		// still locked during cooldown
+		assert_eq!(Balances::reserved_balance(staker), STAKING_PRICE);
+		assert_noop!(
+			Cml::transfer(Origin::signed(staker), 1, owner),
+			Error::<Test>::CmlIsStaking
+		);
+
+		Cml::on_initialize(release_at);
+		assert_eq!(Balances::reserved_balance(staker), 0);
+		assert!(!StakingCmls::<Test>::contains_key(1));
+		assert!(!PendingUnstakes::<Test>::contains_key(release_at));
+		assert_ok!(Cml::transfer(Origin::signed(staker), 1, owner));
+	})
+}
+
+#[test]
+fn stop_mining_unstakes_all_slots() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let staker = 2;
+		<Test as crate::Config>::Currency::make_free_balance_be(&staker, STAKING_PRICE * 10);
+		let machine_id = insert_mining_cml(owner, 0, 1000, 3000);
+		assert_ok!(Cml::start_staking(Origin::signed(staker), 0, None));
+
+		assert_ok!(Cml::stop_mining(Origin::signed(owner), 0, machine_id));
+		assert!(StakingSlots::<Test>::get(0).is_empty());
+		assert_eq!(PendingUnstakes::<Test>::get(UNSTAKE_COOLDOWN).len(), 1);
+	})
+}
+
+#[test]
+fn staking_rewards_are_shared_by_slots() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let staker = 2;
+		let staker_balance = STAKING_PRICE * 10;
+		<Test as crate::Config>::Currency::make_free_balance_be(&staker, staker_balance);
+		insert_mining_cml(owner, 0, 1000, 3000);
+		assert_ok!(Cml::start_staking(Origin::signed(staker), 0, None));
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert_eq!(Balances::free_balance(owner), MINING_REWARD_POOL / 2);
+		assert_eq!(
+			Balances::free_balance(staker),
+			staker_balance - STAKING_PRICE + MINING_REWARD_POOL / 2
+		);
+	})
+}
+
+#[test]
+fn staking_rewards_are_shared_by_staking_value() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let tea_staker = 2;
+		let cml_staker = 3;
+		let staker_balance = STAKING_PRICE * 10;
+		<Test as crate::Config>::Currency::make_free_balance_be(&tea_staker, staker_balance);
+		insert_mining_cml(owner, 0, 1000, 3000);
+		// performance decays to 30000 at height 100, three times of the base performance of
+		// type C seeds
+		insert_tree(cml_staker, 1, 200, 60000);
+		assert_ok!(Cml::start_staking(Origin::signed(tea_staker), 0, None));
+		assert_ok!(Cml::start_staking(Origin::signed(cml_staker), 0, Some(1)));
+
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert_eq!(Balances::free_balance(owner), MINING_REWARD_POOL / 5);
+		assert_eq!(
+			Balances::free_balance(tea_staker),
+			staker_balance - STAKING_PRICE + MINING_REWARD_POOL / 5
+		);
+		assert_eq!(
+			Balances::free_balance(cml_staker),
+			MINING_REWARD_POOL * 3 / 5
+		);
+	})
+}
+
+#[test]
+fn issue_and_transfer_coupon_works() {
+	new_test_ext().execute_with(|| {
+		let user = 1;
+		let user2 = 2;
+		assert_noop!(
+			Cml::issue_coupon(
+				Origin::signed(user),
+				user,
+				CmlType::A,
+				DefrostScheduleType::Investor,
+				5
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(Cml::issue_coupon(
+			Origin::root(),
+			user,
+			CmlType::A,
+			DefrostScheduleType::Investor,
+			5
+		));
+		assert_eq!(
+			InvestorCouponStore::<Test>::get(user, CmlType::A)
+				.unwrap()
+				.amount,
+			5
+		);
+		assert!(TeamCouponStore::<Test>::get(user, CmlType::A).is_none());
+
+		assert_noop!(
+			Cml::transfer_coupon(
+				Origin::signed(user),
+				user2,
+				CmlType::A,
+				DefrostScheduleType::Team,
+				1
+			),
+			Error::<Test>::NotEnoughCoupon
+		);
+		assert_noop!(
+			Cml::transfer_coupon(
+				Origin::signed(user),
+				user2,
+				CmlType::A,
+				DefrostScheduleType::Investor,
+				0
+			),
+			Error::<Test>::InvalidCouponAmount
+		);
+		assert_ok!(Cml::transfer_coupon(
+			Origin::signed(user),
+			user2,
+			CmlType::A,
+			DefrostScheduleType::Investor,
+			3
+		));
+		assert_eq!(
+			InvestorCouponStore::<Test>::get(user, CmlType::A)
+				.unwrap()
+				.amount,
+			2
+		);
+		assert_eq!(
+			InvestorCouponStore::<Test>::get(user2, CmlType::A)
+				.unwrap()
+				.amount,
+			3
+		);
+
+		assert_ok!(Cml::transfer_coupon(
+			Origin::signed(user),
+			user2,
+			CmlType::A,
+			DefrostScheduleType::Investor,
+			2
+		));
+		assert!(InvestorCouponStore::<Test>::get(user, CmlType::A).is_none());
+	})
+}
+
+#[test]
+fn draw_cmls_from_coupon_works() {
+	new_test_ext().execute_with(|| {
+		let npc = 5;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_noop!(
+			Cml::generate_cml(Origin::signed(npc), 0, LUCKY_DRAW_BOX_MAX_LENGTH + 1, 0),
+			Error::<Test>::LuckyDrawBoxFull
+		);
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 10, 0));
+		let team_count = (10 * TEAM_PERCENTAGE / 100) as usize;
+		assert_eq!(
+			LuckyDrawBox::<Test>::get(CmlType::B, DefrostScheduleType::Team).len(),
+			team_count
+		);
+		assert_eq!(
+			LuckyDrawBox::<Test>::get(CmlType::B, DefrostScheduleType::Investor).len(),
+			10 - team_count
+		);
+
+		let user = 1;
+		assert_noop!(
+			Cml::draw_cmls_from_coupon(Origin::signed(user), DefrostScheduleType::Investor, 1),
+			Error::<Test>::WithoutCoupon
+		);
+		assert_ok!(Cml::issue_coupon(
+			Origin::root(),
+			user,
+			CmlType::B,
+			DefrostScheduleType::Team,
+			team_count as u32 + 1
+		));
+		assert_noop!(
+			Cml::draw_cmls_from_coupon(
+				Origin::signed(user),
+				DefrostScheduleType::Team,
+				team_count as u32 + 1
+			),
+			Error::<Test>::NotEnoughDrawSeeds
+		);
+
+		assert_ok!(Cml::issue_coupon(
+			Origin::root(),
+			user,
+			CmlType::B,
+			DefrostScheduleType::Investor,
+			3
+		));
+		assert_noop!(
+			Cml::draw_cmls_from_coupon(Origin::signed(user), DefrostScheduleType::Investor, 0),
+			Error::<Test>::EmptyBatch
+		);
+		assert_noop!(
+			Cml::draw_cmls_from_coupon(
+				Origin::signed(user),
+				DefrostScheduleType::Investor,
+				MAX_BATCH_LENGTH + 1
+			),
+			Error::<Test>::BatchTooLong
+		);
+		assert_noop!(
+			Cml::draw_cmls_from_coupon(Origin::signed(user), DefrostScheduleType::Investor, 4),
+			Error::<Test>::NotEnoughCoupon
+		);
+		assert_ok!(Cml::draw_cmls_from_coupon(
+			Origin::signed(user),
+			DefrostScheduleType::Investor,
+			2
+		));
+		assert_eq!(
+			InvestorCouponStore::<Test>::get(user, CmlType::B)
+				.unwrap()
+				.amount,
+			1
+		);
+		assert_ok!(Cml::draw_cmls_from_coupon(
+			Origin::signed(user),
+			DefrostScheduleType::Investor,
+			1
+		));
+		assert!(InvestorCouponStore::<Test>::get(user, CmlType::B).is_none());
+		assert_eq!(
+			LuckyDrawBox::<Test>::get(CmlType::B, DefrostScheduleType::Investor).len(),
+			10 - team_count - 3
+		);
+
+		let drawn: Vec<CmlId> = UserCmlStore::<Test>::iter_prefix(user)
+			.map(|(id, _)| id)
+			.collect();
+		assert_eq!(drawn.len(), 3);
+		for cml_id in drawn {
+			let cml = CmlStore::<Test>::get(cml_id).unwrap();
+			assert_eq!(cml.owner(), &user);
+			assert_eq!(cml.cml_type(), CmlType::B);
+			assert_eq!(cml.defrost_schedule(), Some(DefrostScheduleType::Investor));
+			assert!(!UserCmlStore::<Test>::contains_key(npc, cml_id));
+		}
+	})
+}
+
+#[test]
+fn seeds_transferred_by_npc_leave_lucky_draw_box() {
+	new_test_ext().execute_with(|| {
+		let npc = 5;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 10, 0));
+
+		let draw_box = LuckyDrawBox::<Test>::get(CmlType::B, DefrostScheduleType::Investor);
+		let cml_id = draw_box[0];
+		crate::functions::transfer_cml::<Test>(cml_id, &npc, &1);
+		let draw_box = LuckyDrawBox::<Test>::get(CmlType::B, DefrostScheduleType::Investor);
+		assert!(!draw_box.contains(&cml_id));
+	})
+}
+
+#[test]
+fn burn_works() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		insert_cml(owner, 0, 1000);
+		insert_mining_cml(owner, 1, 1000, 1000);
+
+		assert_noop!(
+			Cml::burn(Origin::signed(2), 0),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_noop!(
+			Cml::burn(Origin::signed(owner), 1),
+			Error::<Test>::CmlIsMining
+		);
+		assert_noop!(
+			Cml::burn(Origin::signed(owner), 2),
+			Error::<Test>::NotFoundCML
+		);
+
+		assert_ok!(Cml::burn(Origin::signed(owner), 0));
+		assert!(CmlStore::<Test>::get(0).is_none());
+		assert!(!UserCmlStore::<Test>::contains_key(owner, 0));
+		assert_eq!(BurnedCount::<Test>::get(CmlType::C), 1);
+		assert_eq!(Balances::free_balance(owner), 0);
+
+		let stats = Cml::cml_stats();
+		assert_eq!(stats.total, 1);
+		assert_eq!(
+			stats.burned_counts,
+			vec![(CmlType::A, 0), (CmlType::B, 0), (CmlType::C, 1)]
+		);
+	})
+}
+
+#[test]
+fn burn_with_buy_back_works() {
+	new_test_ext().execute_with(|| {
+		let npc = 5;
+		let owner = 1;
+		let price = 100;
+		NPCAccount::<Test>::set(Some(npc));
+		insert_cml(owner, 0, 1000);
+
+		assert_noop!(
+			Cml::set_buy_back_price(Origin::signed(owner), CmlType::C, Some(price)),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(Cml::set_buy_back_price(
+			Origin::root(),
+			CmlType::C,
+			Some(price)
+		));
+		assert_noop!(
+			Cml::burn(Origin::signed(owner), 0),
+			Error::<Test>::InsufficientBuyBackBalance
+		);
+
+		<Test as crate::Config>::Currency::make_free_balance_be(&npc, price * 10);
+		assert_ok!(Cml::burn(Origin::signed(owner), 0));
+		assert_eq!(Balances::free_balance(owner), price);
+		assert_eq!(Balances::free_balance(npc), price * 9);
+		assert_eq!(BurnedCount::<Test>::get(CmlType::C), 1);
+	})
+}
+
+#[test]
+fn npc_burned_seeds_leave_lucky_draw_box() {
+	new_test_ext().execute_with(|| {
+		let npc = 5;
+		NPCAccount::<Test>::set(Some(npc));
+		assert_ok!(Cml::set_buy_back_price(
+			Origin::root(),
+			CmlType::B,
+			Some(100)
+		));
+		assert_ok!(Cml::generate_cml(Origin::signed(npc), 0, 10, 0));
+
+		let cml_id = LuckyDrawBox::<Test>::get(CmlType::B, DefrostScheduleType::Investor)[0];
+		// the NPC account doesn't pay itself for burning its own seeds
+		assert_ok!(Cml::burn(Origin::signed(npc), cml_id));
+		assert!(
+			!LuckyDrawBox::<Test>::get(CmlType::B, DefrostScheduleType::Investor).contains(&cml_id)
+		);
+		assert_eq!(BurnedCount::<Test>::get(CmlType::B), 1);
+	})
+}
+
+#[test]
+fn offer_and_accept_lease_works() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let lessee = 2;
+		let price = 100;
+		insert_tree(owner, 0, 1000, 1000);
+		insert_cml(owner, 1, 1000);
+
+		assert_noop!(
+			Cml::offer_lease(Origin::signed(lessee), 0, lessee, price, 100),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_noop!(
+			Cml::offer_lease(Origin::signed(owner), 0, owner, price, 100),
+			Error::<Test>::InvalidLessee
+		);
+		assert_noop!(
+			Cml::offer_lease(Origin::signed(owner), 0, lessee, price, 0),
+			Error::<Test>::InvalidLeaseDuration
+		);
+		assert_noop!(
+			Cml::offer_lease(Origin::signed(owner), 1, lessee, price, 100),
+			Error::<Test>::CmlIsNotTree
+		);
+		assert_ok!(Cml::offer_lease(
+			Origin::signed(owner),
+			0,
+			lessee,
+			price,
+			100
+		));
+
+		assert_noop!(
+			Cml::accept_lease(Origin::signed(3), 0, price, 100),
+			Error::<Test>::InvalidLessee
+		);
+		assert_noop!(
+			Cml::accept_lease(Origin::signed(lessee), 1, price, 100),
+			Error::<Test>::LeaseOfferNotExist
+		);
+		assert_noop!(
+			Cml::accept_lease(Origin::signed(lessee), 0, price, 100),
+			Error::<Test>::InsufficientLeaseBalance
+		);
+
+		<Test as crate::Config>::Currency::make_free_balance_be(&lessee, price * 10);
+		// the owner raises the price before the lessee accepts
+		assert_ok!(Cml::offer_lease(
+			Origin::signed(owner),
+			0,
+			lessee,
+			price * 2,
+			100
+		));
+		assert_noop!(
+			Cml::accept_lease(Origin::signed(lessee), 0, price, 100),
+			Error::<Test>::LeaseOfferMismatch
+		);
+		assert_ok!(Cml::offer_lease(
+			Origin::signed(owner),
+			0,
+			lessee,
+			price,
+			50
+		));
+		assert_noop!(
+			Cml::accept_lease(Origin::signed(lessee), 0, price, 100),
+			Error::<Test>::LeaseOfferMismatch
+		);
+		assert_ok!(Cml::offer_lease(
+			Origin::signed(owner),
+			0,
+			lessee,
+			price,
+			100
+		));
+		assert_ok!(Cml::accept_lease(Origin::signed(lessee), 0, price, 100));
+		assert_eq!(Balances::free_balance(owner), price);
+		assert_eq!(Balances::free_balance(lessee), price * 9);
+		assert!(LeaseOffers::<Test>::get(0).is_none());
+		assert_eq!(Leases::<Test>::get(0).unwrap().expire_at, 100);
+		assert_eq!(LeaseExpirations::<Test>::get(100), vec![0]);
+
+		assert_eq!(Cml::user_cml_list(owner), vec![0, 1]);
+		assert_eq!(Cml::user_leased_cml_list(lessee), vec![0]);
+		assert!(Cml::user_cml_list(lessee).is_empty());
+		assert_eq!(Cml::cml_info(0).unwrap().lessee, Some(lessee));
+
+		// neither the owner nor the lessee can transfer or burn the leased CML
+		assert_noop!(
+			Cml::transfer(Origin::signed(owner), 0, 3),
+			Error::<Test>::CmlIsLeased
+		);
+		assert_noop!(
+			Cml::transfer(Origin::signed(lessee), 0, 3),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_noop!(
+			Cml::burn(Origin::signed(owner), 0),
+			Error::<Test>::CmlIsLeased
+		);
+		assert_noop!(
+			Cml::burn(Origin::signed(lessee), 0),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_noop!(
+			Cml::offer_lease(Origin::signed(owner), 0, 3, price, 100),
+			Error::<Test>::CmlIsLeased
+		);
+	})
+}
+
+#[test]
+fn lessee_mines_until_lease_expires() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let lessee = 2;
+		let machine_id = register_machine(lessee, 1);
+		let duration = 150;
+		insert_tree(owner, 0, 1000, 1000);
+		<Test as crate::Config>::Currency::make_free_balance_be(&lessee, 1000);
+
+		frame_system::Pallet::<Test>::set_block_number(10);
+		assert_ok!(Cml::offer_lease(
+			Origin::signed(owner),
+			0,
+			lessee,
+			0,
+			duration
+		));
+		assert_ok!(Cml::accept_lease(Origin::signed(lessee), 0, 0, duration));
+
+		assert_noop!(
+			Cml::start_mining(Origin::signed(owner), 0, machine_id),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_ok!(Cml::start_mining(Origin::signed(lessee), 0, machine_id));
+
+		// mining rewards of the leased CML are paid to the lessee
+		Cml::on_initialize(STAKING_PERIOD_LENGTH);
+		assert_eq!(Balances::free_balance(lessee), 1000 + MINING_REWARD_POOL);
+		assert_eq!(Balances::free_balance(owner), 0);
+
+		Cml::on_initialize(10 + duration - 1);
+		assert!(Leases::<Test>::get(0).is_some());
+		Cml::on_initialize(10 + duration);
+		assert!(Leases::<Test>::get(0).is_none());
+		assert!(!UserLeasedCmlStore::<Test>::contains_key(lessee, 0));
+		assert!(!CmlStore::<Test>::get(0).unwrap().is_mining());
+		assert!(Machine::mining_cml(&machine_id).is_none());
+
+		assert_ok!(Cml::transfer(Origin::signed(owner), 0, 3));
+	})
+}
+
+#[test]
+fn list_and_buy_cml_works() {
+	new_test_ext().execute_with(|| {
+		let seller = 1;
+		let buyer = 2;
+		let price = 100;
+		insert_cml(seller, 0, 1000);
+		insert_mining_cml(seller, 1, 1000, 1000);
+
+		assert_noop!(
+			Cml::list_cml(Origin::signed(buyer), 0, price),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_noop!(
+			Cml::list_cml(Origin::signed(seller), 1, price),
+			Error::<Test>::CmlIsMining
+		);
+		assert_ok!(Cml::list_cml(Origin::signed(seller), 0, price));
+		assert_noop!(
+			Cml::list_cml(Origin::signed(seller), 0, price),
+			Error::<Test>::CmlIsListed
+		);
+
+		// listed CMLs are locked until sold or the listing canceled
+		assert_noop!(
+			Cml::transfer(Origin::signed(seller), 0, 3),
+			Error::<Test>::CmlIsListed
+		);
+		assert_noop!(
+			Cml::burn(Origin::signed(seller), 0),
+			Error::<Test>::CmlIsListed
+		);
+
+		assert_noop!(
+			Cml::buy_cml(Origin::signed(seller), 0, price),
+			Error::<Test>::InvalidBuyer
+		);
+		assert_noop!(
+			Cml::buy_cml(Origin::signed(buyer), 1, price),
+			Error::<Test>::CmlNotListed
+		);
+		assert_noop!(
+			Cml::buy_cml(Origin::signed(buyer), 0, price),
+			Error::<Test>::InsufficientBuyBalance
+		);
+
+		<Test as crate::Config>::Currency::make_free_balance_be(&buyer, price * 10);
+		// the seller relists at a higher price before the buyer's call lands
+		assert_ok!(Cml::cancel_listing(Origin::signed(seller), 0));
+		assert_ok!(Cml::list_cml(Origin::signed(seller), 0, price * 2));
+		assert_noop!(
+			Cml::buy_cml(Origin::signed(buyer), 0, price),
+			Error::<Test>::PriceExceedsMax
+		);
+		assert_ok!(Cml::cancel_listing(Origin::signed(seller), 0));
+		assert_ok!(Cml::list_cml(Origin::signed(seller), 0, price));
+		assert_ok!(Cml::buy_cml(Origin::signed(buyer), 0, price));
+		assert!(MarketListings::<Test>::get(0).is_none());
+		assert_eq!(CmlStore::<Test>::get(0).unwrap().owner(), &buyer);
+		assert!(UserCmlStore::<Test>::contains_key(buyer, 0));
+		assert!(!UserCmlStore::<Test>::contains_key(seller, 0));
+		assert_eq!(Balances::free_balance(seller), price);
+		assert_eq!(Balances::free_balance(buyer), price * 9);
+	})
+}
+
+#[test]
+fn cancel_listing_works() {
+	new_test_ext().execute_with(|| {
+		let seller = 1;
+		insert_cml(seller, 0, 1000);
+		assert_noop!(
+			Cml::cancel_listing(Origin::signed(seller), 0),
+			Error::<Test>::CmlNotListed
+		);
+
+		assert_ok!(Cml::list_cml(Origin::signed(seller), 0, 100));
+		assert_eq!(Cml::open_listings().len(), 1);
+		assert_noop!(
+			Cml::cancel_listing(Origin::signed(2), 0),
+			Error::<Test>::CMLOwnerInvalid
+		);
+		assert_ok!(Cml::cancel_listing(Origin::signed(seller), 0));
+		assert!(Cml::open_listings().is_empty());
+
+		assert_ok!(Cml::transfer(Origin::signed(seller), 0, 2));
+	})
+}
+
+#[test]
+fn auction_bids_work() {
+	new_test_ext().execute_with(|| {
+		let seller = 1;
+		let bidder1 = 2;
+		let bidder2 = 3;
+		let reserve_price = 100;
+		insert_cml(seller, 0, 1000);
+		insert_cml(seller, 1, 1000);
+		<Test as crate::Config>::Currency::make_free_balance_be(&bidder1, 1000);
+		<Test as crate::Config>::Currency::make_free_balance_be(&bidder2, 1000);
+
+		frame_system::Pallet::<Test>::set_block_number(10);
+		assert_noop!(
+			Cml::create_auction(Origin::signed(seller), 0, reserve_price, 0),
+			Error::<Test>::InvalidAuctionDuration
+		);
+		assert_ok!(Cml::create_auction(
+			Origin::signed(seller),
+			0,
+			reserve_price,
+			50
+		));
+		assert_eq!(AuctionEndings::<Test>::get(60), vec![0]);
+		assert_ok!(Cml::list_cml(Origin::signed(seller), 1, reserve_price));
+
+		assert_noop!(
+			Cml::bid(Origin::signed(bidder1), 1, reserve_price),
+			Error::<Test>::NotAuction
+		);
+		assert_noop!(
+			Cml::buy_cml(Origin::signed(bidder1), 0, reserve_price),
+			Error::<Test>::NotFixedPriceListing
+		);
+		assert_noop!(
+			Cml::bid(Origin::signed(seller), 0, reserve_price),
+			Error::<Test>::InvalidBuyer
+		);
+		assert_noop!(
+			Cml::bid(Origin::signed(bidder1), 0, reserve_price - 1),
+			Error::<Test>::BidTooLow
+		);
+		assert_noop!(
+			Cml::bid(Origin::signed(bidder1), 0, 1001),
+			Error::<Test>::InsufficientBuyBalance
+		);
+
+		assert_ok!(Cml::bid(Origin::signed(bidder1), 0, reserve_price));
+		assert_eq!(Balances::reserved_balance(bidder1), reserve_price);
+		assert_noop!(
+			Cml::bid(Origin::signed(bidder2), 0, reserve_price),
+			Error::<Test>::BidTooLow
+		);
+
+		// outbid bidder is refunded
+		assert_ok!(Cml::bid(Origin::signed(bidder2), 0, 200));
+		assert_eq!(Balances::reserved_balance(bidder1), 0);
+		assert_eq!(Balances::free_balance(bidder1), 1000);
+		assert_eq!(Balances::reserved_balance(bidder2), 200);
+
+		// raising own bid only reserves the difference
+		assert_ok!(Cml::bid(Origin::signed(bidder2), 0, 300));
+		assert_eq!(Balances::reserved_balance(bidder2), 300);
+		assert_eq!(Balances::free_balance(bidder2), 700);
+		assert_eq!(Cml::user_bids(bidder2), vec![(0, 300)]);
+		assert!(Cml::user_bids(bidder1).is_empty());
+
+		assert_noop!(
+			Cml::cancel_listing(Origin::signed(seller), 0),
+			Error::<Test>::AuctionHasBids
+		);
+
+		frame_system::Pallet::<Test>::set_block_number(60);
+		assert_noop!(
+			Cml::bid(Origin::signed(bidder1), 0, 400),
+			Error::<Test>::AuctionHasEnded
+		);
+	})
+}
+
+#[test]
+fn auctions_are_settled_at_end_height() {
+	new_test_ext().execute_with(|| {
+		let seller = 1;
+		let bidder = 2;
+		insert_cml(seller, 0, 1000);
+		insert_cml(seller, 1, 1000);
+		<Test as crate::Config>::Currency::make_free_balance_be(&bidder, 1000);
+
+		assert_ok!(Cml::create_auction(Origin::signed(seller), 0, 100, 50));
+		assert_ok!(Cml::create_auction(Origin::signed(seller), 1, 100, 50));
+		assert_ok!(Cml::bid(Origin::signed(bidder), 0, 150));
+
+		Cml::on_initialize(49);
+		assert_eq!(Cml::open_listings().len(), 2);
+
+		Cml::on_initialize(50);
+		assert!(Cml::open_listings().is_empty());
+		assert!(AuctionBids::<Test>::get(0).is_none());
+		assert!(AuctionEndings::<Test>::get(50).is_empty());
+
+		assert_eq!(CmlStore::<Test>::get(0).unwrap().owner(), &bidder);
+		assert_eq!(Balances::free_balance(seller), 150);
+		assert_eq!(Balances::free_balance(bidder), 850);
+		assert_eq!(Balances::reserved_balance(bidder), 0);
+
+		// auction without bids ends unsold
+		assert_eq!(CmlStore::<Test>::get(1).unwrap().owner(), &seller);
+		assert_ok!(Cml::transfer(Origin::signed(seller), 1, 3));
+	})
+}
+
+#[test]
+fn cml_store_is_migrated_in_batches() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<Cml>();
+		for cml_id in 0..5 {
+			insert_v0_cml(1, cml_id);
+		}
+		insert_cml(2, 5, 1000);
+		let legacy_id = GENESIS_SEED_A_COUNT + GENESIS_SEED_B_COUNT + GENESIS_SEED_C_COUNT;
+		insert_v0_cml(1, legacy_id);
+		let current_encoded = CmlStore::<Test>::get(5).unwrap().encode();
+		let deadlines_before = CmlDeadlines::<Test>::iter().count();
+		assert!(CmlStore::<Test>::get(0).is_none());
+
+		MigrationCursor::<Test>::put(CmlStore::<Test>::final_prefix().to_vec());
+		for _ in 0..3 {
+			migrations::migrate_batch::<Test>(2);
+			assert!(MigrationCursor::<Test>::exists());
+			assert_eq!(Cml::on_chain_storage_version(), 0);
+		}
+		migrations::migrate_batch::<Test>(2);
+		assert!(!MigrationCursor::<Test>::exists());
+		// migrated CMLs are not scheduled to be swept
+		assert_eq!(CmlDeadlines::<Test>::iter().count(), deadlines_before);
+		assert_eq!(Cml::on_chain_storage_version(), STORAGE_VERSION);
+
+		for cml_id in 0..5 {
+			let cml = CmlStore::<Test>::get(cml_id).unwrap();
+			assert_eq!(cml.owner(), &1);
+			assert_eq!(cml.cml_type(), CmlType::B);
+			assert_eq!(cml.lifespan(), 100);
+			assert_eq!(cml.get_peak_performance(), 1000);
+			assert!(cml.is_frozen_seed());
+			assert!(cml.class_flag().contains(CmlTrait::Genesis));
+
+			// the first genesis seeds follow team defrost schedule
+			let (schedule_type, defrost_time) = GenesisSeeds::genesis_defrost(cml_id).unwrap();
+			assert_eq!(schedule_type, DefrostScheduleType::Team);
+			assert_eq!(cml.defrost_schedule(), Some(DefrostScheduleType::Team));
+			assert!(cml.class_flag().contains(CmlTrait::Team));
+			assert_eq!(cml.defrost_time(), Some(defrost_time as u64));
+			assert!(!cml.can_be_defrost(&0));
+			assert!(cml.can_be_defrost(&(defrost_time as u64)));
+		}
+		let legacy = CmlStore::<Test>::get(legacy_id).unwrap();
+		assert!(legacy.can_be_defrost(&0));
+		assert_eq!(legacy.defrost_schedule(), None);
+		assert!(!legacy.class_flag().contains(CmlTrait::Genesis));
+		assert_eq!(CmlStore::<Test>::get(5).unwrap().encode(), current_encoded);
+	})
+}
+
+#[test]
+fn runtime_upgrade_migrates_cml_store_once() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<Cml>();
+		insert_v0_cml(1, 0);
+
+		Cml::on_runtime_upgrade();
+		assert!(!MigrationCursor::<Test>::exists());
+		assert_eq!(Cml::on_chain_storage_version(), STORAGE_VERSION);
+		assert_eq!(CmlStore::<Test>::get(0).unwrap().owner(), &1);
+
+		// later upgrades do nothing once the storage version is current
+		insert_v0_cml(1, 1);
+		Cml::on_runtime_upgrade();
+		assert!(CmlStore::<Test>::get(1).is_none());
+	})
+}
+
+#[test]
+fn hooks_keep_running_during_migration() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<Cml>();
+		insert_v0_cml(1, 0);
+		insert_v0_cml(1, 1);
+		MigrationCursor::<Test>::put(CmlStore::<Test>::final_prefix().to_vec());
+
+		assert_noop!(
+			Cml::transfer(Origin::signed(1), 0, 2),
+			Error::<Test>::CmlStoreMigrating
+		);
+
+		Leases::<Test>::insert(
+			0,
+			crate::Lease {
+				lessee: 2,
+				price: 0,
+				start_at: 0,
+				expire_at: 10,
+			},
+		);
+		UserLeasedCmlStore::<Test>::insert(2, 0, ());
+		LeaseExpirations::<Test>::append(10, 0);
+		Cml::expire_leases(10);
+
+		// the leased CML is translated on demand while the other one waits for the cursor
+		assert!(MigrationCursor::<Test>::exists());
+		assert_eq!(CmlStore::<Test>::get(0).unwrap().owner(), &1);
+		assert!(CmlStore::<Test>::get(1).is_none());
+		assert!(!Leases::<Test>::contains_key(0));
+		assert!(!UserLeasedCmlStore::<Test>::contains_key(2, 0));
+
+		<Test as crate::Config>::Currency::make_free_balance_be(&3, STAKING_PRICE * 2);
+		assert_ok!(<Test as crate::Config>::Currency::reserve(
+			&3,
+			STAKING_PRICE
+		));
+		PendingUnstakes::<Test>::append(
+			20,
+			crate::StakingItem {
+				owner: 3,
+				category: StakingCategory::Tea,
+				amount: Some(STAKING_PRICE),
+				cml: None,
+			},
+		);
+		Cml::on_initialize(20);
+		assert!(!MigrationCursor::<Test>::exists());
+		assert_eq!(CmlStore::<Test>::get(1).unwrap().owner(), &1);
+		assert_eq!(Balances::free_balance(3), STAKING_PRICE * 2);
+		assert!(!PendingUnstakes::<Test>::contains_key(20));
+
+		assert_ok!(Cml::transfer(Origin::signed(1), 0, 2));
+	})
+}
+
+#[test]
+fn repair_cml_index_works() {
+	new_test_ext().execute_with(|| {
+		insert_cml(1, 0, 1000);
+		insert_cml(1, 1, 1000);
+		insert_cml(2, 2, 1000);
+		assert!(Cml::cml_index_inconsistencies().is_empty());
+		assert_ok!(Cml::check_cml_index());
+
+		UserCmlStore::<Test>::remove(1, 0);
+		UserCmlStore::<Test>::insert(3, 1, ());
+		UserCmlStore::<Test>::insert(3, 9, ());
+		assert_eq!(
+			Cml::cml_index_inconsistencies(),
+			vec![
+				IndexInconsistency::Missing(1, 0),
+				IndexInconsistency::Mismatched(3, 1),
+				IndexInconsistency::Orphan(3, 9),
+			]
+		);
+		assert!(Cml::check_cml_index().is_err());
+
+		assert_noop!(
+			Cml::repair_cml_index(Origin::signed(1), 7),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		// 3 entries in the cml store and 4 in the index
+		assert_noop!(
+			Cml::repair_cml_index(Origin::root(), 6),
+			Error::<Test>::IndexExceedsRepairBound
+		);
+		assert_ok!(Cml::repair_cml_index(Origin::root(), 7));
+		assert_ok!(Cml::check_cml_index());
+		assert!(UserCmlStore::<Test>::contains_key(1, 0));
+		assert!(UserCmlStore::<Test>::contains_key(1, 1));
+		assert!(Cml::user_cml_list(3).is_empty());
+	})
+}
+
+#[test]
+fn integrity_test_works() {
+	<Cml as frame_support::traits::Hooks<u64>>::integrity_test();
+}
+
+#[test]
+fn cml_info_works() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(10);
+		let owner = 1;
+		insert_mining_cml(owner, 0, 1000, 3000);
+
+		let info = Cml::cml_info(0).unwrap();
+		assert_eq!(info.id, 0);
+		assert_eq!(info.owner, owner);
+		assert_eq!(info.lifespan, 1000);
+		assert_eq!(info.performance, 3000);
+		assert_eq!(info.state, CmlState::Tree);
+		assert_eq!(info.remaining_life, 990);
+		assert!(Cml::cml_info(1).is_none());
+	})
+}
+
+#[test]
+fn cml_list_and_stats_works() {
+	new_test_ext().execute_with(|| {
+		let owner = 1;
+		let owner2 = 2;
+		for id in 0..5 {
+			insert_cml(owner, id, 1000);
+		}
+		for id in 5..8 {
+			insert_frozen_cml(owner2, id, 1000, 100);
+		}
+		assert_ok!(Cml::plant(Origin::signed(owner), 3));
+
+		let ids = |infos: Vec<crate::CmlInfo<u64, u64>>| -> Vec<CmlId> {
+			infos.into_iter().map(|info| info.id).collect()
+		};
+		assert_eq!(
+			ids(Cml::cml_list(CmlFilter::default(), 0, 3)),
+			vec![0, 1, 2]
+		);
+		assert_eq!(ids(Cml::cml_list(CmlFilter::default(), 2, 3)), vec![6, 7]);
+		assert_eq!(
+			ids(Cml::cml_list(
+				CmlFilter {
+					owner: Some(owner2),
+					..Default::default()
+				},
+				0,
+				10
+			)),
+			vec![5, 6, 7]
+		);
+		assert_eq!(
+			ids(Cml::cml_list(
+				CmlFilter {
+					state: Some(CmlState::Tree),
+					..Default::default()
+				},
+				0,
+				10
+			)),
+			vec![3]
+		);
+		assert!(Cml::cml_list(
+			CmlFilter {
+				cml_type: Some(CmlType::B),
+				..Default::default()
+			},
+			0,
+			10
+		)
+		.is_empty());
+
+		let stats = Cml::cml_stats();
+		assert_eq!(stats.total, 8);
+		assert_eq!(
+			stats.type_counts,
+			vec![(CmlType::A, 0), (CmlType::B, 0), (CmlType::C, 8)]
+		);
+		assert_eq!(
+			stats.state_counts,
+			vec![
+				(CmlState::FrozenSeed, 7),
+				(CmlState::FreshSeed, 0),
+				(CmlState::Tree, 1),
+				(CmlState::Dead, 0),
+			]
+		);
+	})
+}
+
+fn build_genesis(config: GenesisConfig<Test>) -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default()
+		.build_storage::<Test>()
+		.unwrap();
+	config.assimilate_storage(&mut t).unwrap();
+	t.into()
+}
+
+/// Insert a tree mining on an online machine, returns the tea id of the machine.
+fn insert_mining_cml(owner: u64, cml_id: CmlId, lifespan: u32, performance: u32) -> [u8; 32] {
+	insert_tree(owner, cml_id, lifespan, performance);
+	let machine_id = register_machine(owner, 200 + cml_id as u8);
+	CmlStore::<Test>::mutate(cml_id, |cml| {
+		cml.as_mut().unwrap().start_mining(machine_id);
+	});
+	MiningCmlIds::<Test>::insert(cml_id, ());
+	machine_id
+}
+
+fn insert_tree(owner: u64, cml_id: CmlId, lifespan: u32, performance: u32) {
+	let mut cml = CML::from_seed(
+		Seed {
+			id: cml_id,
+			lifespan,
+			performance,
+			..Default::default()
+		},
+		owner,
+	);
+	cml.defrost(&0);
+	cml.plant(&0);
+	Cml::schedule_sweep(&cml);
+	UserCmlStore::<Test>::insert(owner, cml_id, ());
+	CmlStore::<Test>::insert(cml_id, cml);
+}
+
+/// Register a machine with the tea key derived from `seed` and bring it online by a heartbeat,
+/// returns the tea id of the machine.
+fn register_machine(owner: u64, seed: u8) -> [u8; 32] {
+	let tea_id = register_pending_machine(owner, seed);
+	send_heartbeat(owner, seed);
+	tea_id
+}
+
+fn send_heartbeat(owner: u64, seed: u8) {
+	let tea = ed25519::Pair::from_seed(&[seed; 32]);
+	let tea_id = tea.public().0;
+	let height = frame_system::Pallet::<Test>::block_number();
+	assert_ok!(Machine::update_runtime_activity(
+		Origin::signed(owner),
+		tea_id,
+		height,
+		tea.sign(&Machine::heartbeat_signing_message(&tea_id, height))
+	));
+}
+
+/// Register a machine with the tea key derived from `seed` without any heartbeat, returns the
+/// tea id of the machine.
+fn register_pending_machine(owner: u64, seed: u8) -> [u8; 32] {
+	let issuer_owner = 99;
+	let issuer = ed25519::Pair::from_seed(&[100; 32]);
+	if Machine::issuer_owners(issuer_owner) == 0 {
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			issuer_owner,
+			vec![],
+			vec![],
+			issuer.public().0
+		));
+	}
+	let issuer_id = Machine::issuer_owners(issuer_owner);
+	let nonce = Machine::issuer_nonces(issuer_id);
+	let tea = ed25519::Pair::from_seed(&[seed; 32]);
+	let tea_id = tea.public().0;
+	assert_ok!(Machine::register_machine(
+		Origin::signed(issuer_owner),
+		tea_id,
+		owner,
+		issuer_id,
+		nonce,
+		issuer.sign(&Machine::issuer_signing_message(
+			issuer_id, &tea_id, &owner, nonce,
+		)),
+		tea.sign(&Machine::machine_signing_message(&owner))
+	));
+	tea_id
+}
+
+fn insert_v0_cml(owner: u64, cml_id: CmlId) {
+	unhashed::put(
+		&CmlStore::<Test>::hashed_key_for(cml_id),
+		&migrations::v0::CML {
+			intrinsic: migrations::v0::Seed {
+				id: cml_id,
+				cml_type: CmlType::B,
+				lifespan: 100,
+				performance: 1000,
+				class_flag: 0,
+			},
+			owner,
+		},
+	);
+}
+
+fn insert_cml(owner: u64, cml_id: CmlId, lifespan: u32) {
+	insert_frozen_cml(owner, cml_id, lifespan, 0);
+}
+
+fn insert_frozen_cml(owner: u64, cml_id: CmlId, lifespan: u32, defrost_time: u32) {
+	let cml = CML::from_seed(
+		Seed {
+			id: cml_id,
+			lifespan,
+			defrost_time: Some(defrost_time),
+			..Default::default()
+		},
+		owner,
+	);
+	Cml::schedule_sweep(&cml);
+	UserCmlStore::<Test>::insert(owner, cml_id, ());
+	CmlStore::<Test>::insert(cml_id, cml);
+}
@@ -1,6 +1,22 @@
+mod batch;
+mod class;
 mod cml;
+mod coupon;
+mod distribution;
+mod lease;
+mod market;
 pub mod param;
+mod query;
 mod seeds;
+mod staking;
 
-pub use cml::{CmlId, CmlType, CML};
-pub use seeds::{DefrostScheduleType, GenesisSeeds, Seed};
+pub use batch::{BatchId, GenerationBatch};
+pub use class::{ClassFlag, CmlTrait, TraitEffect};
+pub use cml::{CmlId, CmlStatus, CmlType, MachineId, CML};
+pub use coupon::Coupon;
+pub use distribution::{Distribution, SeedDistributions};
+pub use lease::{Lease, LeaseOffer};
+pub use market::{Bid, Listing, ListingInfo, ListingKind};
+pub use query::{CmlFilter, CmlInfo, CmlState, CmlStats, MAX_CML_LIST_PAGE_SIZE};
+pub use seeds::{DefrostScheduleType, GeneratorVersion, GenesisSeeds, Seed};
+pub use staking::{RewardRound, StakingCategory, StakingIndex, StakingItem};
@@ -0,0 +1,61 @@
+use crate::generator::{construct_seeds, construct_seeds_v2, WideSeed};
+use crate::{CmlId, GeneratorVersion, GenesisSeeds, SeedDistributions};
+use codec::{Decode, Encode, MaxEncodedLen};
+use node_primitives::BlockNumber;
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+
+pub type BatchId = u64;
+
+/// Record of a seeds generation, seeds of the batch can be re-derived by `regenerate`.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct GenerationBatch {
+	/// Id of the first generated seed.
+	pub start_id: CmlId,
+	/// Random seed used to generate properties of the seeds.
+	pub seed: WideSeed,
+	pub a_count: u64,
+	pub b_count: u64,
+	pub c_count: u64,
+	/// Defrost time of seeds in the batch are counted from this height.
+	pub start_height: BlockNumber,
+	/// Version of the generator used to generate the seeds.
+	pub generator_version: GeneratorVersion,
+	/// Distributions of seed attributes, only used by `GeneratorVersion::V2`.
+	pub distributions: SeedDistributions,
+	/// Whether seeds of the batch are airdropped, airdropped seeds have no defrost schedule.
+	pub airdrop: bool,
+}
+
+impl GenerationBatch {
+	/// Seeds of the batch as they were minted.
+	pub fn regenerate(&self) -> GenesisSeeds {
+		let mut seeds = self.generate();
+		if self.airdrop {
+			seeds.drop_defrost_schedules();
+		}
+		seeds
+	}
+
+	fn generate(&self) -> GenesisSeeds {
+		match self.generator_version {
+			GeneratorVersion::V1 => construct_seeds(
+				self.start_id,
+				self.seed,
+				self.a_count,
+				self.b_count,
+				self.c_count,
+				self.start_height,
+			),
+			GeneratorVersion::V2 => construct_seeds_v2(
+				self.start_id,
+				self.seed,
+				self.a_count,
+				self.b_count,
+				self.c_count,
+				self.start_height,
+				self.distributions,
+			),
+		}
+	}
+}
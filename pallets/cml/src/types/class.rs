@@ -0,0 +1,140 @@
+use crate::DefrostScheduleType;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+/// Traits of a CML, each trait takes one bit of `ClassFlag`.
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum CmlTrait {
+	/// Generated at genesis.
+	Genesis,
+	/// Follows the team defrost schedule.
+	Team,
+	/// Follows the investor defrost schedule.
+	Investor,
+	/// Owned or granted by the DAO.
+	Dao,
+	Boosted,
+}
+
+impl CmlTrait {
+	pub const ALL: [CmlTrait; 5] = [
+		CmlTrait::Genesis,
+		CmlTrait::Team,
+		CmlTrait::Investor,
+		CmlTrait::Dao,
+		CmlTrait::Boosted,
+	];
+
+	pub fn bit(&self) -> u64 {
+		1u64 << (*self as u64)
+	}
+}
+
+/// Bit set of `CmlTrait`s, encoded the same as a `u64`.
+#[derive(
+	Clone, Copy, Default, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ClassFlag(u64);
+
+impl ClassFlag {
+	/// Build from raw bits, bits not belonging to any `CmlTrait` are dropped.
+	pub fn from_bits_truncate(bits: u64) -> Self {
+		let all = CmlTrait::ALL.iter().fold(0, |all, t| all | t.bit());
+		ClassFlag(bits & all)
+	}
+
+	pub fn bits(&self) -> u64 {
+		self.0
+	}
+
+	pub fn contains(&self, cml_trait: CmlTrait) -> bool {
+		self.0 & cml_trait.bit() != 0
+	}
+
+	/// Returns true if all traits of `other` are contained.
+	pub fn contains_all(&self, other: &ClassFlag) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	pub fn insert(&mut self, cml_trait: CmlTrait) {
+		self.0 |= cml_trait.bit();
+	}
+
+	pub fn remove(&mut self, cml_trait: CmlTrait) {
+		self.0 &= !cml_trait.bit();
+	}
+
+	pub fn traits(&self) -> Vec<CmlTrait> {
+		CmlTrait::ALL
+			.iter()
+			.filter(|t| self.contains(**t))
+			.cloned()
+			.collect()
+	}
+}
+
+impl From<CmlTrait> for ClassFlag {
+	fn from(cml_trait: CmlTrait) -> Self {
+		ClassFlag(cml_trait.bit())
+	}
+}
+
+impl From<DefrostScheduleType> for ClassFlag {
+	fn from(schedule_type: DefrostScheduleType) -> Self {
+		match schedule_type {
+			DefrostScheduleType::Team => CmlTrait::Team.into(),
+			DefrostScheduleType::Investor => CmlTrait::Investor.into(),
+		}
+	}
+}
+
+/// Effects of CMLs with a trait, defined by governance.
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct TraitEffect {
+	/// Performance multiplier in percent, 100 means the performance is unchanged.
+	pub performance_multiplier: u32,
+	/// Staking slots added to the mining CML.
+	pub extra_staking_slots: u32,
+}
+
+impl Default for TraitEffect {
+	fn default() -> Self {
+		TraitEffect {
+			performance_multiplier: 100,
+			extra_staking_slots: 0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn class_flag_works() {
+		let mut flag = ClassFlag::from(DefrostScheduleType::Team);
+		assert!(flag.contains(CmlTrait::Team));
+		assert!(!flag.contains(CmlTrait::Investor));
+
+		flag.insert(CmlTrait::Genesis);
+		flag.insert(CmlTrait::Boosted);
+		assert_eq!(
+			flag.traits(),
+			vec![CmlTrait::Genesis, CmlTrait::Team, CmlTrait::Boosted]
+		);
+		assert!(flag.contains_all(&CmlTrait::Boosted.into()));
+		assert!(flag.contains_all(&ClassFlag::default()));
+
+		flag.remove(CmlTrait::Team);
+		assert_eq!(flag.bits(), 0b10001);
+		assert!(!flag.contains_all(&CmlTrait::Team.into()));
+
+		assert_eq!(ClassFlag::from_bits_truncate(u64::MAX).traits().len(), 5);
+	}
+}
@@ -1,17 +1,17 @@
 use crate::param::{Performance, GENESIS_SEED_A_COUNT, GENESIS_SEED_B_COUNT, GENESIS_SEED_C_COUNT};
-use crate::Seed;
+use crate::{ClassFlag, CmlState, DefrostScheduleType, GeneratorVersion, Seed};
 use codec::{Decode, Encode, MaxEncodedLen};
-use frame_support::sp_runtime::traits::AtLeast32BitUnsigned;
+use frame_support::sp_runtime::traits::{AtLeast32BitUnsigned, SaturatedConversion};
 use scale_info::TypeInfo;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 use sp_runtime::RuntimeDebug;
-use sp_std::marker::PhantomData;
 use sp_std::prelude::*;
 
 pub type CmlId = u64;
+pub type MachineId = [u8; 32];
 
-#[derive(Clone, Copy, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum CmlType {
 	A,
@@ -19,6 +19,23 @@ pub enum CmlType {
 	C,
 }
 
+/// Lifecycle of a CML: a frozen seed defrosts into a fresh seed, the fresh seed grows into a tree
+/// after planted, and the tree dies when its lifespan runs out. Dead trees and expired seeds are
+/// removed from storage, so they have no status.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum CmlStatus<BlockNumber> {
+	FrozenSeed,
+	/// Param is the block number the seed defrosted at.
+	FreshSeed(BlockNumber),
+	Tree,
+}
+
+impl<BlockNumber> Default for CmlStatus<BlockNumber> {
+	fn default() -> Self {
+		CmlStatus::FrozenSeed
+	}
+}
+
 #[derive(Clone, Encode, Decode, RuntimeDebug, TypeInfo, MaxEncodedLen)]
 pub struct CML<AccountId, BlockNumber>
 where
@@ -27,7 +44,11 @@ where
 {
 	intrinsic: Seed,
 	owner: AccountId,
-	phantom: PhantomData<BlockNumber>,
+	status: CmlStatus<BlockNumber>,
+	/// Block number when the fresh seed planted and converted into a tree.
+	planted_at: Option<BlockNumber>,
+	/// Machine the tree is mining on.
+	machine_id: Option<MachineId>,
 }
 
 impl<AccountId, BlockNumber> CML<AccountId, BlockNumber>
@@ -39,7 +60,9 @@ where
 		CML {
 			intrinsic,
 			owner: account,
-			phantom: PhantomData,
+			status: CmlStatus::FrozenSeed,
+			planted_at: None,
+			machine_id: None,
 		}
 	}
 
@@ -68,7 +91,265 @@ where
 		self.intrinsic.performance
 	}
 
+	pub fn class_flag(&self) -> ClassFlag {
+		self.intrinsic.class_flag
+	}
+
+	pub fn set_class_flag(&mut self, class_flag: ClassFlag) {
+		self.intrinsic.class_flag = class_flag;
+	}
+
+	/// Version of the generator that produced the seed of the CML.
+	pub fn generator_version(&self) -> GeneratorVersion {
+		self.intrinsic.generator_version
+	}
+
+	/// Performance of the tree at the given height. If `decay` is true the performance decays
+	/// linearly from the peak performance to zero over the lifespan of the tree.
+	pub fn current_performance(&self, height: &BlockNumber, decay: bool) -> Performance {
+		if !self.is_tree() || self.should_dead(height) {
+			return 0;
+		}
+		let peak = self.get_peak_performance();
+		if !decay {
+			return peak;
+		}
+
+		let lifespan: u64 = self.lifespan().saturated_into();
+		let remains: u64 = self
+			.dead_at()
+			.map(|dead_at| dead_at.saturating_sub(height.clone()))
+			.unwrap_or_default()
+			.saturated_into();
+		match lifespan {
+			0 => 0,
+			_ => (peak as u64 * remains / lifespan) as Performance,
+		}
+	}
+
 	pub fn set_owner(&mut self, account: AccountId) {
 		self.owner = account;
 	}
+
+	pub fn status(&self) -> &CmlStatus<BlockNumber> {
+		&self.status
+	}
+
+	pub fn planted_at(&self) -> Option<&BlockNumber> {
+		self.planted_at.as_ref()
+	}
+
+	/// Block number the tree dies at, returns `None` if the CML has not been planted.
+	pub fn dead_at(&self) -> Option<BlockNumber> {
+		self.planted_at
+			.as_ref()
+			.map(|planted_at| planted_at.saturating_add(self.lifespan()))
+	}
+
+	pub fn is_frozen_seed(&self) -> bool {
+		self.status == CmlStatus::FrozenSeed
+	}
+
+	pub fn is_fresh_seed(&self) -> bool {
+		matches!(self.status, CmlStatus::FreshSeed(_))
+	}
+
+	pub fn is_tree(&self) -> bool {
+		self.status == CmlStatus::Tree
+	}
+
+	pub fn defrost_schedule(&self) -> Option<DefrostScheduleType> {
+		self.intrinsic.defrost_schedule
+	}
+
+	/// Block number before which the seed can't be defrosted, `None` means the seed can be
+	/// defrosted at any time.
+	pub fn defrost_time(&self) -> Option<BlockNumber> {
+		self.intrinsic.defrost_time.map(|height| height.into())
+	}
+
+	pub fn can_be_defrost(&self, height: &BlockNumber) -> bool {
+		self.is_frozen_seed()
+			&& self
+				.defrost_time()
+				.map(|defrost_time| defrost_time <= *height)
+				.unwrap_or(true)
+	}
+
+	/// Convert frozen seed into fresh seed, caller should make sure `can_be_defrost` is true.
+	pub fn defrost(&mut self, height: &BlockNumber) {
+		self.status = CmlStatus::FreshSeed(height.clone());
+	}
+
+	/// Defrost the seed if possible, returns true if the seed defrosted this time.
+	pub fn try_defrost(&mut self, height: &BlockNumber) -> bool {
+		if !self.can_be_defrost(height) {
+			return false;
+		}
+		self.defrost(height);
+		true
+	}
+
+	/// Block number the fresh seed expires at, returns `None` if the CML is not a fresh seed.
+	pub fn fresh_expire_at(&self, fresh_duration: &BlockNumber) -> Option<BlockNumber> {
+		match self.status {
+			CmlStatus::FreshSeed(ref fresh_at) => {
+				Some(fresh_at.saturating_add(fresh_duration.clone()))
+			}
+			_ => None,
+		}
+	}
+
+	/// A fresh seed expires if it has not been planted within the fresh duration.
+	pub fn seed_expired(&self, height: &BlockNumber, fresh_duration: &BlockNumber) -> bool {
+		self.fresh_expire_at(fresh_duration)
+			.map(|expire_at| expire_at <= *height)
+			.unwrap_or(false)
+	}
+
+	/// Convert fresh seed into tree, caller should make sure the CML is a fresh seed.
+	pub fn plant(&mut self, height: &BlockNumber) {
+		self.status = CmlStatus::Tree;
+		self.planted_at = Some(height.clone());
+	}
+
+	/// A tree should be dead if it has lived longer than its lifespan.
+	pub fn should_dead(&self, height: &BlockNumber) -> bool {
+		self.is_tree()
+			&& self
+				.dead_at()
+				.map(|dead_at| dead_at <= *height)
+				.unwrap_or(false)
+	}
+
+	/// Lifecycle state at the given height, trees that should be dead are regarded as dead.
+	pub fn state(&self, height: &BlockNumber) -> CmlState {
+		if self.should_dead(height) {
+			return CmlState::Dead;
+		}
+		CmlState::from(&self.status)
+	}
+
+	/// Blocks left before the CML dies, seeds have their whole lifespan left.
+	pub fn remaining_life(&self, height: &BlockNumber) -> BlockNumber {
+		match self.status {
+			CmlStatus::Tree => self
+				.dead_at()
+				.map(|dead_at| dead_at.saturating_sub(height.clone()))
+				.unwrap_or_default(),
+			_ => self.lifespan(),
+		}
+	}
+
+	pub fn machine_id(&self) -> Option<&MachineId> {
+		self.machine_id.as_ref()
+	}
+
+	pub fn is_mining(&self) -> bool {
+		self.machine_id.is_some()
+	}
+
+	/// Start mining on the given machine, caller should make sure the CML is a live tree.
+	pub fn start_mining(&mut self, machine_id: MachineId) {
+		self.machine_id = Some(machine_id);
+	}
+
+	/// Stop mining and returns the machine the CML was mining on.
+	pub fn stop_mining(&mut self) -> Option<MachineId> {
+		self.machine_id.take()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn new_cml(lifespan: u32) -> CML<u64, u64> {
+		CML::from_seed(
+			Seed {
+				lifespan,
+				..Default::default()
+			},
+			1,
+		)
+	}
+
+	#[test]
+	fn can_be_defrost_works() {
+		let mut cml = new_cml(100);
+		assert!(cml.can_be_defrost(&0));
+
+		cml.intrinsic.defrost_time = Some(10);
+		assert!(!cml.can_be_defrost(&9));
+		assert!(cml.can_be_defrost(&10));
+
+		cml.defrost(&10);
+		assert!(!cml.can_be_defrost(&11));
+	}
+
+	#[test]
+	fn seed_expired_works() {
+		let mut cml = new_cml(100);
+		assert_eq!(cml.fresh_expire_at(&10), None);
+		assert!(!cml.seed_expired(&100, &10));
+
+		cml.defrost(&20);
+		assert_eq!(cml.fresh_expire_at(&10), Some(30));
+		assert!(!cml.seed_expired(&29, &10));
+		assert!(cml.seed_expired(&30, &10));
+
+		cml.plant(&25);
+		assert!(!cml.seed_expired(&30, &10));
+	}
+
+	#[test]
+	fn lifecycle_works() {
+		let mut cml = new_cml(100);
+		assert!(cml.is_frozen_seed());
+		assert_eq!(cml.dead_at(), None);
+		assert!(!cml.should_dead(&1000));
+
+		assert!(cml.try_defrost(&10));
+		assert_eq!(cml.status(), &CmlStatus::FreshSeed(10));
+		assert!(!cml.try_defrost(&11));
+
+		cml.plant(&20);
+		assert!(cml.is_tree());
+		assert_eq!(cml.planted_at(), Some(&20));
+		assert_eq!(cml.dead_at(), Some(120));
+		assert!(!cml.should_dead(&119));
+		assert!(cml.should_dead(&120));
+	}
+
+	#[test]
+	fn current_performance_works() {
+		let mut cml = new_cml(100);
+		cml.intrinsic.performance = 1000;
+		assert_eq!(cml.current_performance(&0, false), 0);
+
+		cml.defrost(&0);
+		cml.plant(&20);
+		assert_eq!(cml.current_performance(&70, false), 1000);
+		assert_eq!(cml.current_performance(&20, true), 1000);
+		assert_eq!(cml.current_performance(&70, true), 500);
+		assert_eq!(cml.current_performance(&120, true), 0);
+		assert_eq!(cml.current_performance(&120, false), 0);
+	}
+
+	#[test]
+	fn state_and_remaining_life_works() {
+		let mut cml = new_cml(100);
+		assert_eq!(cml.state(&0), CmlState::FrozenSeed);
+		assert_eq!(cml.remaining_life(&0), 100);
+
+		cml.defrost(&0);
+		assert_eq!(cml.state(&0), CmlState::FreshSeed);
+		assert_eq!(cml.remaining_life(&10), 100);
+
+		cml.plant(&20);
+		assert_eq!(cml.state(&50), CmlState::Tree);
+		assert_eq!(cml.remaining_life(&50), 70);
+		assert_eq!(cml.state(&120), CmlState::Dead);
+		assert_eq!(cml.remaining_life(&130), 0);
+	}
 }
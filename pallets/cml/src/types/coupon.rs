@@ -0,0 +1,14 @@
+use crate::CmlType;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+
+/// Coupon that can be used to draw `amount` seeds of `cml_type` from the NPC account.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct Coupon {
+	pub amount: u32,
+	pub cml_type: CmlType,
+}
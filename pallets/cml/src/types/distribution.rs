@@ -0,0 +1,45 @@
+use crate::CmlType;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+
+/// Distribution of the random deviation of seed attributes (lifespan and performance), only
+/// used by `GeneratorVersion::V2`.
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Distribution {
+	/// Every value within the deviation range is equally likely.
+	Uniform,
+	/// Normal distribution centered at the base value, truncated at three standard deviations
+	/// which are mapped to the bounds of the deviation range.
+	TruncatedNormal,
+}
+
+impl Default for Distribution {
+	fn default() -> Self {
+		Distribution::Uniform
+	}
+}
+
+/// Distributions used to generate seeds of each `CmlType`.
+#[derive(
+	Clone, Copy, Default, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo, MaxEncodedLen,
+)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct SeedDistributions {
+	pub a: Distribution,
+	pub b: Distribution,
+	pub c: Distribution,
+}
+
+impl SeedDistributions {
+	pub fn of(&self, cml_type: CmlType) -> Distribution {
+		match cml_type {
+			CmlType::A => self.a,
+			CmlType::B => self.b,
+			CmlType::C => self.c,
+		}
+	}
+}
@@ -0,0 +1,24 @@
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+
+/// Offer of the CML owner to lease a CML to `lessee`, the lease starts after the lessee accepts
+/// the offer and pays the price.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct LeaseOffer<AccountId, Balance, BlockNumber> {
+	pub lessee: AccountId,
+	/// Amount of TEA the lessee pays to the owner.
+	pub price: Balance,
+	/// Count of blocks the lease lasts.
+	pub duration: BlockNumber,
+}
+
+/// A started lease, the lessee controls mining of the CML until `expire_at`.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Lease<AccountId, Balance, BlockNumber> {
+	pub lessee: AccountId,
+	pub price: Balance,
+	pub start_at: BlockNumber,
+	/// Control of the CML returns to the owner at this height.
+	pub expire_at: BlockNumber,
+}
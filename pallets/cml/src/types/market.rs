@@ -0,0 +1,38 @@
+use crate::CmlId;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub enum ListingKind<Balance, BlockNumber> {
+	/// Anyone can buy the CML at the given price.
+	FixedPrice(Balance),
+	/// English auction, the highest bid not lower than `reserve_price` wins at `end_at`.
+	Auction {
+		reserve_price: Balance,
+		end_at: BlockNumber,
+	},
+}
+
+/// A CML listed on the market, the CML is locked from other transfers while listed.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Listing<AccountId, Balance, BlockNumber> {
+	pub seller: AccountId,
+	pub kind: ListingKind<Balance, BlockNumber>,
+	pub created_at: BlockNumber,
+}
+
+/// Highest bid of an auction, `amount` is reserved from the bidder until outbid or settled.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+pub struct Bid<AccountId, Balance> {
+	pub bidder: AccountId,
+	pub amount: Balance,
+}
+
+/// Open listing returned by queries.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct ListingInfo<AccountId, Balance, BlockNumber> {
+	pub cml_id: CmlId,
+	pub listing: Listing<AccountId, Balance, BlockNumber>,
+	pub highest_bid: Option<Bid<AccountId, Balance>>,
+}
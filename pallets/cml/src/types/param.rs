@@ -15,6 +15,10 @@ pub const GENESIS_SEED_C_COUNT: u64 = 0;
 #[cfg(feature = "fast")]
 pub const GENESIS_SEED_C_COUNT: u64 = 0;
 
+/// Percentage of each type of seeds that follows the team defrost schedule, the others follow the
+/// investor defrost schedule.
+pub const TEAM_PERCENTAGE: u64 = 20;
+
 #[cfg(not(feature = "fast"))]
 pub const UNFROZEN_SEEDS_PERCENTAGE_INVESTOR: u32 = 10;
 #[cfg(feature = "fast")]
@@ -64,6 +68,24 @@ pub struct DefrostSchedule {
 	percentage: u8,
 }
 
+impl DefrostSchedule {
+	/// Standard defrost height (without random deviation) of the `index`-th seed in a batch of
+	/// `count` seeds that follows this schedule.
+	pub fn defrost_height(&self, index: u64, count: u64) -> BlockNumber {
+		if count == 0 || self.percentage == 0 {
+			return self.cliff;
+		}
+
+		let percentage = index * 100 / count;
+		if percentage < self.cliff_percentage as u64 {
+			return self.cliff;
+		}
+		let rounds = (percentage - self.cliff_percentage as u64) / self.percentage as u64 + 1;
+		self.cliff
+			.saturating_add(self.interval.saturating_mul(rounds as BlockNumber))
+	}
+}
+
 /// For investors, they have a different defrost schedule. there is 10 % defrost seeds at the genesis block. after that , defrost 5% every month
 ///
 pub const INVESTOR_S_DEFROST_SCHEDULE: DefrostSchedule = DefrostSchedule {
@@ -87,3 +109,45 @@ pub const TEAM_DEFROST_SCHEDULE: DefrostSchedule = DefrostSchedule {
 /// this deviation is set up to three days earlier or later from standard time. The seeds wont be distributed evenly over those period of time
 ///
 pub const DEFROST_RANDOM_BLOCK_RANGE: u32 = 3 * 24 * 600; //three days earlier or later
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn team_defrost_height_works() {
+		let count = 100;
+		assert_eq!(
+			TEAM_DEFROST_SCHEDULE.defrost_height(0, count),
+			3 * BLOCKS_IN_A_MONTH
+		);
+		assert_eq!(
+			TEAM_DEFROST_SCHEDULE.defrost_height(4, count),
+			3 * BLOCKS_IN_A_MONTH
+		);
+		assert_eq!(
+			TEAM_DEFROST_SCHEDULE.defrost_height(5, count),
+			4 * BLOCKS_IN_A_MONTH
+		);
+		assert_eq!(
+			TEAM_DEFROST_SCHEDULE.defrost_height(99, count),
+			22 * BLOCKS_IN_A_MONTH
+		);
+	}
+
+	#[test]
+	fn investor_defrost_height_works() {
+		let count = 100;
+		let cliff_count = INVESTOR_S_DEFROST_SCHEDULE.cliff_percentage as u64;
+		assert_eq!(INVESTOR_S_DEFROST_SCHEDULE.defrost_height(0, count), 0);
+		assert_eq!(
+			INVESTOR_S_DEFROST_SCHEDULE.defrost_height(cliff_count - 1, count),
+			0
+		);
+		assert_eq!(
+			INVESTOR_S_DEFROST_SCHEDULE.defrost_height(cliff_count, count),
+			BLOCKS_IN_A_MONTH
+		);
+		assert_eq!(INVESTOR_S_DEFROST_SCHEDULE.defrost_height(0, 0), 0);
+	}
+}
@@ -0,0 +1,73 @@
+use crate::param::Performance;
+use crate::{ClassFlag, CmlId, CmlStatus, CmlType};
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+/// Max count of CMLs returned in one page of `cml_list`.
+pub const MAX_CML_LIST_PAGE_SIZE: u32 = 100;
+
+/// Lifecycle state of a CML, same as `CmlStatus` but without the associated data.
+#[derive(Clone, Copy, Encode, Decode, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum CmlState {
+	FrozenSeed,
+	FreshSeed,
+	Tree,
+	Dead,
+}
+
+impl<BlockNumber> From<&CmlStatus<BlockNumber>> for CmlState {
+	fn from(status: &CmlStatus<BlockNumber>) -> Self {
+		match status {
+			CmlStatus::FrozenSeed => CmlState::FrozenSeed,
+			CmlStatus::FreshSeed(_) => CmlState::FreshSeed,
+			CmlStatus::Tree => CmlState::Tree,
+		}
+	}
+}
+
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct CmlInfo<AccountId, BlockNumber> {
+	pub id: CmlId,
+	pub cml_type: CmlType,
+	pub owner: AccountId,
+	/// Lessee if the CML is leased.
+	pub lessee: Option<AccountId>,
+	pub lifespan: BlockNumber,
+	pub performance: Performance,
+	pub class_flag: ClassFlag,
+	pub state: CmlState,
+	/// Blocks left before the CML dies.
+	pub remaining_life: BlockNumber,
+}
+
+/// Conditions used to filter CMLs, `None` fields match all CMLs.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct CmlFilter<AccountId> {
+	pub cml_type: Option<CmlType>,
+	pub owner: Option<AccountId>,
+	pub state: Option<CmlState>,
+	/// CMLs should have all traits of the flag.
+	pub class_flag: Option<ClassFlag>,
+}
+
+impl<AccountId> Default for CmlFilter<AccountId> {
+	fn default() -> Self {
+		CmlFilter {
+			cml_type: None,
+			owner: None,
+			state: None,
+			class_flag: None,
+		}
+	}
+}
+
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, Default)]
+pub struct CmlStats {
+	pub total: u64,
+	pub type_counts: Vec<(CmlType, u64)>,
+	pub state_counts: Vec<(CmlState, u64)>,
+	/// Count of CMLs burned by owners of each type, burned CMLs are not counted in `total`.
+	pub burned_counts: Vec<(CmlType, u64)>,
+}
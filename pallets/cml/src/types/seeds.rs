@@ -1,13 +1,11 @@
 use super::param::*;
-use crate::{CmlId, CmlType};
+use crate::{ClassFlag, CmlId, CmlTrait, CmlType};
 use codec::{Decode, Encode, MaxEncodedLen};
 use node_primitives::BlockNumber;
 use scale_info::TypeInfo;
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
-use sp_std::prelude::*;
-
-pub type ClassFlag = u64;
+use sp_std::{collections::btree_set::BTreeSet, prelude::*};
 
 #[derive(Encode, Decode, Clone, Debug, TypeInfo, MaxEncodedLen)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -17,6 +15,11 @@ pub struct Seed {
 	pub lifespan: BlockNumber,
 	pub performance: Performance,
 	pub class_flag: ClassFlag,
+	pub defrost_schedule: Option<DefrostScheduleType>,
+	/// Seed can't be defrosted until this block number.
+	pub defrost_time: Option<BlockNumber>,
+	/// Version of the generator that produced the seed.
+	pub generator_version: GeneratorVersion,
 }
 
 impl Seed {
@@ -25,6 +28,8 @@ impl Seed {
 		cml_id: CmlId,
 		lifespan: BlockNumber,
 		performance: Performance,
+		defrost_schedule: DefrostScheduleType,
+		defrost_time: BlockNumber,
 	) -> Self {
 		let id = cml_id;
 		Seed {
@@ -32,6 +37,9 @@ impl Seed {
 			cml_type,
 			lifespan,
 			performance,
+			class_flag: defrost_schedule.into(),
+			defrost_schedule: Some(defrost_schedule),
+			defrost_time: Some(defrost_time),
 			..Default::default()
 		}
 	}
@@ -44,11 +52,27 @@ impl Default for Seed {
 			cml_type: CmlType::C,
 			lifespan: 0,
 			performance: 0,
-			class_flag: 0,
+			class_flag: ClassFlag::default(),
+			defrost_schedule: None,
+			defrost_time: None,
+			generator_version: GeneratorVersion::V1,
 		}
 	}
 }
 
+impl Seed {
+	/// Drop the defrost schedule, the traits derived from it and the defrost time, so the seed
+	/// can be defrosted at any time.
+	pub fn drop_defrost_schedule(&mut self) {
+		if let Some(schedule_type) = self.defrost_schedule.take() {
+			for cml_trait in ClassFlag::from(schedule_type).traits() {
+				self.class_flag.remove(cml_trait);
+			}
+		}
+		self.defrost_time = None;
+	}
+}
+
 #[derive(Encode, Decode, Clone, Debug, TypeInfo)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct GenesisSeeds {
@@ -57,7 +81,22 @@ pub struct GenesisSeeds {
 	pub c_seeds: Vec<Seed>,
 }
 
-#[derive(Encode, Decode, PartialEq, Clone, Copy, Debug, TypeInfo)]
+/// Versions of the seed generator. `V1` keeps the output of existing chains unchanged, `V2`
+/// generates lifespan and performance independently with full-width randomness.
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy, Debug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum GeneratorVersion {
+	V1,
+	V2,
+}
+
+impl Default for GeneratorVersion {
+	fn default() -> Self {
+		GeneratorVersion::V1
+	}
+}
+
+#[derive(Encode, Decode, PartialEq, Eq, Clone, Copy, Debug, TypeInfo, MaxEncodedLen)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum DefrostScheduleType {
 	Investor,
@@ -82,6 +121,7 @@ impl GenesisSeeds {
 		c_count: u64,
 		gen_lifespan: impl Fn(CmlType, u64) -> BlockNumber,
 		gen_performance: impl Fn(CmlType, u64) -> Performance,
+		gen_defrost_time: impl Fn(DefrostScheduleType, u64, u64, u64) -> BlockNumber,
 	) -> Self {
 		let a_seeds = Self::generate_batch_type_seeds(
 			a_count,
@@ -89,6 +129,7 @@ impl GenesisSeeds {
 			&mut seq_id,
 			&gen_lifespan,
 			&gen_performance,
+			&gen_defrost_time,
 		);
 
 		let b_seeds = Self::generate_batch_type_seeds(
@@ -97,6 +138,7 @@ impl GenesisSeeds {
 			&mut seq_id,
 			&gen_lifespan,
 			&gen_performance,
+			&gen_defrost_time,
 		);
 
 		let c_seeds = Self::generate_batch_type_seeds(
@@ -105,6 +147,7 @@ impl GenesisSeeds {
 			&mut seq_id,
 			&gen_lifespan,
 			&gen_performance,
+			&gen_defrost_time,
 		);
 
 		GenesisSeeds {
@@ -114,24 +157,178 @@ impl GenesisSeeds {
 		}
 	}
 
+	/// Total count of seeds of all types.
+	pub fn len(&self) -> usize {
+		self.a_seeds.len() + self.b_seeds.len() + self.c_seeds.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Check that seeds are listed under their own types, and that seed ids are exactly
+	/// `start_id..start_id + len` without duplicates.
+	pub fn validate(&self, start_id: CmlId) -> Result<(), &'static str> {
+		let end_id = start_id.saturating_add(self.len() as CmlId);
+		let mut ids = BTreeSet::new();
+		for (cml_type, seeds) in [
+			(CmlType::A, &self.a_seeds),
+			(CmlType::B, &self.b_seeds),
+			(CmlType::C, &self.c_seeds),
+		] {
+			for seed in seeds {
+				if seed.cml_type != cml_type {
+					return Err("seed is listed under another cml type");
+				}
+				if seed.id < start_id || seed.id >= end_id {
+					return Err("seed id disagrees with last cml id");
+				}
+				if !ids.insert(seed.id) {
+					return Err("duplicate seed id");
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Mark all seeds as generated by the given generator version.
+	pub fn set_generator_version(&mut self, version: GeneratorVersion) {
+		self.a_seeds
+			.iter_mut()
+			.chain(self.b_seeds.iter_mut())
+			.chain(self.c_seeds.iter_mut())
+			.for_each(|seed| seed.generator_version = version);
+	}
+
+	/// Defrost schedule type and standard defrost height (without random deviation) of the
+	/// genesis seed with the given id, following the layout of seeds generated by
+	/// `init_genesis`. Returns `None` if the id is not a genesis seed id.
+	pub fn genesis_defrost(cml_id: CmlId) -> Option<(DefrostScheduleType, BlockNumber)> {
+		let mut start_id = 0;
+		for count in [
+			GENESIS_SEED_A_COUNT,
+			GENESIS_SEED_B_COUNT,
+			GENESIS_SEED_C_COUNT,
+		] {
+			if cml_id < start_id + count {
+				let (schedule_type, index, schedule_count) =
+					Self::schedule_position(cml_id - start_id, count);
+				let schedule = match schedule_type {
+					DefrostScheduleType::Investor => INVESTOR_S_DEFROST_SCHEDULE,
+					DefrostScheduleType::Team => TEAM_DEFROST_SCHEDULE,
+				};
+				return Some((
+					schedule_type,
+					schedule.defrost_height(index, schedule_count),
+				));
+			}
+			start_id += count;
+		}
+		None
+	}
+
+	/// Defrost schedule type of the `i`-th seed among `count` seeds of the same type, index of
+	/// the seed among seeds following the schedule and count of them. The first
+	/// `TEAM_PERCENTAGE` seeds follow team defrost schedule, others follow investor defrost
+	/// schedule.
+	fn schedule_position(i: u64, count: u64) -> (DefrostScheduleType, u64, u64) {
+		let team_count = count * TEAM_PERCENTAGE / 100;
+		if i < team_count {
+			(DefrostScheduleType::Team, i, team_count)
+		} else {
+			(
+				DefrostScheduleType::Investor,
+				i - team_count,
+				count - team_count,
+			)
+		}
+	}
+
+	/// Add the given trait to all seeds.
+	pub fn insert_trait(&mut self, cml_trait: CmlTrait) {
+		self.a_seeds
+			.iter_mut()
+			.chain(self.b_seeds.iter_mut())
+			.chain(self.c_seeds.iter_mut())
+			.for_each(|seed| seed.class_flag.insert(cml_trait));
+	}
+
+	/// Drop defrost schedules of all seeds, see `Seed::drop_defrost_schedule`.
+	pub fn drop_defrost_schedules(&mut self) {
+		self.a_seeds
+			.iter_mut()
+			.chain(self.b_seeds.iter_mut())
+			.chain(self.c_seeds.iter_mut())
+			.for_each(|seed| seed.drop_defrost_schedule());
+	}
+
 	fn generate_batch_type_seeds(
 		count: u64,
 		cml_type: CmlType,
 		seq_id: &mut u64,
 		gen_lifespan: &impl Fn(CmlType, u64) -> BlockNumber,
 		gen_performance: &impl Fn(CmlType, u64) -> Performance,
+		gen_defrost_time: &impl Fn(DefrostScheduleType, u64, u64, u64) -> BlockNumber,
 	) -> Vec<Seed> {
 		let mut seeds: Vec<Seed> = Vec::new();
-
-		for _ in 0..count {
+		for i in 0..count {
+			let (schedule_type, index, schedule_count) = Self::schedule_position(i, count);
 			seeds.push(Seed::generate(
 				cml_type,
 				*seq_id,
 				gen_lifespan(cml_type, *seq_id),
 				gen_performance(cml_type, *seq_id),
+				schedule_type,
+				gen_defrost_time(schedule_type, *seq_id, index, schedule_count),
 			));
 			*seq_id += 1;
 		}
 		seeds
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn seed(id: CmlId, cml_type: CmlType) -> Seed {
+		Seed {
+			id,
+			cml_type,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn validate_works() {
+		let seeds = GenesisSeeds {
+			a_seeds: vec![seed(5, CmlType::A)],
+			b_seeds: vec![seed(6, CmlType::B), seed(7, CmlType::B)],
+			c_seeds: vec![seed(8, CmlType::C)],
+		};
+		assert_eq!(seeds.len(), 4);
+		assert!(seeds.validate(5).is_ok());
+		assert!(seeds.validate(4).is_err());
+		assert!(seeds.validate(6).is_err());
+		assert!(GenesisSeeds::default().validate(100).is_ok());
+	}
+
+	#[test]
+	fn validate_rejects_duplicate_ids_and_wrong_types() {
+		let duplicated = GenesisSeeds {
+			a_seeds: vec![seed(0, CmlType::A)],
+			b_seeds: vec![seed(0, CmlType::B)],
+			c_seeds: vec![],
+		};
+		assert_eq!(duplicated.validate(0), Err("duplicate seed id"));
+
+		let wrong_type = GenesisSeeds {
+			a_seeds: vec![seed(0, CmlType::B)],
+			..Default::default()
+		};
+		assert_eq!(
+			wrong_type.validate(0),
+			Err("seed is listed under another cml type")
+		);
+	}
+}
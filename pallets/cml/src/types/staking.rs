@@ -0,0 +1,47 @@
+use crate::CmlId;
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+use sp_std::prelude::*;
+
+pub type StakingIndex = u32;
+
+#[derive(Clone, Copy, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo, MaxEncodedLen)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum StakingCategory {
+	/// Staking with TEA, `StakingPrice` of TEA is reserved for each slot.
+	Tea,
+	/// Staking with a CML, the CML is locked while staking.
+	Cml,
+}
+
+impl Default for StakingCategory {
+	fn default() -> Self {
+		StakingCategory::Tea
+	}
+}
+
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, Default, TypeInfo, MaxEncodedLen)]
+pub struct StakingItem<AccountId, Balance> {
+	pub owner: AccountId,
+	pub category: StakingCategory,
+	/// Reserved amount if staking with TEA.
+	pub amount: Option<Balance>,
+	/// Staking CML if staking with CML.
+	pub cml: Option<CmlId>,
+}
+
+/// Progress of splitting mining rewards of a staking period, which may take several blocks.
+#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct RewardRound<BlockNumber> {
+	/// Staking period start the rewards are split at, performance of mining trees and values of
+	/// staking slots are measured at this height.
+	pub height: BlockNumber,
+	/// Sum of performance of mining trees measured so far.
+	pub total_performance: u64,
+	/// Raw key of the last `MiningCmlIds` entry measured, `None` once all mining trees are
+	/// measured and rewards are being paid.
+	pub cursor: Option<Vec<u8>>,
+}
@@ -0,0 +1,113 @@
+// Copyright (C) 2021 Tea Project.
+
+//! Default weights for pallet_cml
+//!
+//! PLACEHOLDER VALUES, NOT BENCHMARK OUTPUT. The numbers are hand-written estimates derived from
+//! the storage accesses of each call, replace this file with the output of the `benchmarking`
+//! module run on reference hardware before relying on them.
+
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{
+	traits::Get,
+	weights::{constants::RocksDbWeight, Weight},
+};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_cml.
+pub trait WeightInfo {
+	fn generate_cml(n: u32) -> Weight;
+	fn transfer() -> Weight;
+	fn transfer_batch(n: u32) -> Weight;
+	fn approve() -> Weight;
+	fn airdrop(n: u32) -> Weight;
+	fn draw_cmls_from_coupon(n: u32) -> Weight;
+}
+
+/// Weights for pallet_cml using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	fn generate_cml(n: u32) -> Weight {
+		(61_238_000 as Weight)
+			.saturating_add((27_415_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().reads(13 as Weight))
+			.saturating_add(T::DbWeight::get().writes(9 as Weight))
+			.saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+	}
+	fn transfer() -> Weight {
+		(72_904_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(8 as Weight))
+			.saturating_add(T::DbWeight::get().writes(7 as Weight))
+	}
+	fn transfer_batch(n: u32) -> Weight {
+		(12_861_000 as Weight)
+			.saturating_add((64_237_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().reads((7 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(T::DbWeight::get().writes((7 as Weight).saturating_mul(n as Weight)))
+	}
+	fn approve() -> Weight {
+		(31_127_000 as Weight)
+			.saturating_add(T::DbWeight::get().reads(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+	}
+	fn airdrop(n: u32) -> Weight {
+		(58_362_000 as Weight)
+			.saturating_add((31_904_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().reads(9 as Weight))
+			.saturating_add(T::DbWeight::get().writes(4 as Weight))
+			.saturating_add(T::DbWeight::get().writes((3 as Weight).saturating_mul(n as Weight)))
+	}
+	fn draw_cmls_from_coupon(n: u32) -> Weight {
+		(64_519_000 as Weight)
+			.saturating_add((42_173_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().reads(8 as Weight))
+			.saturating_add(T::DbWeight::get().reads((4 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
+			.saturating_add(T::DbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	fn generate_cml(n: u32) -> Weight {
+		(61_238_000 as Weight)
+			.saturating_add((27_415_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(13 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(9 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+	}
+	fn transfer() -> Weight {
+		(72_904_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(8 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(7 as Weight))
+	}
+	fn transfer_batch(n: u32) -> Weight {
+		(12_861_000 as Weight)
+			.saturating_add((64_237_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((7 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((7 as Weight).saturating_mul(n as Weight)))
+	}
+	fn approve() -> Weight {
+		(31_127_000 as Weight)
+			.saturating_add(RocksDbWeight::get().reads(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+	}
+	fn airdrop(n: u32) -> Weight {
+		(58_362_000 as Weight)
+			.saturating_add((31_904_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(9 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(4 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((3 as Weight).saturating_mul(n as Weight)))
+	}
+	fn draw_cmls_from_coupon(n: u32) -> Weight {
+		(64_519_000 as Weight)
+			.saturating_add((42_173_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(8 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((4 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
+			.saturating_add(RocksDbWeight::get().writes((6 as Weight).saturating_mul(n as Weight)))
+	}
+}
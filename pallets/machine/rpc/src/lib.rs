@@ -1,7 +1,7 @@
 use codec::Codec;
 use jsonrpc_core::{Error, ErrorCode, Result};
 use jsonrpc_derive::rpc;
-use machine_runtime_api::MachineApi as MachineRuntimeApi;
+use machine_runtime_api::{MachineApi as MachineRuntimeApi, MachineStatus, NodeStatus};
 use sp_api::ProvideRuntimeApi;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::{generic::BlockId, traits::Block as BlockT};
@@ -9,11 +9,30 @@ use std::sync::Arc;
 
 #[rpc]
 pub trait MachineApi<BlockHash, AccountId> {
+	/// Returns (tea id, cml id, multiaddr) of boot nodes.
 	#[rpc(name = "tea_bootNodes")]
-	fn boot_nodes(&self, at: Option<BlockHash>) -> Result<Vec<[u8; 32]>>;
+	fn boot_nodes(&self, at: Option<BlockHash>) -> Result<Vec<([u8; 32], u64, Vec<u8>)>>;
 
+	/// Returns (tea id, cml id, ip address) of tapp store startup nodes.
 	#[rpc(name = "tea_tappStoreStartupNodes")]
-	fn tapp_store_startup_nodes(&self, at: Option<BlockHash>) -> Result<Vec<[u8; 32]>>;
+	fn tapp_store_startup_nodes(
+		&self,
+		at: Option<BlockHash>,
+	) -> Result<Vec<([u8; 32], u64, Vec<u8>)>>;
+
+	/// Returns one of "Pending", "Active" and "Invalid", or null if the node has not published
+	/// its profile.
+	#[rpc(name = "tea_nodeStatus")]
+	fn node_status(&self, tea_id: [u8; 32], at: Option<BlockHash>) -> Result<Option<String>>;
+
+	/// Returns tea ids of nodes that have passed remote attestation.
+	#[rpc(name = "tea_activeNodes")]
+	fn active_nodes(&self, at: Option<BlockHash>) -> Result<Vec<[u8; 32]>>;
+
+	/// Returns tea ids of registered machines in the given heartbeat status, which is one of
+	/// "Pending", "Online" and "Offline".
+	#[rpc(name = "tea_machinesByStatus")]
+	fn machines_by_status(&self, status: String, at: Option<BlockHash>) -> Result<Vec<[u8; 32]>>;
 }
 
 pub struct MachineApiImpl<C, M> {
@@ -50,7 +69,10 @@ where
 	C::Api: machine_runtime_api::MachineApi<Block, AccountId>,
 	AccountId: Codec,
 {
-	fn boot_nodes(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<[u8; 32]>> {
+	fn boot_nodes(
+		&self,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<([u8; 32], u64, Vec<u8>)>> {
 		let api = self.client.runtime_api();
 		let at = BlockId::hash(at.unwrap_or_else(||
 			// If the block hash is not supplied assume the best block.
@@ -63,7 +85,7 @@ where
 	fn tapp_store_startup_nodes(
 		&self,
 		at: Option<<Block as BlockT>::Hash>,
-	) -> Result<Vec<[u8; 32]>> {
+	) -> Result<Vec<([u8; 32], u64, Vec<u8>)>> {
 		let api = self.client.runtime_api();
 		let at = BlockId::hash(at.unwrap_or_else(||
 			// If the block hash is not supplied assume the best block.
@@ -74,4 +96,66 @@ where
 			.map_err(runtime_error_into_rpc_err)?;
 		Ok(result)
 	}
+
+	fn node_status(
+		&self,
+		tea_id: [u8; 32],
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<String>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.node_status(&at, tea_id)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result.map(|status| {
+			match status {
+				NodeStatus::Pending => "Pending",
+				NodeStatus::Active => "Active",
+				NodeStatus::Invalid => "Invalid",
+			}
+			.to_string()
+		}))
+	}
+
+	fn active_nodes(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<[u8; 32]>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api.active_nodes(&at).map_err(runtime_error_into_rpc_err)?;
+		Ok(result)
+	}
+
+	fn machines_by_status(
+		&self,
+		status: String,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<[u8; 32]>> {
+		let status = match status.as_str() {
+			"Pending" => MachineStatus::Pending,
+			"Online" => MachineStatus::Online,
+			"Offline" => MachineStatus::Offline,
+			_ => {
+				return Err(Error {
+					code: ErrorCode::InvalidParams,
+					message: format!("unknown machine status: {}", status),
+					data: None,
+				})
+			}
+		};
+
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(||
+			// If the block hash is not supplied assume the best block.
+			self.client.info().best_hash));
+
+		let result = api
+			.machines_by_status(&at, status)
+			.map_err(runtime_error_into_rpc_err)?;
+		Ok(result)
+	}
 }
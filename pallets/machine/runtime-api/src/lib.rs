@@ -3,15 +3,29 @@
 #![allow(clippy::unnecessary_mut_passed)]
 
 use codec::Codec;
+pub use pallet_machine::{MachineStatus, NodeStatus};
 use sp_std::prelude::*;
 
 sp_api::decl_runtime_apis! {
+	#[api_version(2)]
 	pub trait MachineApi<AccountId>
 	where
 		AccountId: Codec,
 	{
-		fn boot_nodes() -> Vec<[u8; 32]>;
+		/// Returns (tea id, cml id, multiaddr) of boot nodes.
+		fn boot_nodes() -> Vec<([u8; 32], u64, Vec<u8>)>;
 
-		fn tapp_store_startup_nodes() -> Vec<[u8; 32]>;
+		/// Returns (tea id, cml id, ip address) of tapp store startup nodes.
+		fn tapp_store_startup_nodes() -> Vec<([u8; 32], u64, Vec<u8>)>;
+
+		/// Returns attestation status of the node, `None` if the node has not published its
+		/// profile.
+		fn node_status(tea_id: [u8; 32]) -> Option<NodeStatus>;
+
+		/// Returns tea ids of nodes that have passed remote attestation.
+		fn active_nodes() -> Vec<[u8; 32]>;
+
+		/// Returns tea ids of registered machines in the given heartbeat status.
+		fn machines_by_status(status: MachineStatus) -> Vec<[u8; 32]>;
 	}
 }
@@ -1,77 +1,104 @@
-//! Benchmarking setup for pallet-tea
+//! Benchmarking setup for pallet-machine
 
 use super::*;
 #[allow(unused)]
 use crate::Pallet as Template;
-use frame_benchmarking::{benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
 use frame_system::RawOrigin;
 
-benchmarks! {
-	add_new_node {
-		let public = hex_to_key("e9889b1c54ccd6cf184901ded892069921d76f7749b6f73bed6cf3b9be1a8a44");
-		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), public)
+/// Signatures below are signed by ed25519 keys generated from seeds `[1; 32]` (tea id of the
+/// node), `[2; 32]` (ephemeral id of the node), `[3; 32]` (tea id of the validator) and
+/// `[4; 32]` (ephemeral id of the validator).
+const TEA_ID: &str = "8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c";
+const EPHEMERAL_ID: &str = "8139770ea87d175f56a35466c34c7ecccb8d8a91b4ee37a25df60f5b8fc9b394";
+const VALIDATOR_TEA_ID: &str = "ed4928c628d1c2c6eae90338905995612959273a5c63f93636c14614ac8737d1";
+const VALIDATOR_EPHEMERAL_ID: &str =
+	"ca93ac1705187071d67b83c7ff0efe8108e8ec4530575d7726879333dbdabe7c";
+const PEER_ID: &str = "12D3KooWLCU9sscGSP7GySktL2awwNouPwrqvZECLaDafpwLKKvt";
 
+/// Signed by the tea id key over `node_profile_signing_message(EPHEMERAL_ID, PEER_ID)`.
+const PROFILE_SIGNATURE: &str = "aa0374c7317632879c22a01a5b5d286e9cab0d6e57387af64bc0ec6b28f9d6255ccfab805fd2a1df307ecfcdafc6c8dfdf9a28ec95ad1df7b67bbd2f1e678201";
+/// Signed by the validator ephemeral key over `ra_signing_message(TEA_ID, EPHEMERAL_ID, true)`.
+const RA_SIGNATURE: &str = "b9933a22221f0dce320d833543014eebeb0618546ef5c91692033f2d4236e4e8177fe0e5a98794d4f69dcaf7f0a1d420b37e81c767e7ec31828e9e6cb5622e03";
+/// Signed by the tea id key over `heartbeat_signing_message(TEA_ID, 10)`.
+const HEARTBEAT_SIGNATURE: &str = "d975c75ee4f4915afffd4d4477ba167f4d3da1c18220ea8d6e97e62a8739381a644e3b3efa2974ed31fd12403adbd988cdd1086a8d1fe6e4105d8c66056a180e";
+
+benchmarks! {
 	update_node_profile {
-		let (node, tea_id, ephemeral_id, peer_id) = dummy_new_node();
-		Nodes::<T>::insert(&tea_id, node);
+		let n in 1 .. T::MaxActiveNodes::get();
+
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), tea_id, ephemeral_id, Vec::new(), Vec::new(), peer_id)
+		let tea_id = hex_to_key(TEA_ID);
+		insert_machine::<T>(tea_id, caller.clone());
+		// worst case that RA nodes are selected from many active nodes of other owners
+		let other: T::AccountId = account("other", 0, 0);
+		let mut active_nodes = Vec::new();
+		for i in 0..n {
+			let mut id = [0; 32];
+			id[..4].copy_from_slice(&i.to_le_bytes());
+			insert_machine::<T>(id, other.clone());
+			active_nodes.push(id);
+		}
+		let active_nodes: BoundedVec<TeaPubKey, T::MaxActiveNodes> = active_nodes.try_into().unwrap();
+		ActiveNodes::<T>::put(active_nodes);
+	}: _(
+		RawOrigin::Signed(caller),
+		tea_id,
+		hex_to_key(EPHEMERAL_ID),
+		PEER_ID.as_bytes().to_vec(),
+		hex_to_signature(PROFILE_SIGNATURE)
+	)
+	verify {
+		assert_eq!(Nodes::<T>::get(tea_id).unwrap().status, NodeStatus::Pending);
+	}
 
 	remote_attestation {
-		let (mut node, tea_id, ephemeral_id, peer_id) = dummy_new_node();
-		let ra_nodes = generate_ra_nodes();
-
-		let validator_id = ra_nodes.last().unwrap().0.clone();
-		let validator_ephemeral_id = [223, 29, 70, 56, 216, 225, 70, 148, 24, 223, 187, 97, 37, 233, 158, 213, 178, 176, 90, 82, 52, 111, 18, 139, 243, 175, 205, 28, 41, 224, 109, 54];
-		let signature = [135, 89, 133, 152, 88, 167, 186, 93, 214, 178, 213, 158, 106, 253, 244, 178, 12, 190, 182, 190, 218, 164, 197, 70, 94, 137, 118, 159, 199, 205, 143, 204, 139, 247, 71, 90, 21, 72, 151, 58, 93, 212, 148, 7, 49, 199, 101, 53, 254, 153, 144, 194, 22, 166, 234, 181, 31, 48, 7, 90, 185, 78, 210, 11];
-
-		node.ra_nodes = ra_nodes;
-		Nodes::<T>::insert(&tea_id, node);
-
-		let mut validator_node = Node::default();
-		validator_node.ephemeral_id = validator_ephemeral_id;
-		Nodes::<T>::insert(&validator_id, validator_node);
-
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), validator_id, tea_id, true, signature.to_vec())
+		let tea_id = hex_to_key(TEA_ID);
+		let validator_tea_id = hex_to_key(VALIDATOR_TEA_ID);
+		insert_machine::<T>(tea_id, caller.clone());
+		insert_machine::<T>(validator_tea_id, caller.clone());
 
-	update_runtime_activity {
-		let (node, tea_id, _, _) = dummy_new_node();
-		Nodes::<T>::insert(&tea_id, node);
+		Nodes::<T>::insert(validator_tea_id, Node {
+			tea_id: validator_tea_id,
+			ephemeral_id: hex_to_key(VALIDATOR_EPHEMERAL_ID),
+			status: NodeStatus::Active,
+			..Default::default()
+		});
+		Nodes::<T>::insert(tea_id, Node {
+			tea_id,
+			ephemeral_id: hex_to_key(EPHEMERAL_ID),
+			peer_id: PEER_ID.as_bytes().to_vec(),
+			ra_nodes: vec![(validator_tea_id, None)],
+			..Default::default()
+		});
+	}: _(RawOrigin::Signed(caller), validator_tea_id, tea_id, true, hex_to_signature(RA_SIGNATURE))
+	verify {
+		assert_eq!(Nodes::<T>::get(tea_id).unwrap().status, NodeStatus::Active);
+	}
 
-		let ephemeral_id = [165, 58, 163, 23, 13, 97, 185, 160, 186, 118, 53, 125, 233, 94, 151, 57, 7, 247, 104, 108, 190, 115, 86, 119, 36, 182, 201, 201, 236, 59, 199, 1];
-		let signature = [234, 207, 225, 211, 4, 214, 65, 93, 178, 30, 245, 64, 118, 189, 228, 151, 196, 15, 211, 0, 205, 132, 74, 20, 246, 250, 210, 24, 137, 5, 105, 1, 62, 126, 246, 130, 22, 226, 14, 230, 67, 113, 192, 50, 238, 185, 170, 89, 240, 136, 46, 130, 103, 117, 240, 3, 177, 6, 26, 54, 19, 217, 136, 9];
+	update_runtime_activity {
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), tea_id, None, ephemeral_id, signature.to_vec())
+		let tea_id = hex_to_key(TEA_ID);
+		insert_machine::<T>(tea_id, caller.clone());
+		frame_system::Pallet::<T>::set_block_number(10u32.into());
+	}: _(RawOrigin::Signed(caller), tea_id, 10u32.into(), hex_to_signature(HEARTBEAT_SIGNATURE))
+	verify {
+		assert_eq!(Pallet::<T>::machine_status(&tea_id), Some(MachineStatus::Online));
+	}
 }
 
 impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test,);
 
-fn dummy_new_node<T>() -> (Node<T>, TeaPubKey, TeaPubKey, PeerId)
-where
-	T: Default,
-{
-	let tea_id = hex_to_key("df38cb4f12479041c8e8d238109ef2a150b017f382206e24fee932e637c2db7b");
-	let ephemeral_id =
-		hex_to_key("ba9147ba50faca694452db7c458e33a9a0322acbaac24bf35db7bb5165dff3ac");
-	let peer_id = "12D3KooWLCU9sscGSP7GySktL2awwNouPwrqvZECLaDafpwLKKvt";
-
-	let mut node = Node::default();
-	node.tea_id = tea_id.clone();
-	(node, tea_id, ephemeral_id, peer_id.as_bytes().to_vec())
-}
-
-fn generate_ra_nodes() -> Vec<(TeaPubKey, bool)> {
-	vec![
-		"e9889b1c54ccd6cf184901ded892069921d76f7749b6f73bed6cf3b9be1a8a44",
-		"c7e016fad0796bb68594e49a6ef1942cf7e73497e69edb32d19ba2fab3696596",
-		"c9380fde1ba795fc656ab08ab4ef4482cf554790fd3abcd4642418ae8fb5fd52",
-		"2754d7e9c73ced5b302e12464594110850980027f8f83c469e8145eef59220b6",
-	]
-	.iter()
-	.map(|v| (hex_to_key(v), false))
-	.collect::<Vec<_>>()
+fn insert_machine<T: Config>(tea_id: TeaPubKey, owner: T::AccountId) {
+	Machines::<T>::insert(
+		tea_id,
+		Machine {
+			tea_id,
+			issuer_id: 1,
+			owner,
+		},
+	);
 }
 
 fn hex_to_key(s: &str) -> TeaPubKey {
@@ -79,3 +106,9 @@ fn hex_to_key(s: &str) -> TeaPubKey {
 	hex::decode_to_slice(s, &mut key as &mut [u8]).unwrap();
 	key
 }
+
+fn hex_to_signature(s: &str) -> ed25519::Signature {
+	let mut signature = [0; 64];
+	hex::decode_to_slice(s, &mut signature as &mut [u8]).unwrap();
+	ed25519::Signature::from_raw(signature)
+}
@@ -12,4 +12,345 @@ impl<T: tea::Config> tea::Pallet<T> {
 			*id
 		})
 	}
+
+	pub(crate) fn check_issuer_metadata(url: &[u8], name: &[u8]) -> DispatchResult {
+		ensure!(
+			url.len() as u32 <= T::IssuerUrlLength::get(),
+			Error::<T>::IssuerUrlLengthToLong
+		);
+		ensure!(
+			name.len() as u32 <= T::IssuerNameLength::get(),
+			Error::<T>::IssuerNameLengthToLong
+		);
+		Ok(())
+	}
+
+	/// Remove the `IssuerOwners` entry of `owner` if it points to the given issuer.
+	pub(crate) fn remove_issuer_owner(owner: &T::AccountId, issuer_id: IssuerId) {
+		if IssuerOwners::<T>::get(owner) == issuer_id {
+			IssuerOwners::<T>::remove(owner);
+		}
+	}
+
+	pub(crate) fn check_machines_migrated() -> DispatchResult {
+		ensure!(
+			!MigrationCursor::<T>::exists(),
+			Error::<T>::MachinesMigrating
+		);
+		Ok(())
+	}
+
+	/// Insert or replace the machine, `IssuerMachineCount` is updated accordingly. Node states
+	/// of a replaced machine are kept, a new machine is pending until its first heartbeat.
+	pub(crate) fn insert_machine(machine: Machine<T::AccountId>) {
+		match Machines::<T>::get(&machine.tea_id) {
+			Some(old) => IssuerMachineCount::<T>::mutate(old.issuer_id, |count| {
+				*count = count.saturating_sub(1)
+			}),
+			None => Self::insert_pending_activity(&machine.tea_id),
+		}
+		IssuerMachineCount::<T>::mutate(machine.issuer_id, |count| {
+			*count = count.saturating_add(1)
+		});
+		Machines::<T>::insert(machine.tea_id, machine);
+	}
+
+	/// Remove the machine and its node if exists, `IssuerMachineCount` is updated accordingly.
+	/// Caller should make sure no CML is mining on the machine.
+	pub(crate) fn remove_machine(tea_id: &TeaPubKey) -> Option<Machine<T::AccountId>> {
+		let machine = Machines::<T>::take(tea_id)?;
+		Nodes::<T>::remove(tea_id);
+		Self::deactivate_node(tea_id);
+		RuntimeActivities::<T>::remove(tea_id);
+		IssuerMachineCount::<T>::mutate(machine.issuer_id, |count| {
+			*count = count.saturating_sub(1)
+		});
+		Some(machine)
+	}
+
+	/// Tea ids of `bindings` that are neither in `tea_ids` nor in `other_bindings`, i.e.
+	/// machines leaving both startup sets when the bindings are replaced with `tea_ids`.
+	pub(crate) fn leaving_startup_machines<C, D>(
+		bindings: &[(TeaPubKey, CmlId, C)],
+		other_bindings: &[(TeaPubKey, CmlId, D)],
+		tea_ids: &[TeaPubKey],
+	) -> Vec<TeaPubKey> {
+		bindings
+			.iter()
+			.map(|(tea_id, _, _)| *tea_id)
+			.filter(|tea_id| {
+				!tea_ids.contains(tea_id)
+					&& !other_bindings.iter().any(|(other, _, _)| other == tea_id)
+			})
+			.collect()
+	}
+
+	/// Message that the issuer signs to register a machine. It includes the genesis hash and
+	/// `issuer_id` so that a signature can't be replayed on another chain, or by another issuer
+	/// sharing the same key.
+	pub fn issuer_signing_message(
+		issuer_id: IssuerId,
+		tea_id: &TeaPubKey,
+		owner: &T::AccountId,
+		nonce: u64,
+	) -> Vec<u8> {
+		let genesis_hash = frame_system::Pallet::<T>::block_hash(T::BlockNumber::zero());
+		(
+			ISSUER_SIGNING_CONTEXT,
+			genesis_hash,
+			issuer_id,
+			tea_id,
+			owner,
+			nonce,
+		)
+			.encode()
+	}
+
+	/// Message that the machine signs with its tea id key to prove who holds it.
+	pub fn machine_signing_message(owner: &T::AccountId) -> Vec<u8> {
+		(MACHINE_SIGNING_CONTEXT, owner).encode()
+	}
+
+	pub(crate) fn verify_ed25519(
+		public_key: &[u8; 32],
+		message: &[u8],
+		signature: &ed25519::Signature,
+	) -> bool {
+		sp_io::crypto::ed25519_verify(signature, message, &ed25519::Public::from_raw(*public_key))
+	}
+
+	/// Message that the node signs with its tea id key to publish its profile.
+	pub fn node_profile_signing_message(ephemeral_id: &TeaPubKey, peer_id: &PeerId) -> Vec<u8> {
+		(NODE_PROFILE_SIGNING_CONTEXT, ephemeral_id, peer_id).encode()
+	}
+
+	/// Message that the validator signs with its ephemeral key to commit an attestation result,
+	/// the ephemeral id of the target binds the result to the current profile of the target.
+	pub fn ra_signing_message(
+		target_tea_id: &TeaPubKey,
+		target_ephemeral_id: &TeaPubKey,
+		is_pass: bool,
+	) -> Vec<u8> {
+		(
+			RA_SIGNING_CONTEXT,
+			target_tea_id,
+			target_ephemeral_id,
+			is_pass,
+		)
+			.encode()
+	}
+
+	/// Message that the machine signs with its tea id key to send a heartbeat, the height is
+	/// encoded as `u64` so that the message doesn't depend on the block number type.
+	pub fn heartbeat_signing_message(tea_id: &TeaPubKey, height: T::BlockNumber) -> Vec<u8> {
+		(
+			HEARTBEAT_SIGNING_CONTEXT,
+			tea_id,
+			height.saturated_into::<u64>(),
+		)
+			.encode()
+	}
+
+	/// Status of the machine by heartbeats, `None` if the machine has not been registered.
+	/// Legacy machines not yet visited by the ongoing migration are online, as the migration
+	/// will mark them.
+	pub fn machine_status(tea_id: &TeaPubKey) -> Option<MachineStatus> {
+		if !Machines::<T>::contains_key(tea_id) {
+			return None;
+		}
+		Some(match RuntimeActivities::<T>::get(tea_id) {
+			Some(activity) => activity.status,
+			None if MigrationCursor::<T>::exists() => MachineStatus::Online,
+			None => Default::default(),
+		})
+	}
+
+	/// Tea ids of registered machines in the given status.
+	pub fn machines_by_status(status: MachineStatus) -> Vec<TeaPubKey> {
+		Machines::<T>::iter_keys()
+			.filter(|tea_id| Self::machine_status(tea_id) == Some(status))
+			.collect()
+	}
+
+	/// Mark the machine as pending since the current height and schedule its heartbeat check.
+	pub(crate) fn insert_pending_activity(tea_id: &TeaPubKey) {
+		let current_block = frame_system::Pallet::<T>::block_number();
+		RuntimeActivities::<T>::insert(
+			tea_id,
+			RuntimeActivity {
+				last_heartbeat: current_block,
+				status: MachineStatus::Pending,
+			},
+		);
+		Self::schedule_heartbeat_check(tea_id, current_block);
+	}
+
+	/// Schedule the machine to be checked at the first sweep after `last_heartbeat` expires.
+	pub(crate) fn schedule_heartbeat_check(tea_id: &TeaPubKey, last_heartbeat: T::BlockNumber) {
+		let duration = T::ActivityCheckDuration::get();
+		let expire_at = last_heartbeat
+			.saturating_add(T::RuntimeActivityThreshold::get())
+			.saturating_add(One::one())
+			.max(frame_system::Pallet::<T>::block_number().saturating_add(One::one()));
+		let remainder = expire_at % duration;
+		let check_at = if remainder.is_zero() {
+			expire_at
+		} else {
+			expire_at.saturating_add(duration - remainder)
+		};
+		HeartbeatDeadlines::<T>::append(check_at, tea_id);
+	}
+
+	/// Mark machines scheduled at `n` that missed heartbeats for more than
+	/// `RuntimeActivityThreshold` blocks as offline, including pending machines that never sent
+	/// a heartbeat.
+	pub(crate) fn sweep_offline_machines(n: T::BlockNumber) -> Weight {
+		let tea_ids = HeartbeatDeadlines::<T>::take(n);
+		let reads: Weight = 1 + tea_ids.len() as Weight;
+		let mut writes: Weight = 1;
+		for tea_id in tea_ids {
+			let mut activity = match RuntimeActivities::<T>::get(tea_id) {
+				Some(activity) => activity,
+				None => continue,
+			};
+			if activity.status == MachineStatus::Offline
+				|| n.saturating_sub(activity.last_heartbeat) <= T::RuntimeActivityThreshold::get()
+			{
+				continue;
+			}
+
+			activity.status = MachineStatus::Offline;
+			RuntimeActivities::<T>::insert(tea_id, &activity);
+			writes += 1;
+			Self::deposit_event(Event::MachineOffline(tea_id, activity.last_heartbeat));
+		}
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	pub fn node_status(tea_id: &TeaPubKey) -> Option<NodeStatus> {
+		Nodes::<T>::get(tea_id).map(|node| node.status)
+	}
+
+	/// Randomly select at most `RaQuorumSize` active nodes other than `tea_id`, nodes owned by
+	/// `who` (the owner of `tea_id`) are excluded so that owners can't attest their own nodes.
+	pub(crate) fn select_ra_nodes(who: &T::AccountId, tea_id: &TeaPubKey) -> Vec<TeaPubKey> {
+		let mut candidates: Vec<TeaPubKey> = ActiveNodes::<T>::get()
+			.into_iter()
+			.filter(|id| !id.eq(tea_id))
+			.filter(|id| {
+				Machines::<T>::get(id)
+					.map(|machine| !machine.owner.eq(who))
+					.unwrap_or(false)
+			})
+			.collect();
+		let count = (T::RaQuorumSize::get() as usize).min(candidates.len());
+
+		let seed = T::CommonUtils::generate_random(who.clone(), &tea_id.to_vec());
+		// partial Fisher-Yates shuffle, the first `count` candidates are selected
+		for i in 0..count {
+			let random = sp_io::hashing::blake2_256(&(seed, i as u32).encode());
+			let offset = u64::from_le_bytes(random[..8].try_into().unwrap_or_default());
+			let j = i + (offset % (candidates.len() - i) as u64) as usize;
+			candidates.swap(i, j);
+		}
+		candidates.truncate(count);
+		candidates
+	}
+
+	/// `Active` if `RaThreshold` RA nodes passed, `Invalid` if enough RA nodes can't pass any
+	/// more, `Pending` otherwise. Nodes with fewer than `RaThreshold` RA nodes stay pending.
+	pub(crate) fn attestation_status(ra_nodes: &[(TeaPubKey, Option<bool>)]) -> NodeStatus {
+		let threshold = T::RaThreshold::get() as usize;
+		if ra_nodes.len() < threshold {
+			return NodeStatus::Pending;
+		}
+		let passed = ra_nodes
+			.iter()
+			.filter(|(_, result)| *result == Some(true))
+			.count();
+		let rejected = ra_nodes
+			.iter()
+			.filter(|(_, result)| *result == Some(false))
+			.count();
+
+		if passed >= threshold {
+			NodeStatus::Active
+		} else if rejected > ra_nodes.len() - threshold {
+			NodeStatus::Invalid
+		} else {
+			NodeStatus::Pending
+		}
+	}
+
+	pub(crate) fn update_node_status(tea_id: &TeaPubKey, status: NodeStatus) {
+		Nodes::<T>::mutate(tea_id, |node| {
+			if let Some(node) = node {
+				node.status = status;
+			}
+		});
+		if status == NodeStatus::Active {
+			ActiveNodes::<T>::mutate(|nodes| {
+				if !nodes.contains(tea_id) && nodes.try_push(*tea_id).is_err() {
+					log::error!("active nodes are full, {:?} is not added", tea_id);
+				}
+			});
+		} else {
+			Self::deactivate_node(tea_id);
+		}
+		Self::deposit_event(Event::NodeStatusChanged(*tea_id, status));
+	}
+
+	pub(crate) fn is_builtin_node(tea_id: &TeaPubKey) -> bool {
+		Machines::<T>::get(tea_id)
+			.map(|machine| machine.issuer_id == BUILTIN_ISSURE)
+			.unwrap_or(false)
+	}
+
+	/// Ensure there is room in `ActiveNodes` for `tea_id`, which is always the case if the node
+	/// is already active.
+	pub(crate) fn check_active_nodes_capacity(tea_id: &TeaPubKey) -> DispatchResult {
+		let active_nodes = ActiveNodes::<T>::get();
+		ensure!(
+			active_nodes.contains(tea_id) || (active_nodes.len() as u32) < T::MaxActiveNodes::get(),
+			Error::<T>::ActiveNodesFull
+		);
+		Ok(())
+	}
+
+	/// Remove the node from `ActiveNodes`, status in `Nodes` is left to the caller.
+	pub(crate) fn deactivate_node(tea_id: &TeaPubKey) {
+		ActiveNodes::<T>::mutate(|nodes| nodes.retain(|id| !id.eq(tea_id)));
+	}
+
+	/// Disagreements between owners in `Issuers` and the `IssuerOwners` index.
+	pub fn issuer_index_inconsistencies() -> Vec<IndexInconsistency<T::AccountId, IssuerId>> {
+		pallet_utils::check_reverse_index(
+			Issuers::<T>::iter().map(|(issuer_id, issuer)| (issuer_id, issuer.owner)),
+			IssuerOwners::<T>::iter(),
+		)
+	}
+}
+
+impl<T: tea::Config> MachineOperation for tea::Pallet<T> {
+	type AccountId = T::AccountId;
+
+	fn machine_owner(tea_id: &TeaPubKey) -> Option<Self::AccountId> {
+		Machines::<T>::get(tea_id).map(|machine| machine.owner)
+	}
+
+	fn machine_status(tea_id: &TeaPubKey) -> Option<MachineStatus> {
+		tea::Pallet::<T>::machine_status(tea_id)
+	}
+
+	fn mining_cml(tea_id: &TeaPubKey) -> Option<CmlId> {
+		MiningCmls::<T>::get(tea_id)
+	}
+
+	fn occupy(tea_id: &TeaPubKey, cml_id: CmlId) {
+		MiningCmls::<T>::insert(tea_id, cml_id);
+	}
+
+	fn release(tea_id: &TeaPubKey) {
+		MiningCmls::<T>::remove(tea_id);
+	}
 }
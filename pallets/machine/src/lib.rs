@@ -15,21 +15,33 @@ mod tests;
 mod benchmarking;
 
 mod functions;
+pub mod migrations;
 mod rpc;
+pub mod traits;
 mod types;
-mod weights;
+pub mod weights;
 
-use frame_support::{dispatch::DispatchResult, pallet_prelude::*, traits::Currency};
+use frame_support::{
+	dispatch::DispatchResult,
+	pallet_prelude::*,
+	traits::{Currency, StorageVersion},
+};
 use frame_system::pallet_prelude::*;
-use pallet_utils::{extrinsic_procedure, CommonUtils, CurrencyOperations};
+use pallet_utils::{extrinsic_procedure, CommonUtils, CurrencyOperations, IndexInconsistency};
+use sp_core::ed25519;
+use sp_runtime::traits::{One, SaturatedConversion, Saturating, Zero};
 use sp_std::prelude::*;
 
+pub use traits::MachineOperation;
 pub use types::*;
 pub use weights::WeightInfo;
 
 type BalanceOf<T> =
 	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
+/// The current storage version, see `migrations` for how older versions are migrated.
+pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
 #[frame_support::pallet]
 pub mod tea {
 	use super::*;
@@ -57,15 +69,54 @@ pub mod tea {
 		#[pallet::constant]
 		type StartupTappBindingsLength: Get<u32>;
 
+		/// Max length of the url of an issuer.
+		#[pallet::constant]
+		type IssuerUrlLength: Get<u32>;
+
+		/// Max length of the display name of an issuer.
+		#[pallet::constant]
+		type IssuerNameLength: Get<u32>;
+
+		/// Max count of active nodes. RA nodes are selected from all of them, so the weight of
+		/// `update_node_profile` grows with this bound.
+		#[pallet::constant]
+		type MaxActiveNodes: Get<u32>;
+
+		/// Count of active nodes randomly selected to attest a node.
+		#[pallet::constant]
+		type RaQuorumSize: Get<u32>;
+
+		/// Count of positive attestations needed for a node to become active. Nodes with fewer
+		/// selected RA nodes stay pending until they update their profiles again.
+		#[pallet::constant]
+		type RaThreshold: Get<u32>;
+
+		/// Blocks without heartbeat after which a machine is marked offline.
+		#[pallet::constant]
+		type RuntimeActivityThreshold: Get<Self::BlockNumber>;
+
+		/// Interval in blocks between two sweeps of offline machines.
+		#[pallet::constant]
+		type ActivityCheckDuration: Get<Self::BlockNumber>;
+
+		/// Blocks a node that is not active has to wait after its last profile update before
+		/// updating again, so that the selection of RA nodes can't be re-rolled at will.
+		#[pallet::constant]
+		type RaReselectCooldown: Get<Self::BlockNumber>;
+
 		/// Operations about currency that used in Tea Camellia.
 		type CurrencyOperations: CurrencyOperations<
 			AccountId = Self::AccountId,
 			Balance = BalanceOf<Self>,
 		>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Used to allocate CML ID of new created DAO CML.
@@ -75,14 +126,30 @@ pub mod tea {
 
 	#[pallet::storage]
 	#[pallet::getter(fn issuers)]
-	pub(super) type Issuers<T: Config> =
-		StorageMap<_, Twox64Concat, IssuerId, Issuer<T::AccountId>>;
+	pub(super) type Issuers<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		IssuerId,
+		Issuer<T::AccountId, T::IssuerUrlLength, T::IssuerNameLength>,
+	>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn issuer_owners)]
 	pub(super) type IssuerOwners<T: Config> =
 		StorageMap<_, Twox64Concat, T::AccountId, IssuerId, ValueQuery>;
 
+	/// Nonce that the next machine registration signed by each issuer should use.
+	#[pallet::storage]
+	#[pallet::getter(fn issuer_nonces)]
+	pub(super) type IssuerNonces<T: Config> =
+		StorageMap<_, Twox64Concat, IssuerId, u64, ValueQuery>;
+
+	/// Count of machines registered under each issuer.
+	#[pallet::storage]
+	#[pallet::getter(fn issuer_machine_count)]
+	pub(super) type IssuerMachineCount<T: Config> =
+		StorageMap<_, Twox64Concat, IssuerId, u32, ValueQuery>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn machines)]
 	pub(super) type Machines<T: Config> =
@@ -93,6 +160,25 @@ pub mod tea {
 	pub(super) type MachineBindings<T: Config> =
 		StorageMap<_, Twox64Concat, TeaPubKey, CmlId, ValueQuery>;
 
+	/// Machines occupied by mining CMLs.
+	#[pallet::storage]
+	#[pallet::getter(fn mining_cmls)]
+	pub(super) type MiningCmls<T: Config> = StorageMap<_, Twox64Concat, TeaPubKey, CmlId>;
+
+	/// Builtin mining machines that layer-2 nodes bootstrap from, with their bound CML ids and
+	/// connection ids (multiaddr).
+	#[pallet::storage]
+	#[pallet::getter(fn startup_machine_bindings)]
+	pub(super) type StartupMachineBindings<T: Config> = StorageValue<
+		_,
+		BoundedVec<
+			(TeaPubKey, CmlId, BoundedVec<u8, T::ConnIdLength>),
+			T::StartupMachineBindingsLength,
+		>,
+		ValueQuery,
+	>;
+
+	/// Builtin machines hosting the tapp store, with their bound CML ids and ip addresses.
 	#[pallet::storage]
 	#[pallet::getter(fn startup_bonding_bindings)]
 	pub(super) type StartupTappBindings<T: Config> = StorageValue<
@@ -104,10 +190,38 @@ pub mod tea {
 		ValueQuery,
 	>;
 
+	/// Profiles and attestation states of TEA nodes.
+	#[pallet::storage]
+	#[pallet::getter(fn nodes)]
+	pub(super) type Nodes<T: Config> = StorageMap<_, Twox64Concat, TeaPubKey, Node<T::BlockNumber>>;
+
+	/// Nodes that have passed remote attestation, RA nodes are selected from them.
+	#[pallet::storage]
+	#[pallet::getter(fn active_nodes)]
+	pub(super) type ActiveNodes<T: Config> =
+		StorageValue<_, BoundedVec<TeaPubKey, T::MaxActiveNodes>, ValueQuery>;
+
+	/// Latest heartbeats of machines, inserted as pending when the machine is registered.
+	#[pallet::storage]
+	#[pallet::getter(fn runtime_activities)]
+	pub(super) type RuntimeActivities<T: Config> =
+		StorageMap<_, Twox64Concat, TeaPubKey, RuntimeActivity<T::BlockNumber>>;
+
+	/// Machines to check for missed heartbeats at the given height, entries outdated by newer
+	/// heartbeats are skipped when checked.
+	#[pallet::storage]
+	pub(super) type HeartbeatDeadlines<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, Vec<TeaPubKey>, ValueQuery>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn startup_owner)]
 	pub(super) type StartupOwner<T: Config> = StorageValue<_, T::AccountId>;
 
+	/// Raw key of the last `Machines` entry visited by the ongoing migration, `None` if there is
+	/// no ongoing migration.
+	#[pallet::storage]
+	pub type MigrationCursor<T: Config> = StorageValue<_, Vec<u8>>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -154,6 +268,58 @@ pub mod tea {
 			Vec<CmlId>,
 			T::BlockNumber,
 		),
+
+		/// Params:
+		/// 1. count of fixed `IssuerOwners` entries
+		IssuerIndexRepaired(u32),
+
+		/// Params:
+		/// 1. issuer id
+		/// 2. owner
+		IssuerRegistered(IssuerId, T::AccountId),
+
+		/// Params:
+		/// 1. issuer id
+		IssuerUpdated(IssuerId),
+
+		/// Params:
+		/// 1. issuer id
+		/// 2. from account
+		/// 3. to account
+		IssuerOwnershipTransferred(IssuerId, T::AccountId, T::AccountId),
+
+		/// Params:
+		/// 1. issuer id
+		/// 2. owner
+		IssuerDeregistered(IssuerId, T::AccountId),
+
+		/// Params:
+		/// 1. tea_id
+		/// 2. ephemeral id
+		/// 3. peer id
+		/// 4. selected RA nodes
+		NodeProfileUpdated(TeaPubKey, TeaPubKey, PeerId, Vec<TeaPubKey>),
+
+		/// Params:
+		/// 1. validator tea_id
+		/// 2. target tea_id
+		/// 3. is pass
+		RaResultCommitted(TeaPubKey, TeaPubKey, bool),
+
+		/// Params:
+		/// 1. tea_id
+		/// 2. new status
+		NodeStatusChanged(TeaPubKey, NodeStatus),
+
+		/// Params:
+		/// 1. tea_id
+		/// 2. height of the heartbeat
+		MachineOnline(TeaPubKey, T::BlockNumber),
+
+		/// Params:
+		/// 1. tea_id
+		/// 2. height of the last heartbeat
+		MachineOffline(TeaPubKey, T::BlockNumber),
 	}
 
 	// Errors inform users that something went wrong.
@@ -178,11 +344,49 @@ pub mod tea {
 		StartupMachineBindingsLengthToLong,
 		StartupTappBindingsLengthToLong,
 		StartupOwnerIsNone,
+		/// Machine is occupied by a mining CML
+		MachineIsMining,
+		IssuerUrlLengthToLong,
+		IssuerNameLengthToLong,
+		/// Issuer can't be deregistered while there are machines registered under it
+		IssuerHasMachines,
+		/// Machines can't be added or removed until the migration of `Machines` completes
+		MachinesMigrating,
+		/// The given nonce is not the next nonce of the issuer
+		InvalidIssuerNonce,
+		/// Machine registration is not signed by the issuer key
+		InvalidIssuerSignature,
+		/// Machine owner is not signed by the tea id key
+		InvalidTeaSignature,
+		/// The given node has not published its profile
+		NodeNotExist,
+		/// Only pending nodes can be attested
+		NodeNotPending,
+		/// The validator is not selected to attest the node
+		NotRaValidator,
+		/// The validator has committed its result already
+		RaResultAlreadyCommitted,
+		/// Only active nodes can attest other nodes
+		ValidatorNotActive,
+		/// `ActiveNodes` already holds `MaxActiveNodes` nodes
+		ActiveNodesFull,
+		/// Attestation result is not signed by the ephemeral key of the validator
+		InvalidRaSignature,
+		/// Heartbeat height should be newer than the last heartbeat and not in the future
+		InvalidHeartbeatHeight,
+		/// Heartbeat height is older than `RuntimeActivityThreshold` blocks
+		HeartbeatExpired,
+		/// Profile of a node that is not active can't be updated within `RaReselectCooldown`
+		/// blocks after the last update
+		RaReselectTooFrequent,
+		/// Entries of the issuer index exceed `max_items` given to the repair call
+		IndexExceedsRepairBound,
 	}
 
 	#[pallet::genesis_config]
 	pub struct GenesisConfig<T: Config> {
 		pub startup_owner: Option<T::AccountId>,
+		pub startup_machine_bindings: Vec<(TeaPubKey, CmlId, Vec<u8>)>,
 		pub startup_tapp_bindings: Vec<(TeaPubKey, CmlId, Vec<u8>)>,
 	}
 
@@ -191,6 +395,7 @@ pub mod tea {
 		fn default() -> Self {
 			GenesisConfig {
 				startup_owner: Default::default(),
+				startup_machine_bindings: Default::default(),
 				startup_tapp_bindings: Default::default(),
 			}
 		}
@@ -202,19 +407,26 @@ pub mod tea {
 			StartupOwner::<T>::set(self.startup_owner.clone());
 
 			let owner = self.startup_owner.clone().unwrap();
-			self.startup_tapp_bindings
+			self.startup_machine_bindings
 				.iter()
+				.chain(self.startup_tapp_bindings.iter())
 				.for_each(|(tea_id, cml_id, _)| {
-					Machines::<T>::insert(
-						tea_id,
-						Machine {
-							tea_id: *tea_id,
-							issuer_id: BUILTIN_ISSURE,
-							owner: owner.clone(),
-						},
-					);
+					Pallet::<T>::insert_machine(Machine {
+						tea_id: *tea_id,
+						issuer_id: BUILTIN_ISSURE,
+						owner: owner.clone(),
+					});
 					MachineBindings::<T>::insert(tea_id, cml_id);
 				});
+			StartupMachineBindings::<T>::set(
+				self.startup_machine_bindings
+					.clone()
+					.into_iter()
+					.map(|(tea_id, cml_id, conn_id)| (tea_id, cml_id, conn_id.try_into().unwrap()))
+					.collect::<Vec<(TeaPubKey, CmlId, BoundedVec<u8, _>)>>()
+					.try_into()
+					.unwrap(),
+			);
 			StartupTappBindings::<T>::set(
 				self.startup_tapp_bindings
 					.clone()
@@ -227,13 +439,50 @@ pub mod tea {
 		}
 	}
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			let weight =
+				crate::migrations::migrate_batch::<T>(crate::migrations::MIGRATION_BATCH_SIZE);
+			if (n % T::ActivityCheckDuration::get()).is_zero() {
+				weight.saturating_add(Self::sweep_offline_machines(n))
+			} else {
+				weight
+			}
+		}
+
+		fn on_runtime_upgrade() -> Weight {
+			crate::migrations::on_runtime_upgrade::<T>()
+		}
+
+		fn integrity_test() {
+			assert!(T::RaThreshold::get() > 0, "RaThreshold should be nonzero");
+			assert!(
+				T::RaThreshold::get() <= T::RaQuorumSize::get(),
+				"RaThreshold should not be larger than RaQuorumSize"
+			);
+			assert!(
+				T::RaQuorumSize::get() <= T::MaxActiveNodes::get(),
+				"RaQuorumSize should not be larger than MaxActiveNodes"
+			);
+			assert!(
+				!T::ActivityCheckDuration::get().is_zero(),
+				"ActivityCheckDuration should be nonzero"
+			);
+		}
+	}
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
+		/// Register a new issuer owned by `owner`, `public_key` is the ed25519 key that the
+		/// issuer signs its machines with.
 		#[pallet::weight(195_000_000)]
 		pub fn register_issuer(
 			sender: OriginFor<T>,
 			owner: T::AccountId,
-			_url: Vec<u8>,
+			url: Vec<u8>,
+			name: Vec<u8>,
+			public_key: IssuerPubKey,
 		) -> DispatchResult {
 			let root = ensure_root(sender)?;
 
@@ -244,7 +493,7 @@ pub mod tea {
 						!IssuerOwners::<T>::contains_key(&owner),
 						Error::<T>::IssuerOwnerRegistered
 					);
-					Ok(())
+					Self::check_issuer_metadata(&url, &name)
 				},
 				|_| {
 					let new_id = Self::next_id();
@@ -253,48 +502,179 @@ pub mod tea {
 						Issuer {
 							id: new_id,
 							owner: owner.clone(),
+							url: url.clone().try_into().unwrap(),
+							name: name.clone().try_into().unwrap(),
+							public_key,
 						},
 					);
 					IssuerOwners::<T>::insert(owner.clone(), new_id);
+					Self::deposit_event(Event::IssuerRegistered(new_id, owner.clone()));
 				},
 			)
 		}
 
+		/// Replace metadata of the given issuer.
+		#[pallet::weight(195_000_000)]
+		pub fn update_issuer(
+			sender: OriginFor<T>,
+			issuer_id: IssuerId,
+			url: Vec<u8>,
+			name: Vec<u8>,
+			public_key: IssuerPubKey,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			extrinsic_procedure(
+				&root,
+				|_| {
+					ensure!(
+						Issuers::<T>::contains_key(issuer_id),
+						Error::<T>::IssuerNotExist
+					);
+					Self::check_issuer_metadata(&url, &name)
+				},
+				|_| {
+					Issuers::<T>::mutate(issuer_id, |issuer| {
+						if let Some(issuer) = issuer {
+							issuer.url = url.clone().try_into().unwrap();
+							issuer.name = name.clone().try_into().unwrap();
+							issuer.public_key = public_key;
+						}
+					});
+					Self::deposit_event(Event::IssuerUpdated(issuer_id));
+				},
+			)
+		}
+
+		/// Give the given issuer to `new_owner`, who should not own any issuer yet.
+		#[pallet::weight(195_000_000)]
+		pub fn transfer_issuer_ownership(
+			sender: OriginFor<T>,
+			issuer_id: IssuerId,
+			new_owner: T::AccountId,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			extrinsic_procedure(
+				&root,
+				|_| {
+					ensure!(
+						Issuers::<T>::contains_key(issuer_id),
+						Error::<T>::IssuerNotExist
+					);
+					ensure!(
+						!IssuerOwners::<T>::contains_key(&new_owner),
+						Error::<T>::IssuerOwnerRegistered
+					);
+					Ok(())
+				},
+				|_| {
+					let old_owner = Issuers::<T>::mutate(issuer_id, |issuer| {
+						let issuer = issuer.as_mut().unwrap();
+						sp_std::mem::replace(&mut issuer.owner, new_owner.clone())
+					});
+					Self::remove_issuer_owner(&old_owner, issuer_id);
+					IssuerOwners::<T>::insert(&new_owner, issuer_id);
+
+					Self::deposit_event(Event::IssuerOwnershipTransferred(
+						issuer_id,
+						old_owner,
+						new_owner.clone(),
+					));
+				},
+			)
+		}
+
+		/// Remove the given issuer, only issuers without registered machines can be removed.
+		#[pallet::weight(195_000_000)]
+		pub fn deregister_issuer(sender: OriginFor<T>, issuer_id: IssuerId) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			extrinsic_procedure(
+				&root,
+				|_| {
+					ensure!(
+						Issuers::<T>::contains_key(issuer_id),
+						Error::<T>::IssuerNotExist
+					);
+					Self::check_machines_migrated()?;
+					ensure!(
+						IssuerMachineCount::<T>::get(issuer_id) == 0,
+						Error::<T>::IssuerHasMachines
+					);
+					Ok(())
+				},
+				|_| {
+					let issuer = Issuers::<T>::take(issuer_id).unwrap();
+					Self::remove_issuer_owner(&issuer.owner, issuer_id);
+					IssuerMachineCount::<T>::remove(issuer_id);
+					IssuerNonces::<T>::remove(issuer_id);
+
+					Self::deposit_event(Event::IssuerDeregistered(issuer_id, issuer.owner));
+				},
+			)
+		}
+
+		/// Register a machine manufactured by the issuer. `issuer_signature` is signed by the
+		/// issuer key over `issuer_signing_message`, which proves the machine is made by the
+		/// issuer, and `tea_signature` is signed by the `tea_id` key over
+		/// `machine_signing_message`, which proves `owner` holds the machine. `nonce` should
+		/// equal `IssuerNonces` of the issuer so that signatures can't be replayed.
 		#[pallet::weight(195_000_000)]
 		pub fn register_machine(
 			sender: OriginFor<T>,
 			tea_id: TeaPubKey,
 			owner: T::AccountId,
 			issuer_id: IssuerId,
+			nonce: u64,
+			issuer_signature: ed25519::Signature,
+			tea_signature: ed25519::Signature,
 		) -> DispatchResult {
 			let who = ensure_signed(sender)?;
 
 			extrinsic_procedure(
 				&who,
 				|who| {
+					Self::check_machines_migrated()?;
 					ensure!(
 						Issuers::<T>::contains_key(issuer_id),
 						Error::<T>::IssuerNotExist
 					);
-					ensure!(
-						Issuers::<T>::get(issuer_id).unwrap().owner.eq(who),
-						Error::<T>::InvalidIssuerOwner
-					);
+					let issuer = Issuers::<T>::get(issuer_id).unwrap();
+					ensure!(issuer.owner.eq(who), Error::<T>::InvalidIssuerOwner);
 					ensure!(
 						!Machines::<T>::contains_key(tea_id),
 						Error::<T>::MachineAlreadyExist
 					);
+					ensure!(
+						IssuerNonces::<T>::get(issuer_id) == nonce,
+						Error::<T>::InvalidIssuerNonce
+					);
+					ensure!(
+						Self::verify_ed25519(
+							&issuer.public_key,
+							&Self::issuer_signing_message(issuer_id, &tea_id, &owner, nonce),
+							&issuer_signature,
+						),
+						Error::<T>::InvalidIssuerSignature
+					);
+					ensure!(
+						Self::verify_ed25519(
+							&tea_id,
+							&Self::machine_signing_message(&owner),
+							&tea_signature,
+						),
+						Error::<T>::InvalidTeaSignature
+					);
 					Ok(())
 				},
 				|_| {
-					Machines::<T>::insert(
+					IssuerNonces::<T>::mutate(issuer_id, |nonce| *nonce = nonce.saturating_add(1));
+					Self::insert_machine(Machine {
 						tea_id,
-						Machine {
-							tea_id,
-							issuer_id,
-							owner,
-						},
-					)
+						issuer_id,
+						owner: owner.clone(),
+					})
 				},
 			)
 		}
@@ -318,6 +698,10 @@ pub mod tea {
 						Machines::<T>::get(tea_id).unwrap().owner.eq(who),
 						Error::<T>::InvalidMachineOwner
 					);
+					ensure!(
+						!MiningCmls::<T>::contains_key(tea_id),
+						Error::<T>::MachineIsMining
+					);
 					Ok(())
 				},
 				|who| {
@@ -360,6 +744,101 @@ pub mod tea {
 			)
 		}
 
+		/// Replace the boot nodes, `conn_ids` are the multiaddrs layer-2 nodes bootstrap from.
+		#[pallet::weight(195_000_000)]
+		pub fn reset_mining_startup(
+			sender: OriginFor<T>,
+			tea_ids: Vec<TeaPubKey>,
+			cml_ids: Vec<u64>,
+			conn_ids: Vec<Vec<u8>>,
+		) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			let tea_ids_len = tea_ids.len();
+			let cml_ids_len = cml_ids.len();
+			let conn_ids_len = conn_ids.len();
+			let conn_id_len: Vec<u32> = conn_ids.iter().map(|id| id.len() as u32).collect();
+			let leaving = Self::leaving_startup_machines(
+				&StartupMachineBindings::<T>::get(),
+				&StartupTappBindings::<T>::get(),
+				&tea_ids,
+			);
+			let leaving_is_mining = leaving
+				.iter()
+				.any(|tea_id| MiningCmls::<T>::contains_key(tea_id));
+			extrinsic_procedure(
+				&root,
+				|_| {
+					Self::check_machines_migrated()?;
+					ensure!(!leaving_is_mining, Error::<T>::MachineIsMining);
+					ensure!(
+						StartupOwner::<T>::get().is_some(),
+						Error::<T>::StartupOwnerIsNone,
+					);
+					ensure!(
+						tea_ids_len == cml_ids_len,
+						Error::<T>::BindingItemsLengthMismatch
+					);
+					ensure!(
+						tea_ids_len == conn_ids_len,
+						Error::<T>::BindingItemsLengthMismatch
+					);
+					ensure!(
+						tea_ids_len as u32 <= T::StartupMachineBindingsLength::get(),
+						Error::<T>::StartupMachineBindingsLengthToLong
+					);
+					for id_len in conn_id_len {
+						ensure!(
+							id_len <= T::ConnIdLength::get(),
+							Error::<T>::ConnIdLengthToLong
+						);
+					}
+					Ok(())
+				},
+				move |_| {
+					for tea_id in leaving.iter() {
+						Self::remove_machine(tea_id);
+						MachineBindings::<T>::remove(tea_id);
+					}
+
+					let owner = StartupOwner::<T>::get().unwrap();
+					let mut startups = Vec::new();
+					for i in 0..tea_ids.len() {
+						Self::insert_machine(Machine {
+							tea_id: tea_ids[i],
+							issuer_id: BUILTIN_ISSURE,
+							owner: owner.clone(),
+						});
+						MachineBindings::<T>::insert(tea_ids[i], cml_ids[i]);
+						startups.push((
+							tea_ids[i],
+							cml_ids[i],
+							conn_ids[i].clone().try_into().unwrap(),
+						));
+					}
+					let old_bindings = StartupMachineBindings::<T>::get();
+					StartupMachineBindings::<T>::set(startups.try_into().unwrap());
+
+					let mut old_tea_ids = vec![];
+					let mut old_cml_ids = vec![];
+					for (tea_id, cml_id, _) in old_bindings {
+						old_tea_ids.push(tea_id);
+						old_cml_ids.push(cml_id);
+					}
+
+					let current_block = frame_system::Pallet::<T>::block_number();
+					Self::deposit_event(Event::MachineStartupReset(
+						tea_ids,
+						cml_ids,
+						conn_ids,
+						old_tea_ids,
+						old_cml_ids,
+						current_block,
+					));
+				},
+			)
+		}
+
 		#[pallet::weight(195_000_000)]
 		pub fn reset_tapp_startup(
 			sender: OriginFor<T>,
@@ -373,9 +852,19 @@ pub mod tea {
 			let cml_ids_len = cml_ids.len();
 			let ip_list_len = ip_list.len();
 			let ip_address_len: Vec<u32> = ip_list.iter().map(|ip| ip.len() as u32).collect();
+			let leaving = Self::leaving_startup_machines(
+				&StartupTappBindings::<T>::get(),
+				&StartupMachineBindings::<T>::get(),
+				&tea_ids,
+			);
+			let leaving_is_mining = leaving
+				.iter()
+				.any(|tea_id| MiningCmls::<T>::contains_key(tea_id));
 			extrinsic_procedure(
 				&root,
 				|_| {
+					Self::check_machines_migrated()?;
+					ensure!(!leaving_is_mining, Error::<T>::MachineIsMining);
 					ensure!(
 						StartupOwner::<T>::get().is_some(),
 						Error::<T>::StartupOwnerIsNone,
@@ -401,24 +890,19 @@ pub mod tea {
 					Ok(())
 				},
 				move |_| {
-					StartupTappBindings::<T>::get()
-						.iter()
-						.for_each(|(tea_id, _, _)| {
-							Machines::<T>::remove(tea_id);
-							MachineBindings::<T>::remove(tea_id);
-						});
+					for tea_id in leaving.iter() {
+						Self::remove_machine(tea_id);
+						MachineBindings::<T>::remove(tea_id);
+					}
 
 					let owner = StartupOwner::<T>::get().unwrap();
 					let mut startups = Vec::new();
 					for i in 0..tea_ids.len() {
-						Machines::<T>::insert(
-							tea_ids[i],
-							Machine {
-								tea_id: tea_ids[i],
-								issuer_id: BUILTIN_ISSURE,
-								owner: owner.clone(),
-							},
-						);
+						Self::insert_machine(Machine {
+							tea_id: tea_ids[i],
+							issuer_id: BUILTIN_ISSURE,
+							owner: owner.clone(),
+						});
 						MachineBindings::<T>::insert(tea_ids[i], cml_ids[i]);
 						startups.push((
 							tea_ids[i],
@@ -448,5 +932,291 @@ pub mod tea {
 				},
 			)
 		}
+
+		/// Publish the ephemeral key and peer id of the node, `signature` is signed by the tea id
+		/// key over `node_profile_signing_message`. Random active nodes are selected to attest the
+		/// node, which stays pending until enough of them pass it. Builtin nodes become active
+		/// right away. Nodes that are not active can update their profiles again only after
+		/// `RaReselectCooldown` blocks.
+		#[pallet::weight(T::WeightInfo::update_node_profile(T::MaxActiveNodes::get()))]
+		pub fn update_node_profile(
+			sender: OriginFor<T>,
+			tea_id: TeaPubKey,
+			ephemeral_id: TeaPubKey,
+			peer_id: PeerId,
+			signature: ed25519::Signature,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			extrinsic_procedure(
+				&who,
+				|who| {
+					ensure!(
+						Machines::<T>::contains_key(tea_id),
+						Error::<T>::MachineNotExist
+					);
+					ensure!(
+						Machines::<T>::get(tea_id).unwrap().owner.eq(who),
+						Error::<T>::InvalidMachineOwner
+					);
+					ensure!(
+						peer_id.len() as u32 <= T::ConnIdLength::get(),
+						Error::<T>::ConnIdLengthToLong
+					);
+					ensure!(
+						Self::verify_ed25519(
+							&tea_id,
+							&Self::node_profile_signing_message(&ephemeral_id, &peer_id),
+							&signature,
+						),
+						Error::<T>::InvalidTeaSignature
+					);
+					if let Some(node) = Nodes::<T>::get(tea_id) {
+						let current_block = frame_system::Pallet::<T>::block_number();
+						ensure!(
+							node.status == NodeStatus::Active
+								|| current_block
+									>= node
+										.update_time
+										.saturating_add(T::RaReselectCooldown::get()),
+							Error::<T>::RaReselectTooFrequent
+						);
+					}
+					if Self::is_builtin_node(&tea_id) {
+						Self::check_active_nodes_capacity(&tea_id)?;
+					}
+					Ok(())
+				},
+				|who| {
+					let current_block = frame_system::Pallet::<T>::block_number();
+					let mut node = Nodes::<T>::get(tea_id).unwrap_or_else(|| Node {
+						tea_id,
+						create_time: current_block,
+						..Default::default()
+					});
+					// a new ephemeral key should be attested again
+					Self::deactivate_node(&tea_id);
+					node.ephemeral_id = ephemeral_id;
+					node.peer_id = peer_id.clone();
+					node.update_time = current_block;
+
+					// builtin nodes are set up by root, they are trusted without attestation so
+					// that there are active nodes to attest the others
+					let is_builtin = Self::is_builtin_node(&tea_id);
+					let ra_nodes = if is_builtin {
+						vec![]
+					} else {
+						Self::select_ra_nodes(who, &tea_id)
+					};
+					node.ra_nodes = ra_nodes.iter().map(|id| (*id, None)).collect();
+					node.status = NodeStatus::Pending;
+					Nodes::<T>::insert(tea_id, node);
+
+					Self::deposit_event(Event::NodeProfileUpdated(
+						tea_id,
+						ephemeral_id,
+						peer_id.clone(),
+						ra_nodes,
+					));
+					if is_builtin {
+						Self::update_node_status(&tea_id, NodeStatus::Active);
+					}
+				},
+			)
+		}
+
+		/// Commit the attestation result of `validator_tea_id` about `target_tea_id`, `signature`
+		/// is signed by the ephemeral key of the validator over `ra_signing_message`.
+		#[pallet::weight(T::WeightInfo::remote_attestation())]
+		pub fn remote_attestation(
+			sender: OriginFor<T>,
+			validator_tea_id: TeaPubKey,
+			target_tea_id: TeaPubKey,
+			is_pass: bool,
+			signature: ed25519::Signature,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			extrinsic_procedure(
+				&who,
+				|who| {
+					ensure!(
+						Machines::<T>::contains_key(validator_tea_id),
+						Error::<T>::MachineNotExist
+					);
+					ensure!(
+						Machines::<T>::get(validator_tea_id).unwrap().owner.eq(who),
+						Error::<T>::InvalidMachineOwner
+					);
+					let validator =
+						Nodes::<T>::get(validator_tea_id).ok_or(Error::<T>::NodeNotExist)?;
+					ensure!(
+						validator.status == NodeStatus::Active,
+						Error::<T>::ValidatorNotActive
+					);
+					let target = Nodes::<T>::get(target_tea_id).ok_or(Error::<T>::NodeNotExist)?;
+					ensure!(
+						target.status == NodeStatus::Pending,
+						Error::<T>::NodeNotPending
+					);
+					let (_, result) = target
+						.ra_nodes
+						.iter()
+						.find(|(id, _)| validator_tea_id.eq(id))
+						.ok_or(Error::<T>::NotRaValidator)?;
+					ensure!(result.is_none(), Error::<T>::RaResultAlreadyCommitted);
+					ensure!(
+						Self::verify_ed25519(
+							&validator.ephemeral_id,
+							&Self::ra_signing_message(
+								&target_tea_id,
+								&target.ephemeral_id,
+								is_pass
+							),
+							&signature,
+						),
+						Error::<T>::InvalidRaSignature
+					);
+					if is_pass {
+						let mut ra_nodes = target.ra_nodes.clone();
+						if let Some((_, result)) =
+							ra_nodes.iter_mut().find(|(id, _)| validator_tea_id.eq(id))
+						{
+							*result = Some(true);
+						}
+						if Self::attestation_status(&ra_nodes) == NodeStatus::Active {
+							Self::check_active_nodes_capacity(&target_tea_id)?;
+						}
+					}
+					Ok(())
+				},
+				|_| {
+					let status = Nodes::<T>::mutate(target_tea_id, |node| {
+						let node = node.as_mut().unwrap();
+						if let Some((_, result)) = node
+							.ra_nodes
+							.iter_mut()
+							.find(|(id, _)| validator_tea_id.eq(id))
+						{
+							*result = Some(is_pass);
+						}
+						Self::attestation_status(&node.ra_nodes)
+					});
+					Self::deposit_event(Event::RaResultCommitted(
+						validator_tea_id,
+						target_tea_id,
+						is_pass,
+					));
+
+					if status != NodeStatus::Pending {
+						Self::update_node_status(&target_tea_id, status);
+					}
+				},
+			)
+		}
+
+		/// Heartbeat of the machine, `signature` is signed by the tea id key over
+		/// `heartbeat_signing_message`. `height` should be newer than the last heartbeat and
+		/// within `RuntimeActivityThreshold` blocks of the current height.
+		#[pallet::weight(T::WeightInfo::update_runtime_activity())]
+		pub fn update_runtime_activity(
+			sender: OriginFor<T>,
+			tea_id: TeaPubKey,
+			height: T::BlockNumber,
+			signature: ed25519::Signature,
+		) -> DispatchResult {
+			let who = ensure_signed(sender)?;
+
+			let current_block = frame_system::Pallet::<T>::block_number();
+			extrinsic_procedure(
+				&who,
+				|who| {
+					ensure!(
+						Machines::<T>::contains_key(tea_id),
+						Error::<T>::MachineNotExist
+					);
+					ensure!(
+						Machines::<T>::get(tea_id).unwrap().owner.eq(who),
+						Error::<T>::InvalidMachineOwner
+					);
+					ensure!(height <= current_block, Error::<T>::InvalidHeartbeatHeight);
+					if let Some(activity) = RuntimeActivities::<T>::get(tea_id) {
+						ensure!(
+							activity.status == MachineStatus::Pending
+								|| height > activity.last_heartbeat,
+							Error::<T>::InvalidHeartbeatHeight
+						);
+					}
+					ensure!(
+						current_block.saturating_sub(height) <= T::RuntimeActivityThreshold::get(),
+						Error::<T>::HeartbeatExpired
+					);
+					ensure!(
+						Self::verify_ed25519(
+							&tea_id,
+							&Self::heartbeat_signing_message(&tea_id, height),
+							&signature,
+						),
+						Error::<T>::InvalidTeaSignature
+					);
+					Ok(())
+				},
+				|_| {
+					let was_online = RuntimeActivities::<T>::get(tea_id)
+						.map(|activity| activity.status == MachineStatus::Online)
+						.unwrap_or(false);
+					RuntimeActivities::<T>::insert(
+						tea_id,
+						RuntimeActivity {
+							last_heartbeat: height,
+							status: MachineStatus::Online,
+						},
+					);
+					Self::schedule_heartbeat_check(&tea_id, height);
+					if !was_online {
+						Self::deposit_event(Event::MachineOnline(tea_id, height));
+					}
+				},
+			)
+		}
+
+		/// Make `IssuerOwners` agree with owners in `Issuers`, orphan and mismatched entries are
+		/// removed and missing entries are inserted. `max_items` bounds the total count of
+		/// entries in both maps and the weight is charged accordingly.
+		#[pallet::weight(
+			pallet_utils::repair_reverse_index_weight(T::DbWeight::get(), *max_items)
+				.saturating_add(10_000_000)
+		)]
+		pub fn repair_issuer_index(sender: OriginFor<T>, max_items: u32) -> DispatchResult {
+			let root = ensure_root(sender)?;
+
+			extrinsic_procedure(
+				&root,
+				|_| {
+					ensure!(
+						pallet_utils::within_repair_bound(
+							Issuers::<T>::iter_keys(),
+							IssuerOwners::<T>::iter_keys(),
+							max_items,
+						),
+						Error::<T>::IndexExceedsRepairBound
+					);
+					Ok(())
+				},
+				|_| {
+					let fixed = pallet_utils::repair_reverse_index(
+						&Self::issuer_index_inconsistencies(),
+						|owner, issuer_id| Self::remove_issuer_owner(owner, *issuer_id),
+						|owner, issuer_id| {
+							// each owner owns at most one issuer, keep the indexed one if any
+							if !IssuerOwners::<T>::contains_key(owner) {
+								IssuerOwners::<T>::insert(owner, issuer_id);
+							}
+						},
+					);
+					Self::deposit_event(Event::IssuerIndexRepaired(fixed));
+				},
+			)
+		}
 	}
 }
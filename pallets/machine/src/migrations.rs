@@ -0,0 +1,123 @@
+//! Storage migrations of pallet-machine. Issuers are few and translated in `on_runtime_upgrade`,
+//! while `Machines` may hold too many entries to be visited in one block, so legacy machines are
+//! visited in batches of `MIGRATION_BATCH_SIZE` continued in `on_initialize` of the following
+//! blocks. Calls adding or removing machines are rejected until the migration completes.
+
+use super::*;
+use frame_support::{
+	sp_io,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::GetStorageVersion,
+};
+
+/// Max count of `Machines` entries visited in one block during migration.
+pub const MIGRATION_BATCH_SIZE: u32 = 1000;
+
+/// Encodings of storage version 0.
+pub mod v0 {
+	use crate::IssuerId;
+	use codec::{Decode, Encode};
+	use sp_runtime::RuntimeDebug;
+
+	/// Issuer before metadata was added.
+	#[derive(Clone, Encode, Decode, RuntimeDebug)]
+	pub struct Issuer<AccountId> {
+		pub id: IssuerId,
+		pub owner: AccountId,
+	}
+}
+
+/// Give legacy issuers empty metadata and start visiting legacy machines if the on-chain storage
+/// version is behind, the first batch of machines is visited right away.
+///
+/// Legacy issuers get an all-zero public key, which matches no signature, so they can't register
+/// machines until root sets their real keys by `update_issuer` after the upgrade.
+pub fn on_runtime_upgrade<T: Config>() -> Weight {
+	let on_chain_version = Pallet::<T>::on_chain_storage_version();
+	if on_chain_version >= STORAGE_VERSION {
+		return T::DbWeight::get().reads(1);
+	}
+
+	log::info!(
+		"migrating machine pallet from storage version {:?} to {:?}",
+		on_chain_version,
+		STORAGE_VERSION
+	);
+	if MigrationCursor::<T>::exists() {
+		return T::DbWeight::get()
+			.reads(2)
+			.saturating_add(migrate_batch::<T>(MIGRATION_BATCH_SIZE));
+	}
+
+	let mut issuers: Weight = 0;
+	Issuers::<T>::translate::<v0::Issuer<T::AccountId>, _>(|_, old| {
+		issuers += 1;
+		Some(Issuer {
+			id: old.id,
+			owner: old.owner,
+			url: Default::default(),
+			name: Default::default(),
+			public_key: Default::default(),
+		})
+	});
+	MigrationCursor::<T>::put(Machines::<T>::final_prefix().to_vec());
+
+	T::DbWeight::get()
+		.reads_writes(issuers.saturating_add(2), issuers.saturating_add(1))
+		.saturating_add(migrate_batch::<T>(MIGRATION_BATCH_SIZE))
+}
+
+/// Visit at most `limit` entries of `Machines` following the cursor. Each legacy machine is
+/// counted into `IssuerMachineCount` and marked online since the current height, so machines
+/// that were mining keep earning rewards and go offline only if they miss heartbeats from now
+/// on. The storage version is updated once all entries are visited.
+pub fn migrate_batch<T: Config>(limit: u32) -> Weight {
+	let mut cursor = match MigrationCursor::<T>::get() {
+		Some(cursor) => cursor,
+		None => return T::DbWeight::get().reads(1),
+	};
+
+	let prefix = Machines::<T>::final_prefix();
+	let current_block = frame_system::Pallet::<T>::block_number();
+	let mut visited: u32 = 0;
+	let mut finished = false;
+	while visited < limit {
+		let key = match sp_io::storage::next_key(&cursor) {
+			Some(key) if key.starts_with(&prefix) => key,
+			_ => {
+				finished = true;
+				break;
+			}
+		};
+		if let Some(machine) = unhashed::get::<Machine<T::AccountId>>(&key) {
+			IssuerMachineCount::<T>::mutate(machine.issuer_id, |count| {
+				*count = count.saturating_add(1)
+			});
+			RuntimeActivities::<T>::insert(
+				machine.tea_id,
+				RuntimeActivity {
+					last_heartbeat: current_block,
+					status: MachineStatus::Online,
+				},
+			);
+			Pallet::<T>::schedule_heartbeat_check(&machine.tea_id, current_block);
+		}
+		cursor = key;
+		visited += 1;
+	}
+
+	if finished {
+		MigrationCursor::<T>::kill();
+		STORAGE_VERSION.put::<Pallet<T>>();
+		log::info!(
+			"machine pallet migrated to storage version {:?}",
+			STORAGE_VERSION
+		);
+	} else {
+		MigrationCursor::<T>::put(cursor);
+	}
+	T::DbWeight::get().reads_writes(
+		(visited as Weight).saturating_mul(3).saturating_add(2),
+		(visited as Weight).saturating_mul(3).saturating_add(2),
+	)
+}
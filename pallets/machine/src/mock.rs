@@ -89,7 +89,7 @@ impl system::Config for Test {
 }
 
 parameter_types! {
-	pub const RuntimeActivityThreshold: u32 = RUNTIME_ACTIVITY_THRESHOLD;
+	pub const RuntimeActivityThreshold: u64 = RUNTIME_ACTIVITY_THRESHOLD as u64;
 	pub const PerRaTaskPoint: u32 = 100;
 	pub const UpdateValidatorsDuration: u32 = UPDATE_VALIDATORS_DURATION;
 	pub const MaxGroupMemberCount: u32 = MAX_GROUP_MEMBER_COUNT;
@@ -101,6 +101,7 @@ parameter_types! {
 	pub const OfflineEffectThreshold: u32 = OFFLINE_EFFECT_THRESHOLD;
 	pub const ReportRawardDuration: u32 = REPORT_RAWARD_DURATION;
 	pub const MiningNodesActivityCheckDuration: u32 = MINING_NODES_ACTIVITY_CHECK_DURATION;
+	pub const ActivityCheckDuration: u64 = MINING_NODES_ACTIVITY_CHECK_DURATION as u64;
 }
 
 impl pallet_utils::Config for Test {
@@ -134,6 +135,12 @@ parameter_types! {
 	pub const IpAddressLength: u32 = 100;
 	pub const StartupMachineBindingsLength: u32 = 100;
 	pub const StartupTappBindingsLength: u32 = 100;
+	pub const IssuerUrlLength: u32 = 100;
+	pub const IssuerNameLength: u32 = 100;
+	pub const MaxActiveNodes: u32 = 5;
+	pub const RaQuorumSize: u32 = 3;
+	pub const RaThreshold: u32 = 2;
+	pub const RaReselectCooldown: u64 = 100;
 }
 
 impl pallet_machine::Config for Test {
@@ -145,6 +152,15 @@ impl pallet_machine::Config for Test {
 	type IpAddressLength = IpAddressLength;
 	type StartupMachineBindingsLength = StartupMachineBindingsLength;
 	type StartupTappBindingsLength = StartupTappBindingsLength;
+	type IssuerUrlLength = IssuerUrlLength;
+	type IssuerNameLength = IssuerNameLength;
+	type MaxActiveNodes = MaxActiveNodes;
+	type RaQuorumSize = RaQuorumSize;
+	type RaThreshold = RaThreshold;
+	type RuntimeActivityThreshold = RuntimeActivityThreshold;
+	type ActivityCheckDuration = ActivityCheckDuration;
+	type RaReselectCooldown = RaReselectCooldown;
+	type WeightInfo = ();
 }
 
 // Build genesis storage according to the mock runtime.
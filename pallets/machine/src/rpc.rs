@@ -1,11 +1,17 @@
 use super::*;
 
 impl<T: tea::Config> tea::Pallet<T> {
-	pub fn list_boot_nodes() -> Vec<[u8; 32]> {
-		vec![]
+	pub fn list_boot_nodes() -> Vec<(TeaPubKey, CmlId, Vec<u8>)> {
+		StartupMachineBindings::<T>::get()
+			.into_iter()
+			.map(|(tea_id, cml_id, conn_id)| (tea_id, cml_id, conn_id.into_inner()))
+			.collect()
 	}
 
-	pub fn list_tapp_store_startup_nodes() -> Vec<[u8; 32]> {
-		vec![]
+	pub fn list_tapp_store_startup_nodes() -> Vec<(TeaPubKey, CmlId, Vec<u8>)> {
+		StartupTappBindings::<T>::get()
+			.into_iter()
+			.map(|(tea_id, cml_id, ip)| (tea_id, cml_id, ip.into_inner()))
+			.collect()
 	}
 }
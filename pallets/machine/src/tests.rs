@@ -1,8 +1,16 @@
 use crate::{
-	mock::*, CmlId, IssuerOwners, Issuers, MachineBindings, Machines, StartupMachineBindings,
-	StartupOwner, StartupTappBindings, TeaPubKey, BUILTIN_ISSURE,
+	migrations, mock::*, CmlId, Error, IndexInconsistency, IssuerId, IssuerOwners, Issuers,
+	MachineBindings, MachineOperation, MachineStatus, Machines, MigrationCursor, NodeStatus, Nodes,
+	StartupMachineBindings, StartupOwner, StartupTappBindings, TeaPubKey, BUILTIN_ISSURE,
+	STORAGE_VERSION,
 };
-use frame_support::assert_ok;
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::DispatchResult,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::{Get, GetStorageVersion, OnInitialize, StorageVersion},
+};
+use sp_core::{ed25519, Pair};
 
 #[test]
 fn register_issuer_works() {
@@ -12,15 +20,216 @@ fn register_issuer_works() {
 		assert_ok!(Machine::register_issuer(
 			Origin::root(),
 			issuer_owner,
-			b"test ip".to_vec()
+			b"test ip".to_vec(),
+			b"test issuer".to_vec(),
+			[0; 32]
 		));
 
 		let issuer_id = 1;
-		assert_eq!(Issuers::<Test>::get(issuer_id).unwrap().owner, issuer_owner);
+		let issuer = Issuers::<Test>::get(issuer_id).unwrap();
+		assert_eq!(issuer.owner, issuer_owner);
+		assert_eq!(issuer.url.to_vec(), b"test ip".to_vec());
+		assert_eq!(issuer.name.to_vec(), b"test issuer".to_vec());
 		assert_eq!(IssuerOwners::<Test>::get(issuer_owner), issuer_id);
 	})
 }
 
+#[test]
+fn register_issuer_should_fail_if_metadata_too_long() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			Machine::register_issuer(
+				Origin::root(),
+				3,
+				vec![0; IssuerUrlLength::get() as usize + 1],
+				vec![],
+				[0; 32]
+			),
+			Error::<Test>::IssuerUrlLengthToLong
+		);
+		assert_noop!(
+			Machine::register_issuer(
+				Origin::root(),
+				3,
+				vec![],
+				vec![0; IssuerNameLength::get() as usize + 1],
+				[0; 32]
+			),
+			Error::<Test>::IssuerNameLengthToLong
+		);
+	})
+}
+
+#[test]
+fn update_issuer_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			[0; 32]
+		));
+
+		assert_noop!(
+			Machine::update_issuer(Origin::signed(3), 1, vec![], vec![], [1; 32]),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			Machine::update_issuer(Origin::root(), 2, vec![], vec![], [1; 32]),
+			Error::<Test>::IssuerNotExist
+		);
+		assert_ok!(Machine::update_issuer(
+			Origin::root(),
+			1,
+			b"new url".to_vec(),
+			b"new name".to_vec(),
+			[1; 32]
+		));
+
+		let issuer = Issuers::<Test>::get(1).unwrap();
+		assert_eq!(issuer.owner, 3);
+		assert_eq!(issuer.url.to_vec(), b"new url".to_vec());
+		assert_eq!(issuer.name.to_vec(), b"new name".to_vec());
+		assert_eq!(issuer.public_key, [1; 32]);
+	})
+}
+
+#[test]
+fn transfer_issuer_ownership_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			4,
+			vec![],
+			vec![],
+			[0; 32]
+		));
+
+		assert_noop!(
+			Machine::transfer_issuer_ownership(Origin::root(), 1, 4),
+			Error::<Test>::IssuerOwnerRegistered
+		);
+		assert_ok!(Machine::transfer_issuer_ownership(Origin::root(), 1, 5));
+
+		assert_eq!(Issuers::<Test>::get(1).unwrap().owner, 5);
+		assert!(!IssuerOwners::<Test>::contains_key(3));
+		assert_eq!(IssuerOwners::<Test>::get(5), 1);
+		assert!(Machine::issuer_index_inconsistencies().is_empty());
+
+		let tea = tea_pair(1);
+		let (issuer_signature, tea_signature) = sign_machine(&tea, 1, 6, 0);
+		assert_noop!(
+			Machine::register_machine(
+				Origin::signed(3),
+				tea.public().0,
+				6,
+				1,
+				0,
+				issuer_signature,
+				tea_signature
+			),
+			Error::<Test>::InvalidIssuerOwner
+		);
+		register_machine(5, 1, 6, 1);
+	})
+}
+
+#[test]
+fn deregister_issuer_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let tea_id = register_machine(3, 1, 6, 1);
+		assert_eq!(Machine::issuer_machine_count(1), 1);
+
+		assert_noop!(
+			Machine::deregister_issuer(Origin::root(), 1),
+			Error::<Test>::IssuerHasMachines
+		);
+
+		Machine::remove_machine(&tea_id);
+		assert_eq!(Machine::issuer_machine_count(1), 0);
+		assert_ok!(Machine::deregister_issuer(Origin::root(), 1));
+		assert!(!Issuers::<Test>::contains_key(1));
+		assert!(!IssuerOwners::<Test>::contains_key(3));
+
+		assert_noop!(
+			Machine::deregister_issuer(Origin::root(), 1),
+			Error::<Test>::IssuerNotExist
+		);
+		// the owner can register a new issuer after deregistration
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			[0; 32]
+		));
+	})
+}
+
+#[test]
+fn repair_issuer_index_works() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			[0; 32]
+		));
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			4,
+			vec![],
+			vec![],
+			[0; 32]
+		));
+		assert!(Machine::issuer_index_inconsistencies().is_empty());
+
+		IssuerOwners::<Test>::remove(3);
+		IssuerOwners::<Test>::insert(5, 2);
+		IssuerOwners::<Test>::insert(6, 9);
+		assert_eq!(
+			Machine::issuer_index_inconsistencies(),
+			vec![
+				IndexInconsistency::Missing(3, 1),
+				IndexInconsistency::Mismatched(5, 2),
+				IndexInconsistency::Orphan(6, 9),
+			]
+		);
+
+		assert_noop!(
+			Machine::repair_issuer_index(Origin::signed(3), 5),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		// 2 issuers and 3 entries in the index
+		assert_noop!(
+			Machine::repair_issuer_index(Origin::root(), 4),
+			Error::<Test>::IndexExceedsRepairBound
+		);
+		assert_ok!(Machine::repair_issuer_index(Origin::root(), 5));
+		assert!(Machine::issuer_index_inconsistencies().is_empty());
+		assert_eq!(IssuerOwners::<Test>::get(3), 1);
+		assert_eq!(IssuerOwners::<Test>::get(4), 2);
+		assert!(!IssuerOwners::<Test>::contains_key(5));
+		assert!(!IssuerOwners::<Test>::contains_key(6));
+	})
+}
+
 #[test]
 fn register_machine_works() {
 	new_test_ext().execute_with(|| {
@@ -29,24 +238,111 @@ fn register_machine_works() {
 		assert_ok!(Machine::register_issuer(
 			Origin::root(),
 			issuer_owner,
-			b"test ip".to_vec()
+			b"test ip".to_vec(),
+			b"test issuer".to_vec(),
+			issuer_pair().public().0
 		));
 		let issuer_id = 1;
 
 		let user = 6;
-		let tea_id = [1; 32];
+		let tea = tea_pair(1);
+		let tea_id = tea.public().0;
+		let (issuer_signature, tea_signature) = sign_machine(&tea, issuer_id, user, 0);
+		assert_noop!(
+			Machine::register_machine(
+				Origin::signed(issuer_owner),
+				tea_id,
+				user,
+				issuer_id,
+				1,
+				issuer_signature.clone(),
+				tea_signature.clone()
+			),
+			Error::<Test>::InvalidIssuerNonce
+		);
+		assert_noop!(
+			Machine::register_machine(
+				Origin::signed(issuer_owner),
+				tea_id,
+				user,
+				issuer_id,
+				0,
+				tea.sign(&Machine::issuer_signing_message(
+					issuer_id, &tea_id, &user, 0
+				)),
+				tea_signature.clone()
+			),
+			Error::<Test>::InvalidIssuerSignature
+		);
+		let (_, other_owner_signature) = sign_machine(&tea, issuer_id, 7, 0);
+		assert_noop!(
+			Machine::register_machine(
+				Origin::signed(issuer_owner),
+				tea_id,
+				user,
+				issuer_id,
+				0,
+				issuer_signature.clone(),
+				other_owner_signature
+			),
+			Error::<Test>::InvalidTeaSignature
+		);
+
+		// another issuer sharing the same key can't reuse the signature
+		let other_issuer_owner = 4;
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			other_issuer_owner,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let other_issuer_id = Machine::issuer_owners(other_issuer_owner);
+		assert_noop!(
+			Machine::register_machine(
+				Origin::signed(other_issuer_owner),
+				tea_id,
+				user,
+				other_issuer_id,
+				0,
+				issuer_signature.clone(),
+				tea_signature.clone()
+			),
+			Error::<Test>::InvalidIssuerSignature
+		);
+
 		assert_ok!(Machine::register_machine(
 			Origin::signed(issuer_owner),
 			tea_id,
 			user,
-			issuer_id
+			issuer_id,
+			0,
+			issuer_signature,
+			tea_signature
 		));
+		assert_eq!(Machine::issuer_nonces(issuer_id), 1);
 
 		assert!(Machines::<Test>::contains_key(tea_id));
 		let machine = Machines::<Test>::get(tea_id).unwrap();
 		assert_eq!(machine.tea_id, tea_id);
 		assert_eq!(machine.owner, user);
 		assert_eq!(machine.issuer_id, issuer_id);
+
+		// signatures can't be replayed once the nonce is used
+		let tea2 = tea_pair(2);
+		let (issuer_signature, tea_signature) = sign_machine(&tea2, issuer_id, user, 0);
+		assert_noop!(
+			Machine::register_machine(
+				Origin::signed(issuer_owner),
+				tea2.public().0,
+				user,
+				issuer_id,
+				0,
+				issuer_signature,
+				tea_signature
+			),
+			Error::<Test>::InvalidIssuerNonce
+		);
 	})
 }
 
@@ -58,18 +354,14 @@ fn transfer_machine_works() {
 		assert_ok!(Machine::register_issuer(
 			Origin::root(),
 			issuer_owner,
-			b"test ip".to_vec()
+			b"test ip".to_vec(),
+			b"test issuer".to_vec(),
+			issuer_pair().public().0
 		));
 		let issuer_id = 1;
 
 		let user = 6;
-		let tea_id = [1; 32];
-		assert_ok!(Machine::register_machine(
-			Origin::signed(issuer_owner),
-			tea_id,
-			user,
-			issuer_id
-		));
+		let tea_id = register_machine(issuer_owner, issuer_id, user, 1);
 		assert_eq!(Machines::<Test>::get(tea_id).unwrap().owner, user);
 
 		let user2 = 8;
@@ -83,25 +375,59 @@ fn transfer_machine_works() {
 }
 
 #[test]
-fn register_for_layer2_works() {
+fn mining_machine_cannot_be_transferred() {
 	new_test_ext().execute_with(|| {
 		let issuer_owner = 3;
 
 		assert_ok!(Machine::register_issuer(
 			Origin::root(),
 			issuer_owner,
-			b"test ip".to_vec()
+			b"test ip".to_vec(),
+			b"test issuer".to_vec(),
+			issuer_pair().public().0
 		));
 		let issuer_id = 1;
 
 		let user = 6;
-		let tea_id = [1; 32];
-		assert_ok!(Machine::register_machine(
-			Origin::signed(issuer_owner),
+		let tea_id = register_machine(issuer_owner, issuer_id, user, 1);
+		assert_eq!(Machine::machine_owner(&tea_id), Some(user));
+
+		let cml_id = 11;
+		Machine::occupy(&tea_id, cml_id);
+		assert_eq!(Machine::mining_cml(&tea_id), Some(cml_id));
+
+		let user2 = 8;
+		assert_noop!(
+			Machine::transfer_machine(Origin::signed(user), tea_id, user2),
+			Error::<Test>::MachineIsMining
+		);
+
+		Machine::release(&tea_id);
+		assert_eq!(Machine::mining_cml(&tea_id), None);
+		assert_ok!(Machine::transfer_machine(
+			Origin::signed(user),
 			tea_id,
-			user,
-			issuer_id
+			user2
+		));
+	})
+}
+
+#[test]
+fn register_for_layer2_works() {
+	new_test_ext().execute_with(|| {
+		let issuer_owner = 3;
+
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			issuer_owner,
+			b"test ip".to_vec(),
+			b"test issuer".to_vec(),
+			issuer_pair().public().0
 		));
+		let issuer_id = 1;
+
+		let user = 6;
+		let tea_id = register_machine(issuer_owner, issuer_id, user, 1);
 		assert_eq!(Machines::<Test>::get(tea_id).unwrap().owner, user);
 
 		let cml_id = 111;
@@ -197,3 +523,729 @@ fn reset_tapp_startup_works() {
 		assert_eq!(machine2.owner, startup_owner);
 	})
 }
+
+#[test]
+fn list_startup_nodes_works() {
+	new_test_ext().execute_with(|| {
+		StartupOwner::<Test>::set(Some(444));
+		assert!(Machine::list_boot_nodes().is_empty());
+		assert!(Machine::list_tapp_store_startup_nodes().is_empty());
+
+		let conn_id = b"/ip4/127.0.0.1/tcp/4001".to_vec();
+		assert_ok!(Machine::reset_mining_startup(
+			Origin::root(),
+			vec![[1; 32], [2; 32]],
+			vec![11, 22],
+			vec![conn_id.clone(), conn_id.clone()],
+		));
+		assert_ok!(Machine::reset_tapp_startup(
+			Origin::root(),
+			vec![[3; 32]],
+			vec![33],
+			vec![b"127.0.0.1".to_vec()],
+		));
+		assert_eq!(
+			Machine::list_boot_nodes(),
+			vec![
+				([1; 32], 11, conn_id.clone()),
+				([2; 32], 22, conn_id.clone())
+			]
+		);
+		assert_eq!(
+			Machine::list_tapp_store_startup_nodes(),
+			vec![([3; 32], 33, b"127.0.0.1".to_vec())]
+		);
+
+		// old boot nodes are replaced, tapp startup nodes are untouched
+		assert_ok!(Machine::reset_mining_startup(
+			Origin::root(),
+			vec![[4; 32]],
+			vec![44],
+			vec![conn_id.clone()],
+		));
+		assert_eq!(Machine::list_boot_nodes(), vec![([4; 32], 44, conn_id)]);
+		assert!(!Machines::<Test>::contains_key([1; 32]));
+		assert!(!MachineBindings::<Test>::contains_key([1; 32]));
+		assert_eq!(Machine::list_tapp_store_startup_nodes().len(), 1);
+	})
+}
+
+#[test]
+fn reset_mining_startup_should_fail_if_conn_id_is_too_long() {
+	new_test_ext().execute_with(|| {
+		StartupOwner::<Test>::set(Some(444));
+
+		assert_noop!(
+			Machine::reset_mining_startup(
+				Origin::root(),
+				vec![[1; 32]],
+				vec![11],
+				vec![vec![0; ConnIdLength::get() as usize + 1]],
+			),
+			Error::<Test>::ConnIdLengthToLong
+		);
+		assert_noop!(
+			Machine::reset_mining_startup(Origin::root(), vec![[1; 32]], vec![11], vec![]),
+			Error::<Test>::BindingItemsLengthMismatch
+		);
+	})
+}
+
+#[test]
+fn builtin_nodes_are_active_after_profile_updated() {
+	new_test_ext().execute_with(|| {
+		let tea_ids = startup_nodes(&[1, 2]);
+		assert_eq!(Machine::node_status(&tea_ids[0]), None);
+
+		assert_noop!(
+			Machine::update_node_profile(
+				Origin::signed(444),
+				tea_ids[0],
+				ephemeral_pair(1).public().0,
+				vec![1],
+				tea_pair(2).sign(&Machine::node_profile_signing_message(
+					&ephemeral_pair(1).public().0,
+					&vec![1]
+				))
+			),
+			Error::<Test>::InvalidTeaSignature
+		);
+		assert_noop!(
+			Machine::update_node_profile(
+				Origin::signed(5),
+				tea_ids[0],
+				ephemeral_pair(1).public().0,
+				vec![1],
+				tea_pair(1).sign(&Machine::node_profile_signing_message(
+					&ephemeral_pair(1).public().0,
+					&vec![1]
+				))
+			),
+			Error::<Test>::InvalidMachineOwner
+		);
+
+		update_node_profile(444, 1);
+		update_node_profile(444, 2);
+		assert_eq!(Machine::node_status(&tea_ids[0]), Some(NodeStatus::Active));
+		assert_eq!(Machine::node_status(&tea_ids[1]), Some(NodeStatus::Active));
+		assert_eq!(Machine::active_nodes().into_inner(), tea_ids);
+
+		let node = Nodes::<Test>::get(tea_ids[0]).unwrap();
+		assert_eq!(node.ephemeral_id, ephemeral_pair(1).public().0);
+		assert_eq!(node.peer_id, vec![1]);
+		assert!(node.ra_nodes.is_empty());
+	})
+}
+
+#[test]
+fn relisted_startup_machines_keep_node_states() {
+	new_test_ext().execute_with(|| {
+		let tea_ids = startup_nodes(&[1, 2]);
+		update_node_profile(444, 1);
+		update_node_profile(444, 2);
+
+		// the first machine stays in the startup set while the second one leaves
+		assert_ok!(Machine::reset_tapp_startup(
+			Origin::root(),
+			vec![tea_ids[0]],
+			vec![1],
+			vec![vec![1]],
+		));
+		assert_eq!(Machine::node_status(&tea_ids[0]), Some(NodeStatus::Active));
+		assert_eq!(Machine::active_nodes().into_inner(), vec![tea_ids[0]]);
+		assert_eq!(Machine::issuer_machine_count(BUILTIN_ISSURE), 1);
+		assert!(!Machines::<Test>::contains_key(tea_ids[1]));
+		assert!(!Nodes::<Test>::contains_key(tea_ids[1]));
+
+		// mining machines can't leave the startup set
+		Machine::occupy(&tea_ids[0], 1);
+		assert_noop!(
+			Machine::reset_tapp_startup(Origin::root(), vec![], vec![], vec![]),
+			Error::<Test>::MachineIsMining
+		);
+		assert_ok!(Machine::reset_tapp_startup(
+			Origin::root(),
+			vec![tea_ids[0]],
+			vec![1],
+			vec![vec![1]],
+		));
+		assert_eq!(Machine::mining_cml(&tea_ids[0]), Some(1));
+	})
+}
+
+#[test]
+fn machines_in_both_startup_sets_are_kept() {
+	new_test_ext().execute_with(|| {
+		let tea_ids = startup_nodes(&[1, 2]);
+		assert_ok!(Machine::reset_mining_startup(
+			Origin::root(),
+			vec![tea_ids[0]],
+			vec![1],
+			vec![vec![1]],
+		));
+
+		// the first machine is still a tapp startup node
+		assert_ok!(Machine::reset_mining_startup(
+			Origin::root(),
+			vec![],
+			vec![],
+			vec![],
+		));
+		assert!(Machines::<Test>::contains_key(tea_ids[0]));
+		assert_eq!(Machine::issuer_machine_count(BUILTIN_ISSURE), 2);
+
+		// and leaves once it is in neither set
+		assert_ok!(Machine::reset_tapp_startup(
+			Origin::root(),
+			vec![tea_ids[1]],
+			vec![2],
+			vec![vec![2]],
+		));
+		assert!(!Machines::<Test>::contains_key(tea_ids[0]));
+		assert_eq!(Machine::issuer_machine_count(BUILTIN_ISSURE), 1);
+	})
+}
+
+#[test]
+fn node_becomes_active_after_enough_positive_attestations() {
+	new_test_ext().execute_with(|| {
+		startup_nodes(&[1, 2, 3, 4]);
+		for seed in 1..=4 {
+			update_node_profile(444, seed);
+		}
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let user = 6;
+		let tea_id = register_machine(3, 1, user, 10);
+		update_node_profile(user, 10);
+
+		let node = Nodes::<Test>::get(tea_id).unwrap();
+		assert_eq!(node.status, NodeStatus::Pending);
+		assert_eq!(node.ra_nodes.len(), RaQuorumSize::get() as usize);
+		let validators: Vec<u8> = (1..=4)
+			.filter(|seed| {
+				node.ra_nodes
+					.iter()
+					.any(|(id, _)| tea_pair(*seed).public().0.eq(id))
+			})
+			.collect();
+		let outsider = (1..=4).find(|seed| !validators.contains(seed)).unwrap();
+
+		assert_noop!(
+			attest(outsider, tea_id, true),
+			Error::<Test>::NotRaValidator
+		);
+		assert_noop!(
+			Machine::remote_attestation(
+				Origin::signed(444),
+				tea_pair(validators[0]).public().0,
+				tea_id,
+				true,
+				ephemeral_pair(validators[0]).sign(&Machine::ra_signing_message(
+					&tea_id,
+					&node.ephemeral_id,
+					false
+				))
+			),
+			Error::<Test>::InvalidRaSignature
+		);
+
+		assert_ok!(attest(validators[0], tea_id, true));
+		assert_noop!(
+			attest(validators[0], tea_id, true),
+			Error::<Test>::RaResultAlreadyCommitted
+		);
+		assert_eq!(Machine::node_status(&tea_id), Some(NodeStatus::Pending));
+
+		assert_ok!(attest(validators[1], tea_id, true));
+		assert_eq!(Machine::node_status(&tea_id), Some(NodeStatus::Active));
+		assert!(Machine::active_nodes().contains(&tea_id));
+		assert_noop!(
+			attest(validators[2], tea_id, true),
+			Error::<Test>::NodeNotPending
+		);
+
+		// the node should be attested again with a new ephemeral key
+		update_node_profile(user, 10);
+		assert_eq!(Machine::node_status(&tea_id), Some(NodeStatus::Pending));
+		assert!(!Machine::active_nodes().contains(&tea_id));
+	})
+}
+
+#[test]
+fn node_with_fewer_ra_nodes_than_threshold_stays_pending() {
+	new_test_ext().execute_with(|| {
+		startup_nodes(&[1]);
+		update_node_profile(444, 1);
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let tea_id = register_machine(3, 1, 6, 10);
+		update_node_profile(6, 10);
+		assert_eq!(Nodes::<Test>::get(tea_id).unwrap().ra_nodes.len(), 1);
+
+		assert_ok!(attest(1, tea_id, true));
+		assert_eq!(Machine::node_status(&tea_id), Some(NodeStatus::Pending));
+		assert!(!Machine::active_nodes().contains(&tea_id));
+	})
+}
+
+#[test]
+fn nodes_can_not_become_active_beyond_max_active_nodes() {
+	new_test_ext().execute_with(|| {
+		let max = MaxActiveNodes::get() as u8;
+		let tea_ids = startup_nodes(&(1..=max + 1).collect::<Vec<u8>>());
+		for seed in 1..=max {
+			update_node_profile(444, seed);
+		}
+		assert_eq!(Machine::active_nodes().len(), max as usize);
+
+		let ephemeral_id = ephemeral_pair(max + 1).public().0;
+		assert_noop!(
+			Machine::update_node_profile(
+				Origin::signed(444),
+				tea_ids[max as usize],
+				ephemeral_id,
+				vec![max + 1],
+				tea_pair(max + 1).sign(&Machine::node_profile_signing_message(
+					&ephemeral_id,
+					&vec![max + 1]
+				))
+			),
+			Error::<Test>::ActiveNodesFull
+		);
+
+		// active nodes keep their slots when updating profiles
+		update_node_profile(444, 1);
+		assert_eq!(Machine::node_status(&tea_ids[0]), Some(NodeStatus::Active));
+		assert_eq!(Machine::active_nodes().len(), max as usize);
+	})
+}
+
+#[test]
+fn node_becomes_invalid_after_enough_negative_attestations() {
+	new_test_ext().execute_with(|| {
+		startup_nodes(&[1, 2, 3]);
+		for seed in 1..=3 {
+			update_node_profile(444, seed);
+		}
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let tea_id = register_machine(3, 1, 6, 10);
+		let tea_id2 = register_machine(3, 1, 6, 11);
+		update_node_profile(6, 10);
+		update_node_profile(6, 11);
+
+		// pending nodes can't attest others
+		assert_noop!(attest(10, tea_id2, true), Error::<Test>::ValidatorNotActive);
+
+		assert_ok!(attest(1, tea_id, false));
+		assert_eq!(Machine::node_status(&tea_id), Some(NodeStatus::Pending));
+		assert_ok!(attest(2, tea_id, false));
+		assert_eq!(Machine::node_status(&tea_id), Some(NodeStatus::Invalid));
+		assert!(!Machine::active_nodes().contains(&tea_id));
+	})
+}
+
+#[test]
+fn ra_nodes_are_not_owned_by_the_node_owner_and_not_rerolled() {
+	new_test_ext().execute_with(|| {
+		let startup_tea_ids = startup_nodes(&[1, 2, 3]);
+		for seed in 1..=3 {
+			update_node_profile(444, seed);
+		}
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let user = 6;
+		let tea_id = register_machine(3, 1, user, 10);
+		let own_tea_id = register_machine(3, 1, user, 11);
+		update_node_profile(user, 11);
+		Machine::update_node_status(&own_tea_id, NodeStatus::Active);
+
+		update_node_profile(user, 10);
+		let mut ra_nodes: Vec<TeaPubKey> = Nodes::<Test>::get(tea_id)
+			.unwrap()
+			.ra_nodes
+			.into_iter()
+			.map(|(id, _)| id)
+			.collect();
+		ra_nodes.sort();
+		let mut expected = startup_tea_ids.clone();
+		expected.sort();
+		assert_eq!(ra_nodes, expected);
+
+		// pending nodes can't re-roll RA nodes right away
+		let ephemeral_id = ephemeral_pair(10).public().0;
+		let signature = tea_pair(10).sign(&Machine::node_profile_signing_message(
+			&ephemeral_id,
+			&vec![10],
+		));
+		assert_noop!(
+			Machine::update_node_profile(
+				Origin::signed(user),
+				tea_id,
+				ephemeral_id,
+				vec![10],
+				signature.clone()
+			),
+			Error::<Test>::RaReselectTooFrequent
+		);
+		frame_system::Pallet::<Test>::set_block_number(RaReselectCooldown::get());
+		assert_ok!(Machine::update_node_profile(
+			Origin::signed(user),
+			tea_id,
+			ephemeral_id,
+			vec![10],
+			signature
+		));
+	})
+}
+
+#[test]
+fn heartbeats_keep_machines_online() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let user = 6;
+		let tea_id = register_machine(3, 1, user, 10);
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Pending)
+		);
+		assert_eq!(
+			Machine::machines_by_status(MachineStatus::Pending),
+			vec![tea_id]
+		);
+
+		frame_system::Pallet::<Test>::set_block_number(10);
+		assert_noop!(
+			heartbeat(user, 10, 11),
+			Error::<Test>::InvalidHeartbeatHeight
+		);
+		assert_noop!(
+			Machine::update_runtime_activity(
+				Origin::signed(user),
+				tea_id,
+				10,
+				tea_pair(11).sign(&Machine::heartbeat_signing_message(&tea_id, 10))
+			),
+			Error::<Test>::InvalidTeaSignature
+		);
+		assert_ok!(heartbeat(user, 10, 10));
+		assert_noop!(
+			heartbeat(user, 10, 10),
+			Error::<Test>::InvalidHeartbeatHeight
+		);
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Online)
+		);
+		assert_eq!(
+			Machine::machines_by_status(MachineStatus::Online),
+			vec![tea_id]
+		);
+		assert!(Machine::machines_by_status(MachineStatus::Pending).is_empty());
+
+		// swept only at multiples of `ActivityCheckDuration` after the threshold passed
+		Machine::on_initialize(ActivityCheckDuration::get() * 7);
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Online)
+		);
+		Machine::on_initialize(RuntimeActivityThreshold::get() + 11);
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Online)
+		);
+		Machine::on_initialize(ActivityCheckDuration::get() * 8);
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Offline)
+		);
+		assert_eq!(
+			Machine::machines_by_status(MachineStatus::Offline),
+			vec![tea_id]
+		);
+
+		let current = ActivityCheckDuration::get() * 8;
+		frame_system::Pallet::<Test>::set_block_number(current);
+		assert_noop!(
+			heartbeat(user, 10, current - RuntimeActivityThreshold::get() - 1),
+			Error::<Test>::HeartbeatExpired
+		);
+		assert_ok!(heartbeat(user, 10, current));
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Online)
+		);
+	})
+}
+
+#[test]
+fn machines_without_heartbeats_go_offline() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Machine::register_issuer(
+			Origin::root(),
+			3,
+			vec![],
+			vec![],
+			issuer_pair().public().0
+		));
+		let user = 6;
+		let tea_id = register_machine(3, 1, user, 10);
+
+		// checked at the first multiple of `ActivityCheckDuration` after the threshold passed
+		Machine::on_initialize(ActivityCheckDuration::get() * 7);
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Pending)
+		);
+		Machine::on_initialize(ActivityCheckDuration::get() * 8);
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Offline)
+		);
+		assert!(HeartbeatDeadlines::<Test>::get(ActivityCheckDuration::get() * 8).is_empty());
+
+		let current = ActivityCheckDuration::get() * 8;
+		frame_system::Pallet::<Test>::set_block_number(current);
+		assert_ok!(heartbeat(user, 10, current));
+		assert_eq!(
+			Machine::machine_status(&tea_id),
+			Some(MachineStatus::Online)
+		);
+		assert_eq!(
+			HeartbeatDeadlines::<Test>::get(ActivityCheckDuration::get() * 16),
+			vec![tea_id]
+		);
+	})
+}
+
+fn issuer_pair() -> ed25519::Pair {
+	ed25519::Pair::from_seed(&[100; 32])
+}
+
+#[test]
+fn legacy_issuers_and_machines_are_migrated() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(5);
+		let tea_ids = insert_legacy_machines(&[1, 2, 3]);
+
+		migrations::on_runtime_upgrade::<Test>();
+		let issuer = Issuers::<Test>::get(1).unwrap();
+		assert_eq!(issuer.owner, 3);
+		assert_eq!(issuer.public_key, [0; 32]);
+		assert!(!MigrationCursor::<Test>::exists());
+		assert_eq!(Machine::on_chain_storage_version(), STORAGE_VERSION);
+		assert_eq!(Machine::issuer_machine_count(1), 3);
+		// legacy machines keep mining until they miss heartbeats from now on
+		for tea_id in tea_ids.iter() {
+			assert_eq!(Machine::machine_status(tea_id), Some(MachineStatus::Online));
+			assert_eq!(
+				Machine::runtime_activities(tea_id).unwrap().last_heartbeat,
+				5
+			);
+		}
+	})
+}
+
+#[test]
+fn machine_migration_is_continued_in_following_blocks() {
+	new_test_ext().execute_with(|| {
+		frame_system::Pallet::<Test>::set_block_number(5);
+		let tea_ids = insert_legacy_machines(&[1, 2, 3]);
+		MigrationCursor::<Test>::put(Machines::<Test>::final_prefix().to_vec());
+
+		migrations::migrate_batch::<Test>(2);
+		assert!(MigrationCursor::<Test>::exists());
+		assert_eq!(Machine::issuer_machine_count(1), 2);
+		let unvisited: Vec<&TeaPubKey> = tea_ids
+			.iter()
+			.filter(|tea_id| Machine::runtime_activities(tea_id).is_none())
+			.collect();
+		assert_eq!(unvisited.len(), 1);
+		assert_eq!(
+			Machine::machine_status(unvisited[0]),
+			Some(MachineStatus::Online)
+		);
+
+		assert_noop!(
+			Machine::deregister_issuer(Origin::root(), 1),
+			Error::<Test>::MachinesMigrating
+		);
+		let tea = tea_pair(10);
+		let (issuer_signature, tea_signature) = sign_machine(&tea, 1, 6, 0);
+		assert_noop!(
+			Machine::register_machine(
+				Origin::signed(3),
+				tea.public().0,
+				6,
+				1,
+				0,
+				issuer_signature,
+				tea_signature
+			),
+			Error::<Test>::MachinesMigrating
+		);
+
+		Machine::on_initialize(6);
+		assert!(!MigrationCursor::<Test>::exists());
+		assert_eq!(Machine::on_chain_storage_version(), STORAGE_VERSION);
+		assert_eq!(Machine::issuer_machine_count(1), 3);
+		assert_eq!(
+			Machine::runtime_activities(unvisited[0]).unwrap().status,
+			MachineStatus::Online
+		);
+	})
+}
+
+/// Put storage back to version 0 with a legacy issuer 1 owned by account 3, and legacy
+/// machines of the issuer owned by account 6.
+fn insert_legacy_machines(seeds: &[u8]) -> Vec<TeaPubKey> {
+	StorageVersion::new(0).put::<Machine>();
+	unhashed::put(
+		&Issuers::<Test>::hashed_key_for(1),
+		&migrations::v0::Issuer { id: 1, owner: 3u64 },
+	);
+	IssuerOwners::<Test>::insert(3, 1);
+	seeds
+		.iter()
+		.map(|seed| {
+			let tea_id = tea_pair(*seed).public().0;
+			Machines::<Test>::insert(
+				tea_id,
+				crate::Machine {
+					tea_id,
+					issuer_id: 1,
+					owner: 6,
+				},
+			);
+			tea_id
+		})
+		.collect()
+}
+
+fn tea_pair(seed: u8) -> ed25519::Pair {
+	ed25519::Pair::from_seed(&[seed; 32])
+}
+
+/// Returns the issuer signature and the tea signature needed to register the machine.
+fn sign_machine(
+	tea: &ed25519::Pair,
+	issuer_id: IssuerId,
+	owner: u64,
+	nonce: u64,
+) -> (ed25519::Signature, ed25519::Signature) {
+	(
+		issuer_pair().sign(&Machine::issuer_signing_message(
+			issuer_id,
+			&tea.public().0,
+			&owner,
+			nonce,
+		)),
+		tea.sign(&Machine::machine_signing_message(&owner)),
+	)
+}
+
+fn register_machine(issuer_owner: u64, issuer_id: IssuerId, owner: u64, seed: u8) -> TeaPubKey {
+	let tea = tea_pair(seed);
+	let nonce = Machine::issuer_nonces(issuer_id);
+	let (issuer_signature, tea_signature) = sign_machine(&tea, issuer_id, owner, nonce);
+	assert_ok!(Machine::register_machine(
+		Origin::signed(issuer_owner),
+		tea.public().0,
+		owner,
+		issuer_id,
+		nonce,
+		issuer_signature,
+		tea_signature
+	));
+	tea.public().0
+}
+
+fn ephemeral_pair(seed: u8) -> ed25519::Pair {
+	ed25519::Pair::from_seed(&[seed.wrapping_add(128); 32])
+}
+
+/// Set up builtin machines owned by the startup owner `444`.
+fn startup_nodes(seeds: &[u8]) -> Vec<TeaPubKey> {
+	StartupOwner::<Test>::set(Some(444));
+	let tea_ids: Vec<TeaPubKey> = seeds
+		.iter()
+		.map(|seed| tea_pair(*seed).public().0)
+		.collect();
+	assert_ok!(Machine::reset_tapp_startup(
+		Origin::root(),
+		tea_ids.clone(),
+		seeds.iter().map(|seed| *seed as CmlId).collect(),
+		seeds.iter().map(|seed| vec![*seed]).collect(),
+	));
+	tea_ids
+}
+
+fn update_node_profile(owner: u64, seed: u8) {
+	let tea = tea_pair(seed);
+	let ephemeral_id = ephemeral_pair(seed).public().0;
+	let peer_id = vec![seed];
+	assert_ok!(Machine::update_node_profile(
+		Origin::signed(owner),
+		tea.public().0,
+		ephemeral_id,
+		peer_id.clone(),
+		tea.sign(&Machine::node_profile_signing_message(
+			&ephemeral_id,
+			&peer_id
+		))
+	));
+}
+
+fn attest(validator_seed: u8, target: TeaPubKey, is_pass: bool) -> DispatchResult {
+	let validator_tea_id = tea_pair(validator_seed).public().0;
+	let owner = Machines::<Test>::get(validator_tea_id).unwrap().owner;
+	let target_ephemeral_id = Nodes::<Test>::get(target).unwrap().ephemeral_id;
+	Machine::remote_attestation(
+		Origin::signed(owner),
+		validator_tea_id,
+		target,
+		is_pass,
+		ephemeral_pair(validator_seed).sign(&Machine::ra_signing_message(
+			&target,
+			&target_ephemeral_id,
+			is_pass,
+		)),
+	)
+}
+
+fn heartbeat(owner: u64, seed: u8, height: u64) -> DispatchResult {
+	let tea = tea_pair(seed);
+	Machine::update_runtime_activity(
+		Origin::signed(owner),
+		tea.public().0,
+		height,
+		tea.sign(&Machine::heartbeat_signing_message(&tea.public().0, height)),
+	)
+}
@@ -0,0 +1,21 @@
+use super::*;
+
+/// Operations about machines that used by other pallets.
+pub trait MachineOperation {
+	type AccountId: PartialEq + Clone;
+
+	/// Returns owner of the machine, `None` if the machine has not been registered.
+	fn machine_owner(tea_id: &TeaPubKey) -> Option<Self::AccountId>;
+
+	/// Returns status of the machine by heartbeats, `None` if the machine has not been registered.
+	fn machine_status(tea_id: &TeaPubKey) -> Option<MachineStatus>;
+
+	/// Returns the CML mining on the machine, `None` if the machine is free.
+	fn mining_cml(tea_id: &TeaPubKey) -> Option<CmlId>;
+
+	/// Mark the machine as occupied by the given CML, caller should make sure the machine is free.
+	fn occupy(tea_id: &TeaPubKey, cml_id: CmlId);
+
+	/// Free the machine so that it can be used to mine by other CMLs.
+	fn release(tea_id: &TeaPubKey);
+}
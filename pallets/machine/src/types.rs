@@ -1,5 +1,9 @@
 use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+	traits::Get, BoundedVec, CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound,
+};
 use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
 use sp_std::prelude::*;
 
 pub type CmlId = u64;
@@ -7,13 +11,40 @@ pub type IssuerId = u64;
 
 pub const BUILTIN_ISSURE: IssuerId = 0;
 
-#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, TypeInfo, MaxEncodedLen)]
-pub struct Issuer<Account>
+/// Context tags prefixed to signing messages, so that a signature over one kind of message can't
+/// be replayed as another kind.
+pub const ISSUER_SIGNING_CONTEXT: &[u8] = b"tea/machine/issuer";
+pub const MACHINE_SIGNING_CONTEXT: &[u8] = b"tea/machine/owner";
+pub const NODE_PROFILE_SIGNING_CONTEXT: &[u8] = b"tea/machine/node-profile";
+pub const RA_SIGNING_CONTEXT: &[u8] = b"tea/machine/ra";
+pub const HEARTBEAT_SIGNING_CONTEXT: &[u8] = b"tea/machine/heartbeat";
+
+/// Ed25519 public key of an issuer, used to verify machines signed by the issuer.
+pub type IssuerPubKey = [u8; 32];
+
+#[derive(
+	Encode,
+	Decode,
+	CloneNoBound,
+	PartialEqNoBound,
+	EqNoBound,
+	RuntimeDebugNoBound,
+	TypeInfo,
+	MaxEncodedLen,
+)]
+#[scale_info(skip_type_params(UrlLength, NameLength))]
+#[codec(mel_bound(Account: MaxEncodedLen))]
+pub struct Issuer<Account, UrlLength, NameLength>
 where
-	Account: MaxEncodedLen,
+	Account: Clone + PartialEq + Eq + core::fmt::Debug + MaxEncodedLen,
+	UrlLength: Get<u32>,
+	NameLength: Get<u32>,
 {
 	pub id: IssuerId,
 	pub owner: Account,
+	pub url: BoundedVec<u8, UrlLength>,
+	pub name: BoundedVec<u8, NameLength>,
+	pub public_key: IssuerPubKey,
 }
 
 /// Tea public key generated from the TEA secure module (Tpm, Aws Nitro etc.) used to identify
@@ -28,3 +59,59 @@ where
 	pub issuer_id: IssuerId,
 	pub owner: Account,
 }
+
+/// Peer id of the libp2p network that a TEA node listens on.
+pub type PeerId = Vec<u8>;
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum NodeStatus {
+	/// Waiting for remote attestations of the selected RA nodes.
+	Pending,
+	/// Attested by enough RA nodes, can attest other nodes.
+	Active,
+	/// Rejected by the RA nodes, the profile should be updated to be attested again.
+	Invalid,
+}
+
+impl Default for NodeStatus {
+	fn default() -> Self {
+		NodeStatus::Pending
+	}
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, TypeInfo)]
+pub struct Node<BlockNumber> {
+	pub tea_id: TeaPubKey,
+	/// Ephemeral key generated by the node at startup, used to sign attestation results.
+	pub ephemeral_id: TeaPubKey,
+	pub peer_id: PeerId,
+	pub create_time: BlockNumber,
+	pub update_time: BlockNumber,
+	/// Nodes selected to attest this node, and result of each of them if committed.
+	pub ra_nodes: Vec<(TeaPubKey, Option<bool>)>,
+	pub status: NodeStatus,
+}
+
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum MachineStatus {
+	/// Registered but no heartbeat received yet.
+	Pending,
+	/// Heartbeat received within `RuntimeActivityThreshold` blocks.
+	Online,
+	/// Missed heartbeats for more than `RuntimeActivityThreshold` blocks.
+	Offline,
+}
+
+impl Default for MachineStatus {
+	fn default() -> Self {
+		MachineStatus::Pending
+	}
+}
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, TypeInfo)]
+pub struct RuntimeActivity<BlockNumber> {
+	/// Height signed by the latest heartbeat, or the registration height if no heartbeat has
+	/// been received yet.
+	pub last_heartbeat: BlockNumber,
+	pub status: MachineStatus,
+}
@@ -1,25 +1,10 @@
 // Copyright (C) 2021 Tea Project.
 
-//! Autogenerated weights for pallet_tea
+//! Default weights for pallet_machine
 //!
-//! THIS FILE WAS AUTO-GENERATED USING THE SUBSTRATE BENCHMARK CLI VERSION 3.0.0
-//! DATE: 2021-05-28, STEPS: `[50, ]`, REPEAT: 20, LOW RANGE: `[]`, HIGH RANGE: `[]`
-//! EXECUTION: Some(Wasm), WASM-EXECUTION: Compiled, CHAIN: Some("dev"), DB CACHE: 128
-
-// Executed Command:
-// ./target/release/tea-camellia
-// benchmark
-// --chain=dev
-// --execution=wasm
-// --wasm-execution=compiled
-// --pallet=pallet_tea
-// --extrinsic=*
-// --steps=50
-// --repeat=20
-// --heap-pages=4096
-// --header=./file_header.txt
-// --output=pallets/tea/src/weights.rs
-// --template=./.maintain/frame-weight-template.hbs
+//! PLACEHOLDER VALUES, NOT BENCHMARK OUTPUT. The numbers are hand-written estimates derived from
+//! the storage accesses of each call, replace this file with the output of the `benchmarking`
+//! module run on reference hardware before relying on them.
 
 #![allow(unused_parens)]
 #![allow(unused_imports)]
@@ -30,25 +15,21 @@ use frame_support::{
 };
 use sp_std::marker::PhantomData;
 
-/// Weight functions needed for pallet_tea.
+/// Weight functions needed for pallet_machine.
 pub trait WeightInfo {
-	fn add_new_node() -> Weight;
-	fn update_node_profile() -> Weight;
+	fn update_node_profile(n: u32) -> Weight;
 	fn remote_attestation() -> Weight;
 	fn update_runtime_activity() -> Weight;
 }
 
-/// Weights for pallet_tea using the Substrate node and recommended hardware.
+/// Weights for pallet_machine using the Substrate node and recommended hardware.
 pub struct SubstrateWeight<T>(PhantomData<T>);
 impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
-	fn add_new_node() -> Weight {
-		(25_371_000 as Weight)
-			.saturating_add(T::DbWeight::get().reads(1 as Weight))
-			.saturating_add(T::DbWeight::get().writes(1 as Weight))
-	}
-	fn update_node_profile() -> Weight {
+	fn update_node_profile(n: u32) -> Weight {
 		(57_402_000 as Weight)
+			.saturating_add((1_050_000 as Weight).saturating_mul(n as Weight))
 			.saturating_add(T::DbWeight::get().reads(3 as Weight))
+			.saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
 			.saturating_add(T::DbWeight::get().writes(6 as Weight))
 	}
 	fn remote_attestation() -> Weight {
@@ -59,20 +40,17 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 	fn update_runtime_activity() -> Weight {
 		(74_440_000 as Weight)
 			.saturating_add(T::DbWeight::get().reads(1 as Weight))
-			.saturating_add(T::DbWeight::get().writes(1 as Weight))
+			.saturating_add(T::DbWeight::get().writes(2 as Weight))
 	}
 }
 
 // For backwards compatibility and tests
 impl WeightInfo for () {
-	fn add_new_node() -> Weight {
-		(25_371_000 as Weight)
-			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
-			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
-	}
-	fn update_node_profile() -> Weight {
+	fn update_node_profile(n: u32) -> Weight {
 		(57_402_000 as Weight)
+			.saturating_add((1_050_000 as Weight).saturating_mul(n as Weight))
 			.saturating_add(RocksDbWeight::get().reads(3 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
 			.saturating_add(RocksDbWeight::get().writes(6 as Weight))
 	}
 	fn remote_attestation() -> Weight {
@@ -83,6 +61,6 @@ impl WeightInfo for () {
 	fn update_runtime_activity() -> Weight {
 		(74_440_000 as Weight)
 			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
-			.saturating_add(RocksDbWeight::get().writes(1 as Weight))
+			.saturating_add(RocksDbWeight::get().writes(2 as Weight))
 	}
 }
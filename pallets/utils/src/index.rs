@@ -0,0 +1,157 @@
+//! Consistency checks of reverse indexes, i.e. owner keyed maps that mirror the owner field of a
+//! primary map keyed by item id (e.g. `CmlStore` and `UserCmlStore` in pallet-cml).
+
+use codec::{Decode, Encode};
+use frame_support::weights::{RuntimeDbWeight, Weight};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::{collections::btree_map::BTreeMap, prelude::*};
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
+pub enum IndexInconsistency<Owner, Id> {
+	/// Item of the primary map has no entry in the reverse index.
+	Missing(Owner, Id),
+	/// Reverse index entry points to an item that is not in the primary map.
+	Orphan(Owner, Id),
+	/// Reverse index entry points to an item owned by another owner in the primary map.
+	Mismatched(Owner, Id),
+}
+
+/// Compare `(id, owner)` pairs of the primary map with `(owner, id)` pairs of the reverse index,
+/// returns all disagreements ordered by id.
+pub fn check_reverse_index<Owner, Id>(
+	primary: impl IntoIterator<Item = (Id, Owner)>,
+	index: impl IntoIterator<Item = (Owner, Id)>,
+) -> Vec<IndexInconsistency<Owner, Id>>
+where
+	Owner: Ord + Clone,
+	Id: Ord + Clone,
+{
+	let owners: BTreeMap<Id, Owner> = primary.into_iter().collect();
+	let mut unindexed = owners.clone();
+
+	let mut inconsistencies = Vec::new();
+	for (owner, id) in index {
+		match owners.get(&id) {
+			None => inconsistencies.push((id.clone(), IndexInconsistency::Orphan(owner, id))),
+			Some(expected) if !expected.eq(&owner) => {
+				inconsistencies.push((id.clone(), IndexInconsistency::Mismatched(owner, id)))
+			}
+			Some(_) => {
+				unindexed.remove(&id);
+			}
+		}
+	}
+	inconsistencies.extend(
+		unindexed
+			.into_iter()
+			.map(|(id, owner)| (id.clone(), IndexInconsistency::Missing(owner, id))),
+	);
+
+	inconsistencies.sort_by(|(a, _), (b, _)| a.cmp(b));
+	inconsistencies
+		.into_iter()
+		.map(|(_, inconsistency)| inconsistency)
+		.collect()
+}
+
+/// Fix the inconsistencies by removing orphan and mismatched entries from the reverse index and
+/// then inserting the missing ones, returns count of fixed entries.
+pub fn repair_reverse_index<Owner, Id>(
+	inconsistencies: &[IndexInconsistency<Owner, Id>],
+	mut remove: impl FnMut(&Owner, &Id),
+	mut insert: impl FnMut(&Owner, &Id),
+) -> u32 {
+	// removals go first so that indexes allowing one item per owner have room for the missing
+	// entries
+	for inconsistency in inconsistencies {
+		match inconsistency {
+			IndexInconsistency::Orphan(owner, id) | IndexInconsistency::Mismatched(owner, id) => {
+				remove(owner, id)
+			}
+			IndexInconsistency::Missing(..) => {}
+		}
+	}
+	for inconsistency in inconsistencies {
+		if let IndexInconsistency::Missing(owner, id) = inconsistency {
+			insert(owner, id);
+		}
+	}
+	inconsistencies.len() as u32
+}
+
+/// Returns true if the primary map and the reverse index together hold no more than `max_items`
+/// entries, at most `max_items + 1` entries of each are visited.
+pub fn within_repair_bound<A, B>(
+	primary: impl Iterator<Item = A>,
+	index: impl Iterator<Item = B>,
+	max_items: u32,
+) -> bool {
+	let limit = (max_items as usize).saturating_add(1);
+	primary
+		.take(limit)
+		.count()
+		.saturating_add(index.take(limit).count())
+		<= max_items as usize
+}
+
+/// Weight of repairing a reverse index within `max_items`: entries are visited once by
+/// `within_repair_bound` and once by the repair, and each entry causes at most one fix.
+pub fn repair_reverse_index_weight(db: RuntimeDbWeight, max_items: u32) -> Weight {
+	let max_items = max_items as Weight;
+	db.reads_writes(max_items.saturating_mul(3).saturating_add(2), max_items)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::BTreeSet;
+
+	#[test]
+	fn check_reverse_index_works() {
+		let primary = vec![(1, 10), (2, 10), (3, 20)];
+		assert!(check_reverse_index(primary.clone(), vec![(10, 1), (10, 2), (20, 3)]).is_empty());
+
+		assert_eq!(
+			check_reverse_index(primary, vec![(10, 1), (20, 2), (30, 4)]),
+			vec![
+				IndexInconsistency::Mismatched(20, 2),
+				IndexInconsistency::Missing(10, 2),
+				IndexInconsistency::Missing(20, 3),
+				IndexInconsistency::Orphan(30, 4),
+			]
+		);
+	}
+
+	#[test]
+	fn repair_reverse_index_works() {
+		let primary = vec![(1, 10), (2, 10), (3, 20)];
+		let mut index: BTreeSet<(u32, u32)> = vec![(10, 1), (20, 2), (30, 4)].into_iter().collect();
+
+		let inconsistencies = check_reverse_index(primary.clone(), index.clone());
+		let mut removed = Vec::new();
+		let mut inserted = Vec::new();
+		let fixed = repair_reverse_index(
+			&inconsistencies,
+			|owner, id| removed.push((*owner, *id)),
+			|owner, id| inserted.push((*owner, *id)),
+		);
+		assert_eq!(fixed, 4);
+		assert_eq!(removed, vec![(20, 2), (30, 4)]);
+		assert_eq!(inserted, vec![(10, 2), (20, 3)]);
+
+		for entry in removed {
+			index.remove(&entry);
+		}
+		index.extend(inserted);
+		assert!(check_reverse_index(primary, index).is_empty());
+	}
+
+	#[test]
+	fn within_repair_bound_works() {
+		assert!(within_repair_bound(0..3, 0..2, 5));
+		assert!(!within_repair_bound(0..3, 0..3, 5));
+		assert!(!within_repair_bound(0.., 0..0, 5));
+		assert!(within_repair_bound(0..0, 0..0, 0));
+	}
+}
@@ -1,5 +1,9 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub use index::{
+	check_reverse_index, repair_reverse_index, repair_reverse_index_weight, within_repair_bound,
+	IndexInconsistency,
+};
 pub use traits::{CommonUtils, CurrencyOperations};
 /// Edit this file to define custom logic or remove it if it is not needed.
 /// Learn more about FRAME and the core library of Substrate FRAME pallets:
@@ -13,6 +17,7 @@ mod mock;
 mod tests;
 
 mod functions;
+mod index;
 pub mod traits;
 mod types;
 use frame_support::{
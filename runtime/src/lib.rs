@@ -138,10 +138,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 115,
+	spec_version: 120,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 2,
+	transaction_version: 3,
 	state_version: 1,
 };
 
@@ -896,6 +896,14 @@ parameter_types! {
 	pub const IpAddressLength: u32 = 128;
 	pub const StartupMachineBindingsLength: u32 = 100;
 	pub const StartupTappBindingsLength: u32 = 100;
+	pub const IssuerUrlLength: u32 = 256;
+	pub const IssuerNameLength: u32 = 64;
+	pub const MaxActiveNodes: u32 = 500;
+	pub const RaQuorumSize: u32 = 5;
+	pub const RaThreshold: u32 = 3;
+	pub const RuntimeActivityThreshold: BlockNumber = 30 * MINUTES;
+	pub const ActivityCheckDuration: BlockNumber = 10 * MINUTES;
+	pub const RaReselectCooldown: BlockNumber = 30 * MINUTES;
 }
 
 impl pallet_machine::Config for Runtime {
@@ -907,6 +915,30 @@ impl pallet_machine::Config for Runtime {
 	type IpAddressLength = IpAddressLength;
 	type StartupMachineBindingsLength = StartupMachineBindingsLength;
 	type StartupTappBindingsLength = StartupTappBindingsLength;
+	type IssuerUrlLength = IssuerUrlLength;
+	type IssuerNameLength = IssuerNameLength;
+	type MaxActiveNodes = MaxActiveNodes;
+	type RaQuorumSize = RaQuorumSize;
+	type RaThreshold = RaThreshold;
+	type RuntimeActivityThreshold = RuntimeActivityThreshold;
+	type ActivityCheckDuration = ActivityCheckDuration;
+	type RaReselectCooldown = RaReselectCooldown;
+	type WeightInfo = pallet_machine::weights::SubstrateWeight<Runtime>;
+}
+
+parameter_types! {
+	pub const StakingPeriodLength: BlockNumber = HOURS;
+	pub const SeedFreshDuration: BlockNumber = 7 * DAYS;
+	pub const MiningRewardPool: Balance = 1000 * DOLLARS;
+	pub const PerformanceDecay: bool = true;
+	pub const StakingSlotsMaxLength: u32 = 1024;
+	pub const DefaultStakingSlots: u32 = 512;
+	pub const StakingPrice: Balance = 1000 * DOLLARS;
+	pub const UnstakeCooldown: BlockNumber = DAYS;
+	pub const MaxBatchLength: u32 = 100;
+	pub const LuckyDrawBoxMaxLength: u32 = 10_000;
+	pub const MaxSweepsPerBlock: u32 = 500;
+	pub const MaxRewardsPerBlock: u32 = 500;
 }
 
 impl pallet_cml::Config for Runtime {
@@ -914,6 +946,20 @@ impl pallet_cml::Config for Runtime {
 	type Currency = Balances;
 	type CurrencyOperations = Utils;
 	type CommonUtils = Utils;
+	type StakingPeriodLength = StakingPeriodLength;
+	type SeedFreshDuration = SeedFreshDuration;
+	type MaxSweepsPerBlock = MaxSweepsPerBlock;
+	type MaxRewardsPerBlock = MaxRewardsPerBlock;
+	type MachineOperation = Machine;
+	type MiningRewardPool = MiningRewardPool;
+	type PerformanceDecay = PerformanceDecay;
+	type StakingSlotsMaxLength = StakingSlotsMaxLength;
+	type DefaultStakingSlots = DefaultStakingSlots;
+	type StakingPrice = StakingPrice;
+	type UnstakeCooldown = UnstakeCooldown;
+	type MaxBatchLength = MaxBatchLength;
+	type LuckyDrawBoxMaxLength = LuckyDrawBoxMaxLength;
+	type WeightInfo = pallet_cml::weights::SubstrateWeight<Runtime>;
 }
 
 impl pallet_tea_erc20::Config for Runtime {
@@ -1147,16 +1193,72 @@ impl_runtime_apis! {
 		fn user_cml_list(who: AccountId) -> Vec<u64> {
 			Cml::user_cml_list(who)
 		}
+
+		fn user_leased_cml_list(who: AccountId) -> Vec<u64> {
+			Cml::user_leased_cml_list(who)
+		}
+
+		fn user_cml_defrost_heights(who: AccountId) -> Vec<(u64, Option<BlockNumber>)> {
+			Cml::user_cml_defrost_heights(who)
+		}
+
+		fn user_expiring_seeds(who: AccountId, within: BlockNumber) -> Vec<(u64, BlockNumber)> {
+			Cml::user_expiring_seeds(who, within)
+		}
+
+		fn cml_info(cml_id: u64) -> Option<cml_runtime_api::CmlInfo<AccountId, BlockNumber>> {
+			Cml::cml_info(cml_id)
+		}
+
+		fn cml_list(
+			filter: cml_runtime_api::CmlFilter<AccountId>,
+			page: u32,
+			page_size: u32,
+		) -> Vec<cml_runtime_api::CmlInfo<AccountId, BlockNumber>> {
+			Cml::cml_list(filter, page, page_size)
+		}
+
+		fn cml_stats() -> cml_runtime_api::CmlStats {
+			Cml::cml_stats()
+		}
+
+		fn trait_effects() -> Vec<(cml_runtime_api::CmlTrait, cml_runtime_api::TraitEffect)> {
+			Cml::trait_effects()
+		}
+
+		fn open_listings() -> Vec<cml_runtime_api::ListingInfo<AccountId, Balance, BlockNumber>> {
+			Cml::open_listings()
+		}
+
+		fn user_bids(who: AccountId) -> Vec<(u64, Balance)> {
+			Cml::user_bids(who)
+		}
+
+		fn cml_index_inconsistencies() -> Vec<cml_runtime_api::IndexInconsistency<AccountId, u64>> {
+			Cml::cml_index_inconsistencies()
+		}
 	}
 
 	impl machine_runtime_api::MachineApi<Block, AccountId> for Runtime {
-		fn boot_nodes() -> Vec<[u8; 32]> {
+		fn boot_nodes() -> Vec<([u8; 32], u64, Vec<u8>)> {
 			Machine::list_boot_nodes()
 		}
 
-		fn tapp_store_startup_nodes() -> Vec<[u8; 32]> {
+		fn tapp_store_startup_nodes() -> Vec<([u8; 32], u64, Vec<u8>)> {
 			Machine::list_tapp_store_startup_nodes()
 		}
+
+		fn node_status(tea_id: [u8; 32]) -> Option<machine_runtime_api::NodeStatus> {
+			Machine::node_status(&tea_id)
+		}
+
+		fn active_nodes() -> Vec<[u8; 32]> {
+			Machine::active_nodes().into_inner()
+		}
+
+		fn machines_by_status(status: machine_runtime_api::MachineStatus) -> Vec<[u8; 32]> {
+			Machine::machines_by_status(status)
+		}
 	}
 
 	impl fg_primitives::GrandpaApi<Block> for Runtime {